@@ -0,0 +1,384 @@
+//! Discovery of what the installed ffmpeg build supports.
+//!
+//! Distribution builds differ widely: `libx265`, `libvpx-vp9` or `libfdk_aac`
+//! may be missing, and filters appear and disappear between versions.
+//! [`FfmpegCapabilities`] parses ffmpeg's own listings so callers can check
+//! before spawning a job that would fail halfway through.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::command::{FfmpegBinaryPaths, FfmpegCommand};
+use crate::config::FfmpegLocator;
+use crate::error::{Error, Result};
+use crate::runner::CommandRunner;
+use crate::types::CodecType;
+
+/// Semantic version of an ffmpeg release build.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FfmpegVersion {
+    /// Major version.
+    pub major: u32,
+    /// Minor version.
+    pub minor: u32,
+    /// Patch version (0 when not reported).
+    pub patch: u32,
+}
+
+impl FfmpegVersion {
+    /// Create a version from its components.
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for FfmpegVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for FfmpegVersion {
+    type Err = Error;
+
+    /// Parse strings such as `6.1.1`, `n7.0`, or `4.4.2-0ubuntu0.22.04.1`.
+    fn from_str(raw: &str) -> Result<Self> {
+        let invalid = || Error::Parse(format!("unrecognized ffmpeg version: {raw}"));
+        let trimmed = raw.strip_prefix('n').unwrap_or(raw);
+        let end = trimmed
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(trimmed.len());
+        let mut parts = trimmed[..end].split('.').filter(|part| !part.is_empty());
+        let major = parts
+            .next()
+            .and_then(|part| part.parse().ok())
+            .ok_or_else(invalid)?;
+        let minor = parts.next().and_then(|part| part.parse().ok()).unwrap_or(0);
+        let patch = parts.next().and_then(|part| part.parse().ok()).unwrap_or(0);
+        Ok(Self::new(major, minor, patch))
+    }
+}
+
+/// Everything the installed ffmpeg reports it can do.
+#[derive(Clone, Debug, Default)]
+pub struct FfmpegCapabilities {
+    version_string: String,
+    version: Option<FfmpegVersion>,
+    configuration: Vec<String>,
+    encoders: BTreeSet<String>,
+    decoders: BTreeSet<String>,
+    muxers: BTreeSet<String>,
+    demuxers: BTreeSet<String>,
+    filters: BTreeSet<String>,
+    pix_fmts: BTreeSet<String>,
+    input_protocols: BTreeSet<String>,
+    output_protocols: BTreeSet<String>,
+}
+
+impl FfmpegCapabilities {
+    /// Query the locator's ffmpeg binary (uncached; prefer [`FfmpegLocator::capabilities`]).
+    pub fn detect(locator: &FfmpegLocator) -> Result<Self> {
+        Self::detect_with_runner(locator.binaries(), locator.runner())
+    }
+
+    pub(crate) fn detect_with_runner(
+        binaries: &FfmpegBinaryPaths,
+        runner: &Arc<dyn CommandRunner>,
+    ) -> Result<Self> {
+        let query = |flag: &str| -> Result<String> {
+            let mut cmd = FfmpegCommand::new(binaries.ffmpeg());
+            cmd.runner(Arc::clone(runner)).arg("-hide_banner").arg(flag);
+            let output = cmd.run_with_output()?;
+            if !output.status.success() {
+                return Err(Error::command_failed(
+                    &binaries.ffmpeg().to_string_lossy(),
+                    output.status.code(),
+                    &output.stderr,
+                ));
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        };
+
+        let mut caps = Self::default();
+        caps.apply_version(&query("-version")?);
+        caps.encoders = parse_codec_list(&query("-encoders")?);
+        caps.decoders = parse_codec_list(&query("-decoders")?);
+        caps.muxers = parse_format_list(&query("-muxers")?);
+        caps.demuxers = parse_format_list(&query("-demuxers")?);
+        caps.filters = parse_filter_list(&query("-filters")?);
+        caps.pix_fmts = parse_codec_list(&query("-pix_fmts")?);
+        let (input, output) = parse_protocols(&query("-protocols")?);
+        caps.input_protocols = input;
+        caps.output_protocols = output;
+        Ok(caps)
+    }
+
+    fn apply_version(&mut self, text: &str) {
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("ffmpeg version ") {
+                let raw = rest.split_whitespace().next().unwrap_or_default();
+                self.version_string = raw.to_string();
+                self.version = raw.parse().ok();
+            } else if let Some(rest) = line.trim().strip_prefix("configuration:") {
+                self.configuration = rest.split_whitespace().map(str::to_string).collect();
+            }
+        }
+    }
+
+    /// Raw version string as printed by ffmpeg (e.g. `6.1.1-3ubuntu5`).
+    pub fn version_string(&self) -> &str {
+        &self.version_string
+    }
+
+    /// Parsed release version; `None` for git snapshot builds such as `N-112345-g…`.
+    pub fn version(&self) -> Option<FfmpegVersion> {
+        self.version
+    }
+
+    /// Flags ffmpeg was configured with (e.g. `--enable-libx264`).
+    pub fn configuration(&self) -> &[String] {
+        &self.configuration
+    }
+
+    /// Whether ffmpeg was configured with the given flag.
+    pub fn has_configure_flag(&self, flag: &str) -> bool {
+        self.configuration.iter().any(|candidate| candidate == flag)
+    }
+
+    /// Whether an encoder with this name is available (e.g. `libx265`).
+    pub fn supports_encoder(&self, name: &str) -> bool {
+        self.encoders.contains(name)
+    }
+
+    /// Whether a decoder with this name is available.
+    pub fn supports_decoder(&self, name: &str) -> bool {
+        self.decoders.contains(name)
+    }
+
+    /// Whether the encoder [`CodecType::as_str`] maps to is available.
+    ///
+    /// [`CodecType::Copy`] is always supported.
+    pub fn supports_codec(&self, codec: &CodecType) -> bool {
+        matches!(codec, CodecType::Copy) || self.supports_encoder(codec.as_str())
+    }
+
+    /// Whether a muxer (output format) with this name is available.
+    pub fn supports_muxer(&self, name: &str) -> bool {
+        self.muxers.contains(name)
+    }
+
+    /// Whether a demuxer (input format) with this name is available.
+    pub fn supports_demuxer(&self, name: &str) -> bool {
+        self.demuxers.contains(name)
+    }
+
+    /// Whether a filter with this name is available (e.g. `scale`).
+    pub fn supports_filter(&self, name: &str) -> bool {
+        self.filters.contains(name)
+    }
+
+    /// Whether a pixel format with this name is known (e.g. `yuv420p10le`).
+    pub fn supports_pix_fmt(&self, name: &str) -> bool {
+        self.pix_fmts.contains(name)
+    }
+
+    /// Whether a protocol can be used for input (e.g. `https`).
+    pub fn supports_input_protocol(&self, name: &str) -> bool {
+        self.input_protocols.contains(name)
+    }
+
+    /// Whether a protocol can be used for output.
+    pub fn supports_output_protocol(&self, name: &str) -> bool {
+        self.output_protocols.contains(name)
+    }
+
+    /// All encoder names.
+    pub fn encoders(&self) -> impl Iterator<Item = &str> {
+        self.encoders.iter().map(String::as_str)
+    }
+
+    /// All decoder names.
+    pub fn decoders(&self) -> impl Iterator<Item = &str> {
+        self.decoders.iter().map(String::as_str)
+    }
+
+    /// All muxer names.
+    pub fn muxers(&self) -> impl Iterator<Item = &str> {
+        self.muxers.iter().map(String::as_str)
+    }
+
+    /// All demuxer names.
+    pub fn demuxers(&self) -> impl Iterator<Item = &str> {
+        self.demuxers.iter().map(String::as_str)
+    }
+
+    /// All filter names.
+    pub fn filters(&self) -> impl Iterator<Item = &str> {
+        self.filters.iter().map(String::as_str)
+    }
+}
+
+/// Entries listed after the dashed separator of `-encoders`, `-decoders`
+/// and `-pix_fmts`: `<flags> <name> <description…>`.
+fn parse_codec_list(text: &str) -> BTreeSet<String> {
+    entries_after_separator(text)
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Entries of `-muxers`/`-demuxers`; one line may name several formats
+/// (`matroska,webm`).
+fn parse_format_list(text: &str) -> BTreeSet<String> {
+    entries_after_separator(text)
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .flat_map(|names| names.split(','))
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// `-filters` has no separator line; entries are recognised by their
+/// `A->V` style pad description.
+fn parse_filter_list(text: &str) -> BTreeSet<String> {
+    text.lines()
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            let _flags = tokens.next()?;
+            let name = tokens.next()?;
+            let io = tokens.next()?;
+            io.contains("->").then(|| name.to_string())
+        })
+        .collect()
+}
+
+fn parse_protocols(text: &str) -> (BTreeSet<String>, BTreeSet<String>) {
+    let mut input = BTreeSet::new();
+    let mut output = BTreeSet::new();
+    let mut section = None;
+    for line in text.lines() {
+        match line.trim() {
+            "Input:" => section = Some(&mut input),
+            "Output:" => section = Some(&mut output),
+            "" => {}
+            name => {
+                if let Some(set) = section.as_deref_mut() {
+                    set.insert(name.to_string());
+                }
+            }
+        }
+    }
+    (input, output)
+}
+
+fn entries_after_separator(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .skip_while(|line| {
+            let line = line.trim();
+            line.is_empty() || !line.chars().all(|c| c == '-')
+        })
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_parsing() {
+        assert_eq!(
+            "6.1.1".parse::<FfmpegVersion>().unwrap(),
+            FfmpegVersion::new(6, 1, 1)
+        );
+        assert_eq!(
+            "n7.0".parse::<FfmpegVersion>().unwrap(),
+            FfmpegVersion::new(7, 0, 0)
+        );
+        assert_eq!(
+            "4.4.2-0ubuntu0.22.04.1".parse::<FfmpegVersion>().unwrap(),
+            FfmpegVersion::new(4, 4, 2)
+        );
+        assert!("N-112345-gabcdef".parse::<FfmpegVersion>().is_err());
+    }
+
+    #[test]
+    fn version_and_configuration() {
+        let mut caps = FfmpegCapabilities::default();
+        caps.apply_version(
+            "ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023 the FFmpeg developers\n\
+             built with gcc 13 (Ubuntu 13.2.0-23ubuntu3)\n\
+             configuration: --prefix=/usr --enable-gpl --enable-libx264\n\
+             libavutil      58. 29.100 / 58. 29.100\n",
+        );
+        assert_eq!(caps.version_string(), "6.1.1-3ubuntu5");
+        assert_eq!(caps.version(), Some(FfmpegVersion::new(6, 1, 1)));
+        assert!(caps.has_configure_flag("--enable-libx264"));
+        assert!(!caps.has_configure_flag("--enable-libx265"));
+    }
+
+    #[test]
+    fn codec_listing() {
+        let encoders = parse_codec_list(
+            "Encoders:\n \
+             V..... = Video\n \
+             A..... = Audio\n \
+             ------\n \
+             V....D a64multi             Multicolor charset for Commodore 64 (codec a64_multi)\n \
+             V..... libx264              libx264 H.264 / AVC / MPEG-4 AVC (codec h264)\n \
+             A....D aac                  AAC (Advanced Audio Coding)\n",
+        );
+        assert_eq!(
+            encoders.into_iter().collect::<Vec<_>>(),
+            ["a64multi", "aac", "libx264"]
+        );
+    }
+
+    #[test]
+    fn format_listing_splits_aliases() {
+        let demuxers = parse_format_list(
+            "File formats:\n \
+             D. = Demuxing supported\n \
+             .E = Muxing supported\n \
+             --\n \
+             D  matroska,webm    Matroska / WebM\n \
+             D  mov,mp4,m4a,3gp,3g2,mj2 QuickTime / MOV\n",
+        );
+        assert!(demuxers.contains("webm"));
+        assert!(demuxers.contains("mp4"));
+        assert!(!demuxers.contains("--"));
+    }
+
+    #[test]
+    fn filter_listing() {
+        let filters = parse_filter_list(
+            "Filters:\n  \
+             T.. = Timeline support\n  \
+             A = Audio input/output\n  \
+             | = Source or sink filter\n \
+             ... abench            A->A       Benchmark part of a filtergraph.\n \
+             TSC scale             V->V       Scale the input video size.\n \
+             ... split             V->N       Pass on the input to N video outputs.\n",
+        );
+        assert_eq!(
+            filters.into_iter().collect::<Vec<_>>(),
+            ["abench", "scale", "split"]
+        );
+    }
+
+    #[test]
+    fn protocol_listing() {
+        let (input, output) = parse_protocols(
+            "Supported file protocols:\nInput:\n  file\n  https\nOutput:\n  file\n",
+        );
+        assert!(input.contains("https"));
+        assert!(!output.contains("https"));
+        assert!(output.contains("file"));
+    }
+}
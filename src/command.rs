@@ -1,15 +1,30 @@
 //! Low-level helpers for invoking the `ffmpeg` and `ffprobe` binaries.
 
 use std::ffi::{OsStr, OsString};
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Output, Stdio};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Output, Stdio};
+#[cfg(feature = "tokio")]
+use std::sync::Mutex;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[cfg(feature = "tokio")]
-use tokio::process::Command as TokioCommand;
+use tokio::io::{AsyncBufReadExt, BufReader as TokioBufReader, Lines};
+#[cfg(feature = "tokio")]
+use tokio::process::{
+    Child as TokioChild, ChildStdout as TokioChildStdout, Command as TokioCommand,
+};
+#[cfg(feature = "tokio")]
+use tokio::task::JoinHandle;
 
 use which::which;
 
 use crate::error::{Error, Result};
+use crate::logging::{self, LogCapture};
+use crate::progress::{Progress, ProgressParser};
+use crate::runner::{default_runner, CommandRunner, Invocation, StdoutMode};
 
 /// Paths to ffmpeg/ffprobe binaries used by the crate.
 #[derive(Debug, Clone)]
@@ -58,6 +73,10 @@ impl FfmpegBinaryPaths {
 pub struct FfmpegCommand {
     binary: PathBuf,
     args: Vec<OsString>,
+    duration_hint: Option<Duration>,
+    timeout: Option<Duration>,
+    log: LogCapture,
+    runner: Arc<dyn CommandRunner>,
 }
 
 impl FfmpegCommand {
@@ -66,6 +85,10 @@ impl FfmpegCommand {
         Self {
             binary: binary.into(),
             args: Vec::new(),
+            duration_hint: None,
+            timeout: None,
+            log: LogCapture::Disabled,
+            runner: default_runner(),
         }
     }
 
@@ -81,64 +104,628 @@ impl FfmpegCommand {
         self
     }
 
-    fn spawn_command(&self) -> Command {
-        let mut cmd = Command::new(&self.binary);
-        cmd.args(&self.args)
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::piped());
-        cmd
+    /// Expected media duration, used to compute progress percent and ETA.
+    pub fn duration_hint(&mut self, duration: Duration) -> &mut Self {
+        self.duration_hint = Some(duration);
+        self
     }
 
-    #[cfg(feature = "tokio")]
-    fn spawn_async_command(&self) -> TokioCommand {
-        let mut cmd = TokioCommand::new(&self.binary);
-        cmd.args(&self.args)
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::piped());
-        cmd
+    /// Kill the process and fail with [`Error::TimeoutError`] if it runs longer than `timeout`.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Execute through `runner` instead of spawning processes directly.
+    pub fn runner(&mut self, runner: Arc<dyn CommandRunner>) -> &mut Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Keep the complete stderr log in a file or attach it to errors.
+    pub fn log_capture(&mut self, capture: LogCapture) -> &mut Self {
+        self.log = capture;
+        self
+    }
+
+    /// Resolve the invocation handed to the runner.
+    pub fn invocation(&self, stdout: StdoutMode) -> Invocation {
+        Invocation {
+            binary: self.binary.clone(),
+            args: self.args.clone(),
+            timeout: self.timeout,
+            stdout,
+        }
+    }
+
+    /// Render the command as a copy-pasteable POSIX shell line.
+    ///
+    /// ```rust
+    /// use ffmpeg_light::command::FfmpegCommand;
+    ///
+    /// let mut cmd = FfmpegCommand::new("ffmpeg");
+    /// cmd.arg("-i").arg("my clip.mov").arg("out.mp4");
+    /// assert_eq!(cmd.to_shell_string(), "ffmpeg -i 'my clip.mov' out.mp4");
+    /// ```
+    pub fn to_shell_string(&self) -> String {
+        self.invocation(StdoutMode::Inherit).to_shell_string()
+    }
+
+    /// Spawn the command without waiting for it, returning a handle to control it.
+    ///
+    /// stdout is inherited; stdin is kept open so the job can be asked to quit.
+    /// Logging arguments are added as in [`run`](Self::run).
+    /// The job is a real process, so a custom runner is rejected with
+    /// [`Error::Unsupported`]. The configured timeout and [`LogCapture`] apply
+    /// to [`RunningJob::wait`] and [`RunningJob::wait_timeout`].
+    pub fn spawn(&self) -> Result<RunningJob> {
+        self.check_process_runner("spawn()")?;
+        let mut cmd = Command::new(&self.binary);
+        cmd.args(&self.run_invocation(StdoutMode::Inherit).args);
+        let mut job = RunningJob::spawn(cmd, &self.binary, StdoutMode::Inherit)?;
+        job.log = self.log.clone();
+        job.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        Ok(job)
     }
 
     /// Run the command and inherit stdout.
+    ///
+    /// ffmpeg runs with `-loglevel level+info` so stderr lines carry `[level]` tags.
+    /// A `-loglevel`/`-v` passed through [`arg`](Self::arg) keeps its level but
+    /// always gains the `level` flag, e.g. `-v error` runs as `-v level+error`.
     pub fn run(&self) -> Result<()> {
-        let output = self.run_with_output()?;
-        if !output.status.success() {
-            return Err(Error::command_failed(
-                display_path(&self.binary),
-                output.status.code(),
-                &output.stderr,
-            ));
-        }
-        Ok(())
+        let output = self.execute(StdoutMode::Inherit, &mut |_| {})?;
+        complete(&self.binary, &self.log, output).map(|_| ())
     }
 
     /// Run the command and capture stdout/stderr.
+    ///
+    /// The exit status is not checked; a [`LogCapture::File`] is still written.
     pub fn run_with_output(&self) -> Result<Output> {
-        let mut cmd = self.spawn_command();
-        let output = cmd.output()?;
+        let output = self.execute(StdoutMode::Capture, &mut |_| {})?;
+        self.log.apply(&output, Ok(()))?;
         Ok(output)
     }
 
-    /// Run the command asynchronously (requires the `tokio` feature).
+    /// Run the command, invoking `callback` for every `-progress` report.
+    ///
+    /// stdout is reserved for progress output while the command runs.
+    pub fn run_with_progress<F>(&self, mut callback: F) -> Result<()>
+    where
+        F: FnMut(&Progress),
+    {
+        let mut parser = ProgressParser::new(self.duration_hint);
+        let output = self.execute(StdoutMode::Lines, &mut |line| {
+            if let Some(progress) = parser.push_line(line) {
+                callback(&progress);
+            }
+        })?;
+        complete(&self.binary, &self.log, output).map(|_| ())
+    }
+
+    /// Run the command asynchronously and inherit stdout (requires the `tokio` feature).
     #[cfg(feature = "tokio")]
     pub async fn run_async(&self) -> Result<()> {
-        let output = self.run_with_output_async().await?;
-        if !output.status.success() {
-            return Err(Error::command_failed(
-                display_path(&self.binary),
-                output.status.code(),
-                &output.stderr,
-            ));
-        }
-        Ok(())
+        let output = self.execute_async(StdoutMode::Inherit, &mut |_| {}).await?;
+        complete(&self.binary, &self.log, output).map(|_| ())
+    }
+
+    /// Async variant of [`run_with_progress`](Self::run_with_progress) (requires `tokio`).
+    ///
+    /// Dropping the future kills the child process.
+    #[cfg(feature = "tokio")]
+    pub async fn run_with_progress_async<F>(&self, mut callback: F) -> Result<()>
+    where
+        F: FnMut(&Progress) + Send,
+    {
+        let mut parser = ProgressParser::new(self.duration_hint);
+        let mut on_line = |line: &str| {
+            if let Some(progress) = parser.push_line(line) {
+                callback(&progress);
+            }
+        };
+        let output = self.execute_async(StdoutMode::Lines, &mut on_line).await?;
+        complete(&self.binary, &self.log, output).map(|_| ())
     }
 
     /// Run the command asynchronously and capture stdout/stderr (requires `tokio`).
+    ///
+    /// Dropping the future kills the child process.
     #[cfg(feature = "tokio")]
     pub async fn run_with_output_async(&self) -> Result<Output> {
-        let mut cmd = self.spawn_async_command();
-        let output = cmd.output().await?;
+        let output = self.execute_async(StdoutMode::Capture, &mut |_| {}).await?;
+        self.log.apply(&output, Ok(()))?;
         Ok(output)
     }
+
+    /// Invocation handed to the runner when executing: [`invocation`](Self::invocation)
+    /// plus `-loglevel level+info`, and the `-progress` arguments in line mode.
+    fn run_invocation(&self, stdout: StdoutMode) -> Invocation {
+        let mut invocation = self.invocation(stdout);
+        let has_loglevel = tag_loglevel_args(&mut invocation.args);
+        let mut prefix: Vec<OsString> = Vec::new();
+        if stdout == StdoutMode::Lines {
+            prefix.extend(PROGRESS_ARGS.iter().map(OsString::from));
+        }
+        if !has_loglevel {
+            prefix.extend(LOG_ARGS.iter().map(OsString::from));
+        }
+        invocation.args.splice(0..0, prefix);
+        invocation
+    }
+
+    /// Fail if `method`, which starts the process itself, would bypass the runner.
+    fn check_process_runner(&self, method: &str) -> Result<()> {
+        if self.runner.spawns_processes() {
+            return Ok(());
+        }
+        Err(Error::Unsupported(format!(
+            "{method} starts ffmpeg directly and cannot go through a custom runner"
+        )))
+    }
+
+    fn execute(&self, stdout: StdoutMode, on_line: &mut dyn FnMut(&str)) -> Result<Output> {
+        self.runner
+            .run(&self.run_invocation(stdout), on_line)
+            .map_err(|err| self.log.apply_to_error(err))
+    }
+
+    #[cfg(feature = "tokio")]
+    async fn execute_async(
+        &self,
+        stdout: StdoutMode,
+        on_line: &mut (dyn FnMut(&str) + Send),
+    ) -> Result<Output> {
+        let invocation = self.run_invocation(stdout);
+        self.runner
+            .run_async(&invocation, on_line)
+            .await
+            .map_err(|err| self.log.apply_to_error(err))
+    }
+
+    /// Spawn the command and stream its `-progress` reports (requires `tokio`).
+    ///
+    /// Like [`spawn`](Self::spawn), this starts a real process and rejects a
+    /// custom runner.
+    #[cfg(feature = "tokio")]
+    pub fn progress_stream(&self) -> Result<ProgressStream> {
+        self.check_process_runner("progress_stream()")?;
+        let mut cmd = TokioCommand::new(&self.binary);
+        cmd.args(&self.run_invocation(StdoutMode::Lines).args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let mut child = cmd.spawn()?;
+
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let stderr_log = Arc::new(Mutex::new(Vec::new()));
+        let stderr_reader = logging::spawn_stderr_reader(stderr, Arc::clone(&stderr_log));
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        Ok(ProgressStream {
+            binary: self.binary.clone(),
+            child,
+            lines: TokioBufReader::new(stdout).lines(),
+            parser: ProgressParser::new(self.duration_hint),
+            stderr_reader,
+            stderr_log,
+            log: self.log.clone(),
+            deadline: self
+                .timeout
+                .map(|timeout| tokio::time::Instant::now() + timeout),
+            timed_out: false,
+        })
+    }
+}
+
+/// Arguments prepended to route machine-readable progress to stdout.
+const PROGRESS_ARGS: [&str; 3] = ["-progress", "pipe:1", "-nostats"];
+
+/// Arguments prepended so every stderr line carries its `[level]` tag.
+const LOG_ARGS: [&str; 2] = ["-loglevel", "level+info"];
+
+/// Add the `level` flag to every `-loglevel`/`-v` value in `args`.
+///
+/// Returns whether any such option was found. Log parsing relies on the
+/// `[level]` tags, so an explicit `-level` flag is dropped as well.
+fn tag_loglevel_args(args: &mut [OsString]) -> bool {
+    let mut found = false;
+    let mut iter = args.iter_mut();
+    while let Some(arg) = iter.next() {
+        if arg != "-loglevel" && arg != "-v" {
+            continue;
+        }
+        if let Some(value) = iter.next() {
+            *value = with_level_flag(&value.to_string_lossy()).into();
+            found = true;
+        }
+    }
+    found
+}
+
+fn with_level_flag(value: &str) -> String {
+    let value = value.replace("-level", "");
+    if value.starts_with("level") || value.contains("+level") {
+        value
+    } else if value.starts_with('+') || value.starts_with('-') {
+        format!("+level{value}")
+    } else {
+        format!("level+{value}")
+    }
+}
+
+/// How long [`RunningJob::cancel`] waits between escalation steps.
+const CANCEL_GRACE: Duration = Duration::from_secs(2);
+
+/// Interval used when polling a child process for exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Debug)]
+enum StdoutReader {
+    Capture(thread::JoinHandle<io::Result<Vec<u8>>>),
+    Lines(mpsc::Receiver<io::Result<String>>),
+}
+
+/// Handle to a spawned ffmpeg/ffprobe process.
+///
+/// Dropping the handle before the process exits kills it.
+#[derive(Debug)]
+pub struct RunningJob {
+    binary: PathBuf,
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: Option<StdoutReader>,
+    stderr: Option<thread::JoinHandle<io::Result<Vec<u8>>>>,
+    exited: bool,
+    log: LogCapture,
+    deadline: Option<Instant>,
+}
+
+impl RunningJob {
+    pub(crate) fn spawn(mut cmd: Command, binary: &Path, stdout: StdoutMode) -> Result<Self> {
+        cmd.stdin(Stdio::piped()).stderr(Stdio::piped());
+        cmd.stdout(match stdout {
+            StdoutMode::Inherit => Stdio::inherit(),
+            StdoutMode::Capture | StdoutMode::Lines => Stdio::piped(),
+        });
+        let mut child = cmd.spawn()?;
+
+        let stderr = child
+            .stderr
+            .take()
+            .map(|pipe| thread::spawn(logging::in_current_span(|| logging::read_stderr(pipe))));
+        let stdout = child.stdout.take().map(|pipe| match stdout {
+            StdoutMode::Lines => {
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || {
+                    for line in BufReader::new(pipe).lines() {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                });
+                StdoutReader::Lines(receiver)
+            }
+            _ => {
+                let mut pipe = pipe;
+                StdoutReader::Capture(thread::spawn(move || {
+                    let mut buf = Vec::new();
+                    pipe.read_to_end(&mut buf).map(|_| buf)
+                }))
+            }
+        });
+
+        Ok(Self {
+            binary: binary.to_path_buf(),
+            stdin: child.stdin.take(),
+            child,
+            stdout,
+            stderr,
+            exited: false,
+            log: LogCapture::Disabled,
+            deadline: None,
+        })
+    }
+
+    /// OS process identifier.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Check whether the process has exited without blocking.
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        let status = self.child.try_wait()?;
+        self.exited |= status.is_some();
+        Ok(status)
+    }
+
+    /// Wait for the process to exit, failing if it exits unsuccessfully.
+    ///
+    /// A timeout configured on the command cancels the job once it passes.
+    pub fn wait(self) -> Result<()> {
+        let deadline = self.deadline;
+        self.wait_until(deadline)
+    }
+
+    /// Like [`wait`](Self::wait), but cancels the job and returns
+    /// [`Error::TimeoutError`] once `timeout` elapses.
+    pub fn wait_timeout(self, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let deadline = self
+            .deadline
+            .map_or(deadline, |configured| configured.min(deadline));
+        self.wait_until(Some(deadline))
+    }
+
+    fn wait_until(self, deadline: Option<Instant>) -> Result<()> {
+        let (binary, log) = (self.binary.clone(), self.log.clone());
+        let output = self
+            .finish(deadline, &mut |_| {})
+            .map_err(|err| log.apply_to_error(err))?;
+        complete(&binary, &log, output).map(|_| ())
+    }
+
+    /// Stop the process, escalating until it exits.
+    ///
+    /// ffmpeg is first asked to quit by sending `q` on stdin, which lets it
+    /// finalize the output. If it is still running after a grace period it
+    /// receives `SIGTERM` (Unix only), and finally `SIGKILL`.
+    pub fn cancel(&mut self) -> Result<()> {
+        if self.exited {
+            return Ok(());
+        }
+        if let Some(mut stdin) = self.stdin.take() {
+            // The process may already be gone; a broken pipe is not an error here.
+            let _ = stdin.write_all(b"q\n");
+        }
+        if self.wait_for_exit(CANCEL_GRACE)? {
+            return Ok(());
+        }
+        #[cfg(unix)]
+        {
+            terminate(&self.child);
+            if self.wait_for_exit(CANCEL_GRACE)? {
+                return Ok(());
+            }
+        }
+        self.kill()
+    }
+
+    /// Kill the process immediately.
+    pub fn kill(&mut self) -> Result<()> {
+        if !self.exited {
+            // `kill` fails if the process already exited, which is fine.
+            let _ = self.child.kill();
+            self.child.wait()?;
+            self.exited = true;
+        }
+        Ok(())
+    }
+
+    fn wait_for_exit(&mut self, grace: Duration) -> Result<bool> {
+        let deadline = Instant::now() + grace;
+        while Instant::now() < deadline {
+            if self.try_wait()?.is_some() {
+                return Ok(true);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        Ok(false)
+    }
+
+    /// Wait for exit (honoring `deadline`), feeding stdout lines to `on_line`
+    /// when the job was spawned in line mode.
+    pub(crate) fn finish(
+        mut self,
+        deadline: Option<Instant>,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<Output> {
+        let lines = match &self.stdout {
+            Some(StdoutReader::Lines(_)) => match self.stdout.take() {
+                Some(StdoutReader::Lines(receiver)) => Some(receiver),
+                _ => unreachable!(),
+            },
+            _ => None,
+        };
+        let mut lines_open = lines.is_some();
+
+        let status = loop {
+            if let Some(receiver) = lines.as_ref().filter(|_| lines_open) {
+                match receiver.recv_timeout(POLL_INTERVAL) {
+                    Ok(line) => {
+                        on_line(&line?);
+                        continue;
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => lines_open = false,
+                }
+            } else if deadline.is_none() {
+                let status = self.child.wait()?;
+                self.exited = true;
+                break status;
+            }
+
+            if let Some(status) = self.try_wait()? {
+                if !lines_open {
+                    break status;
+                }
+            } else if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                self.cancel()?;
+                let error = Error::TimeoutError(format!(
+                    "{} did not finish before the deadline",
+                    display_path(&self.binary)
+                ));
+                let stderr = match self.stderr.take() {
+                    Some(handle) => join_reader(handle)?,
+                    None => Vec::new(),
+                };
+                return Err(logging::with_partial_log(error, &stderr));
+            } else if !lines_open {
+                thread::sleep(POLL_INTERVAL);
+            }
+        };
+
+        self.stdin.take();
+        let stdout = match self.stdout.take() {
+            Some(StdoutReader::Capture(handle)) => join_reader(handle)?,
+            _ => Vec::new(),
+        };
+        let stderr = match self.stderr.take() {
+            Some(handle) => join_reader(handle)?,
+            None => Vec::new(),
+        };
+        Ok(Output {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+impl Drop for RunningJob {
+    fn drop(&mut self) {
+        if !self.exited {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+#[cfg(unix)]
+fn terminate(child: &Child) {
+    if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: `kill` has no memory-safety preconditions; the pid belongs to
+        // a child we have not yet reaped, so it cannot have been recycled.
+        unsafe {
+            libc::kill(pid, libc::SIGTERM);
+        }
+    }
+}
+
+fn join_reader(handle: thread::JoinHandle<io::Result<Vec<u8>>>) -> Result<Vec<u8>> {
+    let bytes = handle
+        .join()
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "output reader panicked"))??;
+    Ok(bytes)
+}
+
+/// Check the exit status and hand the log to `capture`.
+fn complete(binary: &Path, capture: &LogCapture, output: Output) -> Result<Output> {
+    let checked = check_status(binary, &output);
+    capture.apply(&output, checked)?;
+    Ok(output)
+}
+
+fn check_status(binary: &Path, output: &Output) -> Result<()> {
+    if !output.status.success() {
+        return Err(Error::command_failed(
+            display_path(binary),
+            output.status.code(),
+            &output.stderr,
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+pub(crate) async fn with_timeout_async<F, T>(
+    binary: &Path,
+    timeout: Option<Duration>,
+    future: F,
+) -> Result<T>
+where
+    F: std::future::Future<Output = io::Result<T>>,
+{
+    match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, future).await {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(Error::TimeoutError(format!(
+                "{} did not finish before the deadline",
+                display_path(binary)
+            ))),
+        },
+        None => Ok(future.await?),
+    }
+}
+
+/// Async stream of [`Progress`] reports from a running ffmpeg process.
+///
+/// Call [`next`](Self::next) until it returns `None`, then [`finish`](Self::finish)
+/// to collect the exit status. Dropping the stream kills the process.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct ProgressStream {
+    binary: PathBuf,
+    child: TokioChild,
+    lines: Lines<TokioBufReader<TokioChildStdout>>,
+    parser: ProgressParser,
+    stderr_reader: JoinHandle<io::Result<()>>,
+    stderr_log: Arc<Mutex<Vec<u8>>>,
+    log: LogCapture,
+    deadline: Option<tokio::time::Instant>,
+    timed_out: bool,
+}
+
+#[cfg(feature = "tokio")]
+impl ProgressStream {
+    /// Wait for the next progress report; `None` once ffmpeg closes stdout.
+    ///
+    /// Failures reading the progress pipe are yielded as `Err`.
+    pub async fn next(&mut self) -> Option<Result<Progress>> {
+        loop {
+            let line = match self.deadline {
+                Some(deadline) => {
+                    match tokio::time::timeout_at(deadline, self.lines.next_line()).await {
+                        Ok(line) => line,
+                        Err(_) => {
+                            self.timed_out = true;
+                            let _ = self.child.kill().await;
+                            return None;
+                        }
+                    }
+                }
+                None => self.lines.next_line().await,
+            };
+            let line = match line {
+                Ok(Some(line)) => line,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err.into())),
+            };
+            if let Some(progress) = self.parser.push_line(&line) {
+                return Some(Ok(progress));
+            }
+        }
+    }
+
+    /// Drain any remaining output and wait for the process to exit.
+    pub async fn finish(mut self) -> Result<()> {
+        while let Some(report) = self.next().await {
+            if let Err(err) = report {
+                let _ = self.child.kill().await;
+                return Err(err);
+            }
+        }
+        if self.timed_out {
+            let error = Error::TimeoutError(format!(
+                "{} did not finish before the deadline",
+                display_path(&self.binary)
+            ));
+            let stderr = logging::take_log(&self.stderr_log);
+            return Err(self
+                .log
+                .apply_to_error(logging::with_partial_log(error, &stderr)));
+        }
+        let status = self.child.wait().await?;
+        self.stderr_reader.await.map_err(io::Error::from)??;
+        let stderr = logging::take_log(&self.stderr_log);
+        let output = Output {
+            status,
+            stdout: Vec::new(),
+            stderr,
+        };
+        complete(&self.binary, &self.log, output).map(|_| ())
+    }
 }
 
 /// Specialized command for `ffprobe` returning JSON output.
@@ -146,6 +733,9 @@ pub struct FfprobeCommand {
     binary: PathBuf,
     input: PathBuf,
     extra_args: Vec<OsString>,
+    timeout: Option<Duration>,
+    log: LogCapture,
+    runner: Arc<dyn CommandRunner>,
 }
 
 impl FfprobeCommand {
@@ -155,6 +745,9 @@ impl FfprobeCommand {
             binary: binary.into(),
             input: input.into(),
             extra_args: Vec::new(),
+            timeout: None,
+            log: LogCapture::Disabled,
+            runner: default_runner(),
         }
     }
 
@@ -164,66 +757,75 @@ impl FfprobeCommand {
         self
     }
 
-    fn build_command(&self) -> Command {
-        let mut cmd = Command::new(&self.binary);
-        cmd.arg("-v")
-            .arg("quiet")
-            .arg("-print_format")
-            .arg("json")
-            .arg("-show_format")
-            .arg("-show_streams");
-        for arg in &self.extra_args {
-            cmd.arg(arg);
-        }
-        cmd.arg(&self.input)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-        cmd
+    /// Kill ffprobe and fail with [`Error::TimeoutError`] if it runs longer than `timeout`.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
     }
 
-    #[cfg(feature = "tokio")]
-    fn build_async_command(&self) -> TokioCommand {
-        let mut cmd = TokioCommand::new(&self.binary);
-        cmd.arg("-v")
-            .arg("quiet")
-            .arg("-print_format")
-            .arg("json")
-            .arg("-show_format")
-            .arg("-show_streams");
-        for arg in &self.extra_args {
-            cmd.arg(arg);
+    /// Execute through `runner` instead of spawning processes directly.
+    pub fn runner(&mut self, runner: Arc<dyn CommandRunner>) -> &mut Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Keep the complete stderr log in a file or attach it to errors.
+    pub fn log_capture(&mut self, capture: LogCapture) -> &mut Self {
+        self.log = capture;
+        self
+    }
+
+    /// Resolve the invocation handed to the runner.
+    pub fn invocation(&self) -> Invocation {
+        let mut args: Vec<OsString> = [
+            "-v",
+            "level+error",
+            "-print_format",
+            "json",
+            "-show_format",
+            "-show_streams",
+        ]
+        .iter()
+        .map(OsString::from)
+        .collect();
+        args.extend(self.extra_args.iter().cloned());
+        tag_loglevel_args(&mut args);
+        args.push(self.input.clone().into_os_string());
+        Invocation {
+            binary: self.binary.clone(),
+            args,
+            timeout: self.timeout,
+            stdout: StdoutMode::Capture,
         }
-        cmd.arg(&self.input)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-        cmd
+    }
+
+    /// Render the command as a copy-pasteable POSIX shell line.
+    pub fn to_shell_string(&self) -> String {
+        self.invocation().to_shell_string()
     }
 
     /// Execute ffprobe and fetch the captured output.
+    ///
+    /// A `-v`/`-loglevel` passed through [`arg`](Self::arg) keeps its level but
+    /// always gains the `level` flag, so stderr lines stay tagged.
     pub fn run(&self) -> Result<Output> {
-        let output = self.build_command().output()?;
-        if !output.status.success() {
-            return Err(Error::command_failed(
-                display_path(&self.binary),
-                output.status.code(),
-                &output.stderr,
-            ));
-        }
-        Ok(output)
+        let output = self
+            .runner
+            .run(&self.invocation(), &mut |_| {})
+            .map_err(|err| self.log.apply_to_error(err))?;
+        complete(&self.binary, &self.log, output)
     }
 
-    /// Async variant of [`run`] (requires `tokio`).
+    /// Async variant of [`run`](Self::run) (requires `tokio`).
     #[cfg(feature = "tokio")]
     pub async fn run_async(&self) -> Result<Output> {
-        let output = self.build_async_command().output().await?;
-        if !output.status.success() {
-            return Err(Error::command_failed(
-                display_path(&self.binary),
-                output.status.code(),
-                &output.stderr,
-            ));
-        }
-        Ok(output)
+        let invocation = self.invocation();
+        let output = self
+            .runner
+            .run_async(&invocation, &mut |_| {})
+            .await
+            .map_err(|err| self.log.apply_to_error(err))?;
+        complete(&self.binary, &self.log, output)
     }
 }
 
@@ -241,7 +843,7 @@ pub async fn ffprobe_json_async(
     paths: &FfmpegBinaryPaths,
     input: impl AsRef<Path>,
 ) -> Result<String> {
-    let mut cmd = FfprobeCommand::new(paths.ffprobe(), input.as_ref());
+    let cmd = FfprobeCommand::new(paths.ffprobe(), input.as_ref());
     let output = cmd.run_async().await?;
     let json = String::from_utf8(output.stdout).map_err(|err| Error::Parse(err.to_string()))?;
     Ok(json)
@@ -291,17 +893,13 @@ mod tests {
     #[test]
     fn ffprobe_command_includes_json_flags() {
         let cmd = FfprobeCommand::new("/usr/bin/ffprobe", "video.mkv");
-        let process = cmd.build_command();
-        let args = process
-            .get_args()
-            .map(|arg| arg.to_string_lossy().into_owned())
-            .collect::<Vec<_>>();
+        let args = cmd.invocation().args_lossy();
 
         assert_eq!(
             args,
             vec![
                 "-v",
-                "quiet",
+                "level+error",
                 "-print_format",
                 "json",
                 "-show_format",
@@ -310,4 +908,99 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn user_loglevel_keeps_level_tags() {
+        let mut cmd = FfmpegCommand::new("ffmpeg");
+        cmd.arg("-y").arg("-loglevel").arg("error").arg("out.mp4");
+        assert_eq!(
+            cmd.run_invocation(StdoutMode::Inherit).args_lossy(),
+            vec!["-y", "-loglevel", "level+error", "out.mp4"]
+        );
+
+        let mut probe = FfprobeCommand::new("ffprobe", "video.mkv");
+        probe.arg("-v").arg("quiet");
+        assert_eq!(
+            probe.invocation().args_lossy()[6..],
+            ["-v", "level+quiet", "video.mkv"]
+        );
+
+        assert_eq!(with_level_flag("repeat+level+info"), "repeat+level+info");
+        assert_eq!(with_level_flag("-level+warning"), "+level+warning");
+        assert_eq!(with_level_flag("+repeat"), "+level+repeat");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeout_kills_long_running_process() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // A stand-in for ffmpeg that ignores its arguments, logs and hangs.
+        let dir = crate::tempdir::TempDir::new("ffmpeg-light-test").unwrap();
+        let script = dir.path().join("ffmpeg");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\necho '[info] started' >&2\nexec sleep 5\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut cmd = FfmpegCommand::new(&script);
+        cmd.timeout(Duration::from_millis(300))
+            .log_capture(LogCapture::memory(10));
+        let started = Instant::now();
+        let err = cmd.run().unwrap_err();
+        assert!(matches!(err, Error::TimeoutError(_)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let log = dir.path().join("ffmpeg.log");
+        cmd.log_capture(LogCapture::file(&log));
+        let err = cmd
+            .spawn()
+            .unwrap()
+            .wait_timeout(Duration::from_secs(10))
+            .unwrap_err();
+        assert!(matches!(err, Error::TimeoutError(_)));
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "[info] started\n");
+
+        let started = Instant::now();
+        let err = cmd.spawn().unwrap().wait().unwrap_err();
+        assert!(matches!(err, Error::TimeoutError(_)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn spawn_adds_the_same_log_arguments_as_run() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // A stand-in for ffmpeg that logs its arguments and fails.
+        let dir = crate::tempdir::TempDir::new("ffmpeg-light-test").unwrap();
+        let script = dir.path().join("ffmpeg");
+        std::fs::write(&script, "#!/bin/sh\necho \"[error] $*\" >&2\nexit 1\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        for (args, logged) in [
+            (&["out.mp4"][..], "[error] -loglevel level+info out.mp4"),
+            (
+                &["-v", "warning", "out.mp4"][..],
+                "[error] -v level+warning out.mp4",
+            ),
+        ] {
+            let mut cmd = FfmpegCommand::new(&script);
+            cmd.args(args).log_capture(LogCapture::memory(10));
+            let ran = cmd.run().unwrap_err();
+            let spawned = cmd.spawn().unwrap().wait().unwrap_err();
+            assert_eq!(ran.log(), Some(&[logged.to_string()][..]));
+            assert_eq!(spawned.log(), ran.log());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cancel_escalates_to_sigterm() {
+        let mut job = FfmpegCommand::new("sleep").arg("30").spawn().unwrap();
+        job.cancel().unwrap();
+        assert!(job.try_wait().unwrap().is_some());
+    }
 }
@@ -0,0 +1,750 @@
+//! Joining several files one after another.
+//!
+//! Inputs whose streams share codec parameters are joined losslessly with
+//! the concat demuxer and `-c copy`. Anything else goes through the `concat`
+//! filter, with every input first scaled and padded to a common size, set to
+//! a common frame rate and resampled to a common audio rate. Inputs without
+//! audio get silence so the result stays in sync.
+//!
+//! A [`Transition`](crate::concat::Transition) blends consecutive inputs
+//! with `xfade` and `acrossfade` instead; each one overlaps the end of an
+//! input with the start of the next, so the output is shorter than the inputs
+//! combined.
+//!
+//! ```rust,no_run
+//! use ffmpeg_light::concat::{ConcatBuilder, ConcatMethod};
+//!
+//! let method = ConcatBuilder::new()
+//!     .input("intro.mp4")
+//!     .input("episode.mp4")
+//!     .input("outro.mp4")
+//!     .output("full.mp4")
+//!     .run()?;
+//! if method == ConcatMethod::Filter {
+//!     println!("inputs differ, the output was re-encoded");
+//! }
+//! # Ok::<(), ffmpeg_light::Error>(())
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::command::{FfmpegBinaryPaths, FfmpegCommand};
+use crate::config::{FfmpegLocator, Tools};
+use crate::error::{Error, Result};
+use crate::logging::LogCapture;
+use crate::probe::probe_with_runner;
+#[cfg(feature = "tokio")]
+use crate::probe::probe_with_runner_async;
+use crate::runner::CommandRunner;
+use crate::tempdir::TempDir;
+use crate::types::{ProbeResult, StreamInfo};
+
+/// Audio sample rate of filtered output when no input has audio to copy it from.
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+
+/// How the inputs are joined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConcatMethod {
+    /// Use the demuxer when the inputs are compatible, the filter otherwise.
+    #[default]
+    Auto,
+    /// Concat demuxer with stream copy; the inputs must share codec parameters.
+    Demuxer,
+    /// Concat filter with normalization and re-encoding.
+    Filter,
+}
+
+/// Video effect between two inputs, rendered with ffmpeg's `xfade` filter.
+///
+/// Audio is crossfaded over the same duration with `acrossfade`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Transition {
+    /// Crossfade.
+    Fade,
+    /// Fade through black.
+    FadeBlack,
+    /// Fade through white.
+    FadeWhite,
+    /// Random-pixel dissolve.
+    Dissolve,
+    /// The next input is revealed from right to left.
+    WipeLeft,
+    /// The next input is revealed from left to right.
+    WipeRight,
+    /// The next input is revealed from bottom to top.
+    WipeUp,
+    /// The next input is revealed from top to bottom.
+    WipeDown,
+    /// The next input pushes the current one out to the left.
+    SlideLeft,
+    /// The next input pushes the current one out to the right.
+    SlideRight,
+    /// The next input pushes the current one out to the top.
+    SlideUp,
+    /// The next input pushes the current one out to the bottom.
+    SlideDown,
+    /// Soft-edged wipe to the left.
+    SmoothLeft,
+    /// Soft-edged wipe to the right.
+    SmoothRight,
+    /// The next input grows from a circle in the center.
+    CircleOpen,
+    /// The current input shrinks into a circle in the center.
+    CircleClose,
+    /// Clock-hand sweep.
+    Radial,
+    /// Pixelate out and back in.
+    Pixelize,
+    /// Any other `xfade` transition, by name.
+    Other(String),
+}
+
+impl Transition {
+    /// Name of the transition in `xfade`'s `transition` option.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Transition::Fade => "fade",
+            Transition::FadeBlack => "fadeblack",
+            Transition::FadeWhite => "fadewhite",
+            Transition::Dissolve => "dissolve",
+            Transition::WipeLeft => "wipeleft",
+            Transition::WipeRight => "wiperight",
+            Transition::WipeUp => "wipeup",
+            Transition::WipeDown => "wipedown",
+            Transition::SlideLeft => "slideleft",
+            Transition::SlideRight => "slideright",
+            Transition::SlideUp => "slideup",
+            Transition::SlideDown => "slidedown",
+            Transition::SmoothLeft => "smoothleft",
+            Transition::SmoothRight => "smoothright",
+            Transition::CircleOpen => "circleopen",
+            Transition::CircleClose => "circleclose",
+            Transition::Radial => "radial",
+            Transition::Pixelize => "pixelize",
+            Transition::Other(name) => name,
+        }
+    }
+}
+
+/// Builder joining inputs in the order they were added.
+#[derive(Clone, Debug)]
+pub struct ConcatBuilder {
+    tools: Tools,
+    inputs: Vec<PathBuf>,
+    output: Option<PathBuf>,
+    method: ConcatMethod,
+    size: Option<(u32, u32)>,
+    frame_rate: Option<f64>,
+    sample_rate: Option<u32>,
+    video_codec: Option<String>,
+    audio_codec: Option<String>,
+    preset: Option<String>,
+    transition: Option<(Transition, Duration)>,
+}
+
+impl Default for ConcatBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConcatBuilder {
+    /// Create a builder picking the method automatically.
+    pub fn new() -> Self {
+        Self {
+            tools: Tools::default(),
+            inputs: Vec::new(),
+            output: None,
+            method: ConcatMethod::default(),
+            size: None,
+            frame_rate: None,
+            sample_rate: None,
+            video_codec: None,
+            audio_codec: None,
+            preset: None,
+            transition: None,
+        }
+    }
+
+    /// Use pre-discovered binaries instead of searching PATH every call.
+    pub fn with_binaries(mut self, binaries: &FfmpegBinaryPaths) -> Self {
+        self.tools.binaries = Some(binaries.clone());
+        self
+    }
+
+    /// Pin the builder to a specific locator.
+    pub fn with_locator(mut self, locator: &FfmpegLocator) -> Self {
+        self.tools.locate(locator);
+        self
+    }
+
+    /// Execute ffmpeg and ffprobe through a custom [`CommandRunner`].
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.tools.runner = Some(runner);
+        self
+    }
+
+    /// Append an input.
+    pub fn input<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.inputs.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Append several inputs.
+    pub fn inputs<P: AsRef<Path>>(mut self, paths: impl IntoIterator<Item = P>) -> Self {
+        self.inputs
+            .extend(paths.into_iter().map(|path| path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Destination path.
+    pub fn output<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.output = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Force a method instead of choosing from the probed inputs.
+    pub fn method(mut self, method: ConcatMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Frame size of filtered output (default: the first input's).
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    /// Frame rate of filtered output (default: the first input's).
+    pub fn frame_rate(mut self, fps: f64) -> Self {
+        self.frame_rate = Some(fps);
+        self
+    }
+
+    /// Audio sample rate of filtered output (default: the first input's with audio).
+    pub fn sample_rate(mut self, hz: u32) -> Self {
+        self.sample_rate = Some(hz);
+        self
+    }
+
+    /// Video encoder of filtered output.
+    pub fn video_codec(mut self, codec: impl Into<String>) -> Self {
+        self.video_codec = Some(codec.into());
+        self
+    }
+
+    /// Audio encoder of filtered output.
+    pub fn audio_codec(mut self, codec: impl Into<String>) -> Self {
+        self.audio_codec = Some(codec.into());
+        self
+    }
+
+    /// Encoder preset of filtered output (maps to `-preset`).
+    pub fn preset(mut self, preset: impl Into<String>) -> Self {
+        self.preset = Some(preset.into());
+        self
+    }
+
+    /// Blend every pair of consecutive inputs over `duration`.
+    ///
+    /// Offsets come from the probed input durations, which must be known,
+    /// and every input must be long enough to hold its transitions. The
+    /// filter method is used, with a frame rate common to all inputs.
+    pub fn transition(mut self, transition: Transition, duration: Duration) -> Self {
+        self.transition = Some((transition, duration));
+        self
+    }
+
+    /// Abort each ffmpeg or ffprobe process that runs longer than `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.tools.timeout = Some(timeout);
+        self
+    }
+
+    /// Keep ffmpeg's complete log in a file or attach it to the returned error.
+    pub fn log_capture(mut self, capture: LogCapture) -> Self {
+        self.tools.log = capture;
+        self
+    }
+
+    /// Accessor for the configured inputs.
+    pub fn inputs_ref(&self) -> &[PathBuf] {
+        &self.inputs
+    }
+
+    /// Accessor for the configured output path.
+    pub fn output_path(&self) -> Option<&Path> {
+        self.output.as_deref()
+    }
+
+    /// Accessor for the configured method.
+    pub fn method_value(&self) -> ConcatMethod {
+        self.method
+    }
+
+    /// Accessor for the configured transition and its duration.
+    pub fn transition_ref(&self) -> Option<(&Transition, Duration)> {
+        self.transition
+            .as_ref()
+            .map(|(transition, duration)| (transition, *duration))
+    }
+
+    /// Accessor for the configured timeout.
+    pub fn timeout_value(&self) -> Option<Duration> {
+        self.tools.timeout
+    }
+
+    /// Probe the inputs, join them and return the method that was used.
+    pub fn run(self) -> Result<ConcatMethod> {
+        self.check()?;
+        let (binaries, runner) = self.tools.resolve()?;
+        let sources = self
+            .inputs
+            .iter()
+            .map(|input| probe_with_runner(&binaries, &runner, input, self.tools.timeout))
+            .collect::<Result<Vec<_>>>()?;
+        let (method, cmd, _list) = self.command(&sources)?;
+        cmd.run()?;
+        Ok(method)
+    }
+
+    /// Async variant of [`run`](Self::run) (requires `tokio`).
+    ///
+    /// Dropping the future kills the running process.
+    #[cfg(feature = "tokio")]
+    pub async fn run_async(self) -> Result<ConcatMethod> {
+        self.check()?;
+        let (binaries, runner) = self.tools.resolve()?;
+        let mut sources = Vec::with_capacity(self.inputs.len());
+        for input in &self.inputs {
+            sources.push(
+                probe_with_runner_async(&binaries, &runner, input, self.tools.timeout).await?,
+            );
+        }
+        let (method, cmd, _list) = self.command(&sources)?;
+        cmd.run_async().await?;
+        Ok(method)
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.inputs.len() < 2 {
+            return Err(Error::InvalidInput(
+                "concatenation needs at least two inputs".into(),
+            ));
+        }
+        if self.output.is_none() {
+            return Err(Error::InvalidInput("output path is required".into()));
+        }
+        if let Some((_, duration)) = &self.transition {
+            if duration.is_zero() {
+                return Err(Error::InvalidInput(
+                    "transition duration must be positive".into(),
+                ));
+            }
+            if self.method == ConcatMethod::Demuxer {
+                return Err(Error::InvalidInput(
+                    "transitions re-encode and cannot use the concat demuxer".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Command joining the probed inputs, with the list file it reads (if any).
+    fn command(
+        &self,
+        sources: &[ProbeResult],
+    ) -> Result<(ConcatMethod, FfmpegCommand, Option<TempDir>)> {
+        let method = match self.method {
+            ConcatMethod::Auto if self.transition.is_some() => ConcatMethod::Filter,
+            ConcatMethod::Auto if compatible(sources) => ConcatMethod::Demuxer,
+            ConcatMethod::Auto => ConcatMethod::Filter,
+            ConcatMethod::Demuxer if !compatible(sources) => {
+                return Err(Error::InvalidInput(
+                    "the concat demuxer needs inputs with identical codec parameters".into(),
+                ))
+            }
+            method => method,
+        };
+        let mut cmd = self.tools.ffmpeg()?;
+        cmd.arg("-y");
+
+        let list = if method == ConcatMethod::Demuxer {
+            let dir = TempDir::new("ffmpeg-light-concat")?;
+            let list_file = dir.path().join("inputs.txt");
+            fs::write(&list_file, list_file_contents(&self.inputs)?)?;
+            cmd.arg("-f")
+                .arg("concat")
+                .arg("-safe")
+                .arg("0")
+                .arg("-i")
+                .arg(&list_file)
+                .arg("-c")
+                .arg("copy");
+            Some(dir)
+        } else {
+            for input in &self.inputs {
+                cmd.arg("-i").arg(input);
+            }
+            let graph = self.filter_graph(sources)?;
+            cmd.arg("-filter_complex").arg(graph.filter);
+            for label in &graph.outputs {
+                cmd.arg("-map").arg(label);
+            }
+            if let Some(codec) = &self.video_codec {
+                cmd.arg("-c:v").arg(codec);
+            }
+            if let Some(codec) = &self.audio_codec {
+                cmd.arg("-c:a").arg(codec);
+            }
+            if let Some(preset) = &self.preset {
+                cmd.arg("-preset").arg(preset);
+            }
+            None
+        };
+        if let Some(output) = &self.output {
+            cmd.arg(output);
+        }
+        Ok((method, cmd, list))
+    }
+
+    /// Filter graph normalizing every input to the first one's format, then
+    /// joining them with `concat` or the configured transition.
+    fn filter_graph(&self, sources: &[ProbeResult]) -> Result<ConcatGraph> {
+        let has_video = sources.iter().any(|source| source.first_video().is_some());
+        let has_audio = sources.iter().any(|source| source.first_audio().is_some());
+        if has_video && sources.iter().any(|source| source.first_video().is_none()) {
+            return Err(Error::InvalidInput(
+                "every input needs a video stream when any of them has one".into(),
+            ));
+        }
+        let size = self.size.or_else(|| {
+            let video = sources.iter().find_map(ProbeResult::first_video)?;
+            Some((video.width?, video.height?))
+        });
+        if has_video && size.is_none() {
+            return Err(Error::InvalidInput(
+                "the first input's frame size is unknown; set size()".into(),
+            ));
+        }
+        let frame_rate = self.frame_rate.or_else(|| {
+            sources
+                .iter()
+                .find_map(ProbeResult::first_video)?
+                .frame_rate
+        });
+        let sample_rate = self
+            .sample_rate
+            .or_else(|| {
+                sources
+                    .iter()
+                    .find_map(ProbeResult::first_audio)?
+                    .sample_rate
+            })
+            .unwrap_or(DEFAULT_SAMPLE_RATE);
+
+        let lengths = match &self.transition {
+            Some((_, duration)) => {
+                if has_video && frame_rate.is_none() {
+                    return Err(Error::InvalidInput(
+                        "transitions need a common frame rate; set frame_rate()".into(),
+                    ));
+                }
+                transition_lengths(sources, *duration)?
+            }
+            None => Vec::new(),
+        };
+
+        let mut chains = Vec::new();
+        let mut segments = String::new();
+        for (index, source) in sources.iter().enumerate() {
+            if let Some((width, height)) = size.filter(|_| has_video) {
+                let mut chain = format!(
+                    "[{index}:v:0]scale={width}:{height}:force_original_aspect_ratio=decrease,\
+                     pad={width}:{height}:(ow-iw)/2:(oh-ih)/2,setsar=1"
+                );
+                if let Some(fps) = frame_rate {
+                    chain.push_str(&format!(",fps={fps}"));
+                }
+                chain.push_str(&format!("[v{index}]"));
+                chains.push(chain);
+                segments.push_str(&format!("[v{index}]"));
+            }
+            if has_audio {
+                let chain = if source.first_audio().is_some() {
+                    format!("[{index}:a:0]aresample={sample_rate}[a{index}]")
+                } else {
+                    // Silence keeps later inputs' audio aligned with their video.
+                    let duration = source.duration().ok_or_else(|| {
+                        Error::InvalidInput(format!(
+                            "input {index} has no audio and an unknown duration"
+                        ))
+                    })?;
+                    format!(
+                        "anullsrc=r={sample_rate}:cl=stereo,atrim=duration={}[a{index}]",
+                        duration.as_secs_f64()
+                    )
+                };
+                chains.push(chain);
+                segments.push_str(&format!("[a{index}]"));
+            }
+        }
+
+        let mut outputs = Vec::new();
+        if has_video {
+            outputs.push("[v]".to_string());
+        }
+        if has_audio {
+            outputs.push("[a]".to_string());
+        }
+        if let Some((transition, duration)) = &self.transition {
+            let duration = duration.as_secs_f64();
+            let last = sources.len() - 1;
+            let mut offset = Duration::ZERO;
+            for join in 1..sources.len() {
+                // The previous inputs' lengths, minus the overlaps so far.
+                offset += lengths[join - 1];
+                let (video_in, audio_in) = if join == 1 {
+                    ("[v0]".to_string(), "[a0]".to_string())
+                } else {
+                    (format!("[vx{}]", join - 1), format!("[ax{}]", join - 1))
+                };
+                let (video_out, audio_out) = if join == last {
+                    ("[v]".to_string(), "[a]".to_string())
+                } else {
+                    (format!("[vx{join}]"), format!("[ax{join}]"))
+                };
+                if has_video {
+                    chains.push(format!(
+                        "{video_in}[v{join}]xfade=transition={}:duration={duration}:offset={}{video_out}",
+                        transition.as_str(),
+                        offset.as_secs_f64()
+                    ));
+                }
+                if has_audio {
+                    chains.push(format!(
+                        "{audio_in}[a{join}]acrossfade=d={duration}{audio_out}"
+                    ));
+                }
+            }
+            return Ok(ConcatGraph {
+                filter: chains.join(";"),
+                outputs,
+            });
+        }
+        chains.push(format!(
+            "{segments}concat=n={}:v={}:a={}{}",
+            sources.len(),
+            u8::from(has_video),
+            u8::from(has_audio),
+            outputs.concat()
+        ));
+        Ok(ConcatGraph {
+            filter: chains.join(";"),
+            outputs,
+        })
+    }
+}
+
+/// Time each input contributes before the next transition starts.
+///
+/// An input overlaps the previous one by `overlap` and the next one by
+/// `overlap` too, so the middle inputs must be longer than twice that.
+fn transition_lengths(sources: &[ProbeResult], overlap: Duration) -> Result<Vec<Duration>> {
+    let last = sources.len() - 1;
+    sources
+        .iter()
+        .enumerate()
+        .map(|(index, source)| {
+            let duration = source.duration().ok_or_else(|| {
+                Error::InvalidInput(format!(
+                    "input {index} has an unknown duration; transitions need it"
+                ))
+            })?;
+            let overlaps = if index == 0 || index == last { 1 } else { 2 };
+            if duration <= overlap * overlaps {
+                return Err(Error::InvalidInput(format!(
+                    "input {index} lasts {:.3}s, too short for {overlaps} transition(s) of {:.3}s",
+                    duration.as_secs_f64(),
+                    overlap.as_secs_f64()
+                )));
+            }
+            Ok(duration - overlap)
+        })
+        .collect()
+}
+
+/// Rendered `-filter_complex` value and the labels to map.
+#[derive(Debug)]
+struct ConcatGraph {
+    filter: String,
+    outputs: Vec<String>,
+}
+
+/// Whether the inputs can be joined by stream copy.
+///
+/// Compares the codec, profile, size, frame rate, pixel format, aspect ratio
+/// and time base of every video stream and the codec, channels, channel
+/// layout, sample rate and sample format of every audio stream, in order.
+/// Parameters ffprobe did not report compare equal to each other.
+fn compatible(sources: &[ProbeResult]) -> bool {
+    let signature = |source: &ProbeResult| -> Vec<String> {
+        source
+            .streams()
+            .iter()
+            .filter_map(|stream| match stream {
+                StreamInfo::Video(video) => Some(format!(
+                    "v:{}:{:?}:{:?}x{:?}@{:.2}:{:?}:{:?}:{:?}",
+                    video.codec.as_str(),
+                    video.profile,
+                    video.width,
+                    video.height,
+                    video.frame_rate.unwrap_or_default(),
+                    video.pix_fmt,
+                    video.sample_aspect_ratio,
+                    video.time_base
+                )),
+                StreamInfo::Audio(audio) => Some(format!(
+                    "a:{}:{:?}:{:?}@{:?}:{:?}",
+                    audio.codec.as_str(),
+                    audio.channels,
+                    audio.channel_layout,
+                    audio.sample_rate,
+                    audio.sample_fmt
+                )),
+                _ => None,
+            })
+            .collect()
+    };
+    let mut signatures = sources.iter().map(signature);
+    let first = signatures.next().unwrap_or_default();
+    !first.is_empty() && signatures.all(|other| other == first)
+}
+
+/// Contents of a concat demuxer list file for `paths`.
+///
+/// Paths are made absolute, since the demuxer resolves relative entries
+/// against the list file, and single quotes are escaped as `'\''`.
+pub(crate) fn list_file_contents(paths: &[PathBuf]) -> Result<String> {
+    let cwd = env::current_dir()?;
+    Ok(paths
+        .iter()
+        .map(|path| {
+            let path = cwd.join(path);
+            let path = path.to_string_lossy().replace('\'', r"'\''");
+            format!("file '{path}'\n")
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::parse_probe_output;
+
+    /// ffprobe output for a 5-second H.264 input, 720 pixels high.
+    fn source(width: u32, audio: bool) -> ProbeResult {
+        let audio = if audio {
+            r#", {"codec_type": "audio", "codec_name": "aac", "channels": 2,
+                "channel_layout": "stereo", "sample_rate": "44100", "sample_fmt": "fltp"}"#
+        } else {
+            ""
+        };
+        parse_probe_output(&format!(
+            r#"{{"format": {{"duration": "5.000000"}}, "streams": [
+                {{"codec_type": "video", "codec_name": "h264", "profile": "High", "level": 31,
+                  "width": {width}, "height": 720, "avg_frame_rate": "25/1",
+                  "pix_fmt": "yuv420p", "sample_aspect_ratio": "1:1",
+                  "time_base": "1/12800"}}{audio}]}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn compares_codec_parameters() {
+        assert!(compatible(&[source(1280, true), source(1280, true)]));
+        assert!(!compatible(&[source(1280, true), source(960, true)]));
+        assert!(!compatible(&[source(1280, true), source(1280, false)]));
+
+        let with = |change: fn(&mut StreamInfo)| {
+            let original = source(1280, true);
+            let mut streams = original.streams().to_vec();
+            streams.iter_mut().for_each(change);
+            let changed = ProbeResult::new(original.format().clone(), streams);
+            compatible(&[original, changed])
+        };
+        assert!(!with(|stream| if let StreamInfo::Video(video) = stream {
+            video.pix_fmt = Some("yuv420p10le".into());
+        }));
+        assert!(!with(|stream| if let StreamInfo::Video(video) = stream {
+            video.time_base = Some("1/90000".into());
+        }));
+        assert!(!with(|stream| if let StreamInfo::Audio(audio) = stream {
+            audio.channel_layout = Some("5.1".into());
+        }));
+        assert!(!with(|stream| if let StreamInfo::Audio(audio) = stream {
+            audio.sample_fmt = Some("s16".into());
+        }));
+    }
+
+    #[test]
+    fn pads_silence_for_inputs_without_audio() {
+        let graph = ConcatBuilder::new()
+            .filter_graph(&[source(1280, false), source(960, true)])
+            .unwrap();
+        assert_eq!(
+            graph.filter,
+            "[0:v:0]scale=1280:720:force_original_aspect_ratio=decrease,\
+             pad=1280:720:(ow-iw)/2:(oh-ih)/2,setsar=1,fps=25[v0];\
+             anullsrc=r=44100:cl=stereo,atrim=duration=5[a0];\
+             [1:v:0]scale=1280:720:force_original_aspect_ratio=decrease,\
+             pad=1280:720:(ow-iw)/2:(oh-ih)/2,setsar=1,fps=25[v1];\
+             [1:a:0]aresample=44100[a1];\
+             [v0][a0][v1][a1]concat=n=2:v=1:a=1[v][a]"
+        );
+        assert_eq!(graph.outputs, ["[v]", "[a]"]);
+    }
+
+    #[test]
+    fn offsets_transitions_by_probed_durations() {
+        let builder =
+            ConcatBuilder::new().transition(Transition::WipeLeft, Duration::from_millis(500));
+        let graph = builder
+            .filter_graph(&[source(1280, true), source(1280, true), source(1280, false)])
+            .unwrap();
+        let joins: Vec<_> = graph.filter.split(';').skip(6).collect();
+        assert_eq!(
+            joins,
+            [
+                "[v0][v1]xfade=transition=wipeleft:duration=0.5:offset=4.5[vx1]",
+                "[a0][a1]acrossfade=d=0.5[ax1]",
+                "[vx1][v2]xfade=transition=wipeleft:duration=0.5:offset=9[v]",
+                "[ax1][a2]acrossfade=d=0.5[a]"
+            ]
+        );
+
+        let long = ConcatBuilder::new().transition(Transition::Fade, Duration::from_secs(3));
+        let err = long
+            .filter_graph(&[source(1280, true), source(1280, true), source(1280, true)])
+            .unwrap_err();
+        assert!(err.to_string().contains("input 1 lasts 5.000s"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn escapes_quotes_in_list_file() {
+        let list = list_file_contents(&[
+            PathBuf::from("/media/it's here.mp4"),
+            PathBuf::from("/media/outro.mp4"),
+        ])
+        .unwrap();
+        assert_eq!(
+            list,
+            "file '/media/it'\\''s here.mp4'\nfile '/media/outro.mp4'\n"
+        );
+    }
+}
@@ -1,22 +1,27 @@
 //! Configuration helpers for locating FFmpeg binaries.
 
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
-use crate::command::FfmpegBinaryPaths;
+use crate::capabilities::FfmpegCapabilities;
+use crate::command::{FfmpegBinaryPaths, FfmpegCommand};
 use crate::error::{Error, Result};
+use crate::logging::LogCapture;
+use crate::runner::{default_runner, CommandRunner};
 
 /// Determines how the crate should locate `ffmpeg` and `ffprobe`.
 #[derive(Clone, Debug)]
 pub struct FfmpegLocator {
     paths: FfmpegBinaryPaths,
+    runner: Arc<dyn CommandRunner>,
+    capabilities: Arc<OnceLock<FfmpegCapabilities>>,
 }
 
 impl FfmpegLocator {
     /// Use binaries discovered on the current `PATH`.
     pub fn system() -> Result<Self> {
-        Ok(Self {
-            paths: FfmpegBinaryPaths::auto()?,
-        })
+        Ok(Self::from_paths(FfmpegBinaryPaths::auto()?))
     }
 
     /// Use explicitly provided binary paths.
@@ -33,14 +38,30 @@ impl FfmpegLocator {
                 suggestion: Some(format!("ffprobe not found at {}", ffprobe.display())),
             });
         }
-        Ok(Self {
-            paths: FfmpegBinaryPaths::with_paths(ffmpeg, ffprobe),
-        })
+        Ok(Self::from_paths(FfmpegBinaryPaths::with_paths(
+            ffmpeg, ffprobe,
+        )))
     }
 
     /// Reuse the given binary paths.
     pub fn from_paths(paths: FfmpegBinaryPaths) -> Self {
-        Self { paths }
+        Self {
+            paths,
+            runner: default_runner(),
+            capabilities: Arc::default(),
+        }
+    }
+
+    /// Execute every command created from this locator through `runner`.
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self.capabilities = Arc::default();
+        self
+    }
+
+    /// Runner used to execute commands.
+    pub fn runner(&self) -> &Arc<dyn CommandRunner> {
+        &self.runner
     }
 
     /// Raw binary paths.
@@ -48,6 +69,18 @@ impl FfmpegLocator {
         &self.paths
     }
 
+    /// Capabilities of the located ffmpeg, detected on first use and cached.
+    ///
+    /// Clones of the locator share the cache. A failed detection is not
+    /// cached, so the next call retries.
+    pub fn capabilities(&self) -> Result<&FfmpegCapabilities> {
+        if let Some(caps) = self.capabilities.get() {
+            return Ok(caps);
+        }
+        let detected = FfmpegCapabilities::detect_with_runner(&self.paths, &self.runner)?;
+        Ok(self.capabilities.get_or_init(|| detected))
+    }
+
     /// Path to ffmpeg.
     pub fn ffmpeg(&self) -> &Path {
         self.paths.ffmpeg()
@@ -63,3 +96,42 @@ impl FfmpegLocator {
 pub fn system_locator() -> Result<FfmpegLocator> {
     FfmpegLocator::system()
 }
+
+/// Binaries, runner, timeout and log capture shared by the job builders.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Tools {
+    pub(crate) binaries: Option<FfmpegBinaryPaths>,
+    pub(crate) runner: Option<Arc<dyn CommandRunner>>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) log: LogCapture,
+}
+
+impl Tools {
+    /// Use the binaries and runner of `locator`.
+    pub(crate) fn locate(&mut self, locator: &FfmpegLocator) {
+        self.binaries = Some(locator.binaries().clone());
+        self.runner = Some(Arc::clone(locator.runner()));
+    }
+
+    /// The configured binaries and runner, searching `PATH` if no binaries were set.
+    pub(crate) fn resolve(&self) -> Result<(FfmpegBinaryPaths, Arc<dyn CommandRunner>)> {
+        let binaries = match &self.binaries {
+            Some(paths) => paths.clone(),
+            None => FfmpegLocator::system()?.binaries().clone(),
+        };
+        let runner = self.runner.clone().unwrap_or_else(default_runner);
+        Ok((binaries, runner))
+    }
+
+    /// An ffmpeg command with the runner, timeout and log capture applied.
+    pub(crate) fn ffmpeg(&self) -> Result<FfmpegCommand> {
+        let (binaries, runner) = self.resolve()?;
+        let mut cmd = FfmpegCommand::new(binaries.ffmpeg());
+        cmd.runner(runner);
+        if let Some(timeout) = self.timeout {
+            cmd.timeout(timeout);
+        }
+        cmd.log_capture(self.log.clone());
+        Ok(cmd)
+    }
+}
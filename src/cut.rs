@@ -0,0 +1,766 @@
+//! Lossless cutting on keyframe boundaries.
+//!
+//! A stream copy can only start on a keyframe, so
+//! [`CutBuilder`](crate::cut::CutBuilder) reads the packet index of the first
+//! video stream with ffprobe and snaps the requested range to keyframes before
+//! copying it with `-c copy`. The returned [`CutReport`](crate::cut::CutReport)
+//! tells where the cut actually starts and ends.
+//!
+//! [`CutMode::Smart`](crate::cut::CutMode::Smart) keeps the exact range
+//! instead: the partial GOPs at both ends are re-encoded with the source
+//! codec, profile, level, pixel format and aspect ratio and joined with the
+//! copied middle through the concat demuxer. The parts are written as MPEG-TS,
+//! which repeats the parameter sets in the stream, and MP4/MOV outputs are
+//! tagged `avc3`/`hev1` so players pick up the re-encoded parts' SPS/PPS.
+//! Only H.264 and HEVC sources can be smart-cut.
+//!
+//! ```rust,no_run
+//! use ffmpeg_light::cut::CutBuilder;
+//! use ffmpeg_light::types::Time;
+//!
+//! let report = CutBuilder::new()
+//!     .input("match.mp4")
+//!     .output("highlight.mp4")
+//!     .start(Time::from_seconds(754))
+//!     .end(Time::from_seconds(812))
+//!     .run()?;
+//! println!("cut {} to {}", report.start, report.end);
+//! # Ok::<(), ffmpeg_light::Error>(())
+//! ```
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::command::{FfmpegBinaryPaths, FfmpegCommand, FfprobeCommand};
+use crate::concat;
+use crate::config::{FfmpegLocator, Tools};
+use crate::error::{Error, Result};
+use crate::logging::LogCapture;
+use crate::probe::{self, KeyframeIndex};
+use crate::runner::CommandRunner;
+use crate::tempdir::TempDir;
+use crate::types::{CodecType, ProbeResult, Time, VideoStreamInfo};
+
+/// Timestamps closer than this are treated as equal when snapping.
+const TOLERANCE: Duration = Duration::from_millis(1);
+
+/// How the cut handles boundaries that do not fall on keyframes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CutMode {
+    /// Stream-copy everything, moving the boundaries to keyframes.
+    #[default]
+    Copy,
+    /// Re-encode the partial GOPs at the boundaries and copy the rest
+    /// (H.264 and HEVC sources only).
+    Smart,
+}
+
+/// Which keyframes [`CutMode::Copy`] moves the boundaries to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyframeSnap {
+    /// Widen the range: start at or before, end at or after the request.
+    #[default]
+    Outward,
+    /// Narrow the range so nothing outside the request is kept.
+    Inward,
+    /// Use the closest keyframe on either side.
+    Nearest,
+}
+
+/// One part of the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CutSegment {
+    /// Start in the source.
+    pub start: Time,
+    /// End in the source.
+    pub end: Time,
+    /// Whether the part was re-encoded rather than copied.
+    pub reencoded: bool,
+}
+
+/// Range that was actually cut.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CutReport {
+    /// Start that was asked for.
+    pub requested_start: Time,
+    /// End that was asked for, clamped to the source duration.
+    pub requested_end: Time,
+    /// Start of the output in the source.
+    pub start: Time,
+    /// End of the output in the source.
+    pub end: Time,
+    /// Parts of the output, in order.
+    pub segments: Vec<CutSegment>,
+}
+
+impl CutReport {
+    /// Length of the output.
+    pub fn duration(&self) -> Duration {
+        self.end
+            .as_duration()
+            .saturating_sub(self.start.as_duration())
+    }
+
+    /// Whether every part was stream-copied.
+    pub fn is_lossless(&self) -> bool {
+        self.segments.iter().all(|segment| !segment.reencoded)
+    }
+}
+
+/// Builder for keyframe-aware cuts of a single input.
+#[derive(Clone, Debug)]
+pub struct CutBuilder {
+    tools: Tools,
+    input: Option<PathBuf>,
+    output: Option<PathBuf>,
+    start: Time,
+    end: Option<Time>,
+    mode: CutMode,
+    snap: KeyframeSnap,
+}
+
+impl Default for CutBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CutBuilder {
+    /// Create a builder copying from the start to the end of the input.
+    pub fn new() -> Self {
+        Self {
+            tools: Tools::default(),
+            input: None,
+            output: None,
+            start: Time::zero(),
+            end: None,
+            mode: CutMode::default(),
+            snap: KeyframeSnap::default(),
+        }
+    }
+
+    /// Use pre-discovered binaries instead of searching PATH every call.
+    pub fn with_binaries(mut self, binaries: &FfmpegBinaryPaths) -> Self {
+        self.tools.binaries = Some(binaries.clone());
+        self
+    }
+
+    /// Pin the builder to a specific locator.
+    pub fn with_locator(mut self, locator: &FfmpegLocator) -> Self {
+        self.tools.locate(locator);
+        self
+    }
+
+    /// Execute ffmpeg and ffprobe through a custom [`CommandRunner`].
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.tools.runner = Some(runner);
+        self
+    }
+
+    /// Source media path.
+    pub fn input<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.input = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Destination path; the container should accept the source codecs.
+    pub fn output<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.output = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Requested start of the cut (default: beginning of the input).
+    pub fn start(mut self, start: Time) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Requested end of the cut (default: end of the input).
+    pub fn end(mut self, end: Time) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    /// Copy only (default) or re-encode the boundary GOPs.
+    pub fn mode(mut self, mode: CutMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Keyframes used by [`CutMode::Copy`] (default [`KeyframeSnap::Outward`]).
+    pub fn snap(mut self, snap: KeyframeSnap) -> Self {
+        self.snap = snap;
+        self
+    }
+
+    /// Abort each ffmpeg or ffprobe process that runs longer than `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.tools.timeout = Some(timeout);
+        self
+    }
+
+    /// Keep ffmpeg's complete log in a file or attach it to the returned error.
+    pub fn log_capture(mut self, capture: LogCapture) -> Self {
+        self.tools.log = capture;
+        self
+    }
+
+    /// Accessor for the configured input path.
+    pub fn input_path(&self) -> Option<&Path> {
+        self.input.as_deref()
+    }
+
+    /// Accessor for the configured output path.
+    pub fn output_path(&self) -> Option<&Path> {
+        self.output.as_deref()
+    }
+
+    /// Accessor for the requested start.
+    pub fn start_value(&self) -> Time {
+        self.start
+    }
+
+    /// Accessor for the requested end.
+    pub fn end_value(&self) -> Option<Time> {
+        self.end
+    }
+
+    /// Accessor for the configured mode.
+    pub fn mode_value(&self) -> CutMode {
+        self.mode
+    }
+
+    /// Accessor for the configured keyframe snapping.
+    pub fn snap_value(&self) -> KeyframeSnap {
+        self.snap
+    }
+
+    /// Accessor for the configured timeout.
+    pub fn timeout_value(&self) -> Option<Duration> {
+        self.tools.timeout
+    }
+
+    /// Read the keyframes and work out the cut without writing anything.
+    pub fn plan(&self) -> Result<CutReport> {
+        self.check()?;
+        let json = self.packet_command()?.run()?.stdout;
+        self.resolve(&json)
+    }
+
+    /// Cut the input and report the range that was written.
+    pub fn run(self) -> Result<CutReport> {
+        let report = self.plan()?;
+        let source = if report.is_lossless() {
+            None
+        } else {
+            let (binaries, runner) = self.tools.resolve()?;
+            let input = self.input.as_deref().unwrap_or(Path::new(""));
+            Some(probe::probe_with_runner(
+                &binaries,
+                &runner,
+                input,
+                self.tools.timeout,
+            )?)
+        };
+        let scratch = self.scratch(&report)?;
+        for cmd in self.commands(&report, source.as_ref(), scratch.as_ref())? {
+            cmd.run()?;
+        }
+        Ok(report)
+    }
+
+    /// Async variant of [`run`](Self::run) (requires `tokio`).
+    ///
+    /// Dropping the future kills the running ffmpeg process.
+    #[cfg(feature = "tokio")]
+    pub async fn run_async(self) -> Result<CutReport> {
+        self.check()?;
+        let json = self.packet_command()?.run_async().await?.stdout;
+        let report = self.resolve(&json)?;
+        let source = if report.is_lossless() {
+            None
+        } else {
+            let (binaries, runner) = self.tools.resolve()?;
+            let input = self.input.as_deref().unwrap_or(Path::new(""));
+            Some(
+                probe::probe_with_runner_async(&binaries, &runner, input, self.tools.timeout)
+                    .await?,
+            )
+        };
+        let scratch = self.scratch(&report)?;
+        for cmd in self.commands(&report, source.as_ref(), scratch.as_ref())? {
+            cmd.run_async().await?;
+        }
+        Ok(report)
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.input.is_none() {
+            return Err(Error::InvalidInput("input path is required".into()));
+        }
+        if self.output.is_none() {
+            return Err(Error::InvalidInput("output path is required".into()));
+        }
+        if matches!(self.end, Some(end) if end <= self.start) {
+            return Err(Error::InvalidInput(format!(
+                "cut end {} is not after its start {}",
+                self.end.unwrap_or_default(),
+                self.start
+            )));
+        }
+        Ok(())
+    }
+
+    /// ffprobe listing the packets of the first video stream.
+    fn packet_command(&self) -> Result<FfprobeCommand> {
+        let (binaries, runner) = self.tools.resolve()?;
+        let input = self.input.as_deref().unwrap_or(Path::new(""));
+        Ok(probe::keyframe_command(
+            &binaries,
+            &runner,
+            input,
+            self.tools.timeout,
+        ))
+    }
+
+    fn resolve(&self, json: &[u8]) -> Result<CutReport> {
+        let index = KeyframeIndex::parse(json)?;
+        if index.keyframes.is_empty() {
+            return Err(Error::InvalidInput(
+                "input has no video keyframes to cut on".into(),
+            ));
+        }
+        index.cut(
+            self.start.as_duration(),
+            self.end.map(Time::as_duration),
+            self.mode,
+            self.snap,
+        )
+    }
+
+    /// Scratch directory for the parts of a multi-part cut.
+    fn scratch(&self, report: &CutReport) -> Result<Option<TempDir>> {
+        if report.segments.len() < 2 {
+            return Ok(None);
+        }
+        Ok(Some(TempDir::new("ffmpeg-light-cut")?))
+    }
+
+    /// ffmpeg commands writing the parts and, for several parts, joining them.
+    fn commands(
+        &self,
+        report: &CutReport,
+        source: Option<&ProbeResult>,
+        scratch: Option<&TempDir>,
+    ) -> Result<Vec<FfmpegCommand>> {
+        let output = self.output.as_deref().unwrap_or(Path::new(""));
+        let Some(scratch) = scratch else {
+            let segment = report.segments[0];
+            return Ok(vec![self.segment_command(segment, source, output)?]);
+        };
+
+        let mut commands = Vec::new();
+        let mut parts = Vec::new();
+        for (index, segment) in report.segments.iter().enumerate() {
+            let part = scratch.path().join(format!("part-{index}.ts"));
+            commands.push(self.segment_command(*segment, source, &part)?);
+            parts.push(part);
+        }
+        let list_file = scratch.path().join("parts.txt");
+        fs::write(&list_file, concat::list_file_contents(&parts)?)?;
+
+        let mut cmd = self.ffmpeg()?;
+        cmd.arg("-f")
+            .arg("concat")
+            .arg("-safe")
+            .arg("0")
+            .arg("-i")
+            .arg(&list_file)
+            .arg("-c")
+            .arg("copy");
+        // avc1/hvc1 only allow the parameter sets of the sample description.
+        let tag = match source
+            .and_then(ProbeResult::first_video)
+            .map(|video| &video.codec)
+        {
+            Some(CodecType::H264) => Some("avc3"),
+            Some(CodecType::Hevc) => Some("hev1"),
+            _ => None,
+        };
+        if let Some(tag) = tag.filter(|_| is_mov_family(output)) {
+            cmd.arg("-tag:v").arg(tag);
+        }
+        cmd.arg(output);
+        commands.push(cmd);
+        Ok(commands)
+    }
+
+    fn segment_command(
+        &self,
+        segment: CutSegment,
+        source: Option<&ProbeResult>,
+        output: &Path,
+    ) -> Result<FfmpegCommand> {
+        let start = segment.start.as_duration();
+        let length = segment.end.as_duration().saturating_sub(start);
+        let mut cmd = self.ffmpeg()?;
+        cmd.arg("-ss").arg(seconds(start));
+        if let Some(input) = &self.input {
+            cmd.arg("-i").arg(input);
+        }
+        cmd.arg("-t").arg(seconds(length));
+        match source {
+            Some(source) if segment.reencoded => {
+                let video = source.first_video().ok_or_else(|| {
+                    Error::InvalidInput("input has no video stream to re-encode".into())
+                })?;
+                cmd.args(&matching_video_args(video)?);
+                if let Some(audio) = source.first_audio() {
+                    cmd.arg("-c:a").arg(audio.codec.as_str());
+                    if let Some(rate) = audio.sample_rate {
+                        cmd.arg("-ar").arg(rate.to_string());
+                    }
+                    if let Some(channels) = audio.channels {
+                        cmd.arg("-ac").arg(channels.to_string());
+                    }
+                }
+            }
+            _ => {
+                cmd.arg("-c")
+                    .arg("copy")
+                    .arg("-avoid_negative_ts")
+                    .arg("make_zero");
+            }
+        }
+        cmd.arg(output);
+        Ok(cmd)
+    }
+
+    fn ffmpeg(&self) -> Result<FfmpegCommand> {
+        let mut cmd = self.tools.ffmpeg()?;
+        cmd.arg("-y");
+        Ok(cmd)
+    }
+}
+
+impl KeyframeIndex {
+    /// Last keyframe at or before `time` (the first keyframe if there is none).
+    fn at_or_before(&self, time: Duration) -> Duration {
+        self.keyframes
+            .iter()
+            .rev()
+            .find(|keyframe| **keyframe <= time + TOLERANCE)
+            .copied()
+            .unwrap_or(self.keyframes[0])
+    }
+
+    /// First keyframe at or after `time` (the end of the stream if there is none).
+    fn at_or_after(&self, time: Duration) -> Duration {
+        self.keyframes
+            .iter()
+            .find(|keyframe| **keyframe + TOLERANCE >= time)
+            .copied()
+            .unwrap_or(self.end)
+    }
+
+    /// Boundary at or before an end point; the end of the stream counts.
+    fn end_at_or_before(&self, time: Duration) -> Duration {
+        if time + TOLERANCE >= self.end {
+            self.end
+        } else {
+            self.at_or_before(time)
+        }
+    }
+
+    fn cut(
+        &self,
+        start: Duration,
+        end: Option<Duration>,
+        mode: CutMode,
+        snap: KeyframeSnap,
+    ) -> Result<CutReport> {
+        let end = end.map_or(self.end, |end| end.min(self.end));
+        if start + TOLERANCE >= end {
+            return Err(Error::InvalidInput(format!(
+                "cut starts at {} but the input ends at {}",
+                Time::from(start),
+                Time::from(end)
+            )));
+        }
+        let part = |from: Duration, to: Duration, reencoded| CutSegment {
+            start: from.into(),
+            end: to.into(),
+            reencoded,
+        };
+
+        let segments = match mode {
+            CutMode::Copy => {
+                let (earlier, later) = (self.at_or_before(start), self.at_or_after(start));
+                let from = match snap {
+                    KeyframeSnap::Outward => earlier,
+                    KeyframeSnap::Inward => later,
+                    KeyframeSnap::Nearest => nearest(start, earlier, later),
+                };
+                let (earlier, later) = (self.end_at_or_before(end), self.at_or_after(end));
+                let to = match snap {
+                    KeyframeSnap::Outward => later,
+                    KeyframeSnap::Inward => earlier,
+                    KeyframeSnap::Nearest => nearest(end, earlier, later),
+                };
+                if from + TOLERANCE >= to {
+                    return Err(Error::InvalidInput(format!(
+                        "no keyframe-aligned range between {} and {}; \
+                         snap outward or use CutMode::Smart",
+                        Time::from(start),
+                        Time::from(end)
+                    )));
+                }
+                vec![part(from, to, false)]
+            }
+            CutMode::Smart => {
+                let (first, last) = (self.at_or_after(start), self.end_at_or_before(end));
+                if first + TOLERANCE >= last {
+                    // The range sits inside a single GOP.
+                    vec![part(start, end, true)]
+                } else {
+                    let mut segments = Vec::new();
+                    if start + TOLERANCE < first {
+                        segments.push(part(start, first, true));
+                    }
+                    segments.push(part(first, last, false));
+                    if last + TOLERANCE < end {
+                        segments.push(part(last, end, true));
+                    }
+                    segments
+                }
+            }
+        };
+
+        Ok(CutReport {
+            requested_start: start.into(),
+            requested_end: end.into(),
+            start: segments[0].start,
+            end: segments[segments.len() - 1].end,
+            segments,
+        })
+    }
+}
+
+fn nearest(target: Duration, earlier: Duration, later: Duration) -> Duration {
+    if target.saturating_sub(earlier) <= later.saturating_sub(target) {
+        earlier
+    } else {
+        later
+    }
+}
+
+/// Encoder arguments reproducing the source video's codec parameters, so a
+/// re-encoded part can be joined with stream-copied ones.
+///
+/// Only H.264 and HEVC sources are supported, as their profiles and levels
+/// map onto libx264 and libx265 options.
+fn matching_video_args(video: &VideoStreamInfo) -> Result<Vec<OsString>> {
+    let mut args: Vec<OsString> = vec!["-c:v".into(), video.codec.as_str().into()];
+    let profile = video
+        .profile
+        .as_deref()
+        .map(|profile| profile.to_ascii_lowercase().replace([' ', ':'], ""));
+    match video.codec {
+        CodecType::H264 => {
+            if let Some(profile) = profile {
+                let profile = match profile.as_str() {
+                    "constrainedbaseline" | "baseline" => "baseline",
+                    "high444predictive" => "high444",
+                    "main" | "high" | "high10" | "high422" => &profile,
+                    _ => return Err(unmatched_profile(video)),
+                };
+                args.extend(["-profile:v".into(), profile.into()]);
+            }
+            if let Some(level) = video.level {
+                args.extend([
+                    "-level:v".into(),
+                    format!("{}.{}", level / 10, level % 10).into(),
+                ]);
+            }
+        }
+        CodecType::Hevc => {
+            if let Some(profile) = profile {
+                if !matches!(profile.as_str(), "main" | "main10" | "mainstillpicture") {
+                    return Err(unmatched_profile(video));
+                }
+                args.extend(["-profile:v".into(), profile.into()]);
+            }
+            // HEVC levels are reported as 30 times the level number.
+            if let Some(level) = video.level {
+                let level = format!("level-idc={}.{}", level / 30, level % 30 / 3);
+                args.extend(["-x265-params:v".into(), level.into()]);
+            }
+        }
+        _ => {
+            return Err(Error::Unsupported(format!(
+            "smart cuts can only match H.264 and HEVC encoder settings, not {}; use CutMode::Copy",
+            video.codec.as_str()
+        )))
+        }
+    }
+    if let Some(pix_fmt) = &video.pix_fmt {
+        args.extend(["-pix_fmt:v".into(), pix_fmt.into()]);
+    }
+    if let Some(sar) = &video.sample_aspect_ratio {
+        args.extend([
+            "-vf".into(),
+            format!("setsar={}", sar.replace(':', "/")).into(),
+        ]);
+    }
+    Ok(args)
+}
+
+fn unmatched_profile(video: &VideoStreamInfo) -> Error {
+    Error::Unsupported(format!(
+        "smart cuts cannot re-encode the {} profile {}; use CutMode::Copy",
+        video.codec.as_str(),
+        video.profile.as_deref().unwrap_or_default()
+    ))
+}
+
+/// Whether `path` is written by ffmpeg's mov/mp4 muxer, which accepts `avc3`/`hev1`.
+fn is_mov_family(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "mp4" | "m4v" | "mov"))
+}
+
+/// Seconds with microsecond precision, so keyframe timestamps survive the round trip.
+fn seconds(duration: Duration) -> String {
+    format!("{:.6}", duration.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> KeyframeIndex {
+        KeyframeIndex {
+            keyframes: [0, 2, 4, 6, 8].map(Duration::from_secs).to_vec(),
+            end: Duration::from_secs(10),
+        }
+    }
+
+    fn bounds(report: &CutReport) -> Vec<(f64, f64, bool)> {
+        report
+            .segments
+            .iter()
+            .map(|segment| {
+                (
+                    segment.start.as_duration().as_secs_f64(),
+                    segment.end.as_duration().as_secs_f64(),
+                    segment.reencoded,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn snaps_copies_to_keyframes() {
+        let secs = Duration::from_secs_f64;
+        let cut = |start, end, snap| {
+            index()
+                .cut(secs(start), Some(secs(end)), CutMode::Copy, snap)
+                .map(|report| bounds(&report))
+        };
+        assert_eq!(
+            cut(2.5, 6.5, KeyframeSnap::Outward).unwrap(),
+            [(2.0, 8.0, false)]
+        );
+        assert_eq!(
+            cut(2.5, 6.5, KeyframeSnap::Inward).unwrap(),
+            [(4.0, 6.0, false)]
+        );
+        assert_eq!(
+            cut(3.5, 6.5, KeyframeSnap::Nearest).unwrap(),
+            [(4.0, 6.0, false)]
+        );
+        assert_eq!(
+            cut(8.5, 20.0, KeyframeSnap::Outward).unwrap(),
+            [(8.0, 10.0, false)]
+        );
+        assert!(cut(2.5, 3.5, KeyframeSnap::Inward).is_err());
+    }
+
+    #[test]
+    fn smart_cut_reencodes_partial_gops_only() {
+        let secs = Duration::from_secs_f64;
+        let report = index()
+            .cut(
+                secs(2.5),
+                Some(secs(6.5)),
+                CutMode::Smart,
+                KeyframeSnap::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            bounds(&report),
+            [(2.5, 4.0, true), (4.0, 6.0, false), (6.0, 6.5, true)]
+        );
+        assert_eq!(report.duration(), secs(4.0));
+        let inside = index()
+            .cut(
+                secs(2.5),
+                Some(secs(3.5)),
+                CutMode::Smart,
+                KeyframeSnap::default(),
+            )
+            .unwrap();
+        assert_eq!(bounds(&inside), [(2.5, 3.5, true)]);
+    }
+
+    #[test]
+    fn matches_source_profile_and_level() {
+        let video = |codec, profile: &str, level| VideoStreamInfo {
+            codec,
+            width: None,
+            height: None,
+            bit_rate: None,
+            frame_rate: None,
+            pix_fmt: None,
+            profile: Some(profile.into()),
+            level: Some(level),
+            sample_aspect_ratio: None,
+            time_base: None,
+        };
+        let args = |video| -> Vec<String> {
+            matching_video_args(&video)
+                .unwrap()
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(
+            args(video(CodecType::H264, "Constrained Baseline", 31)),
+            [
+                "-c:v",
+                "libx264",
+                "-profile:v",
+                "baseline",
+                "-level:v",
+                "3.1"
+            ]
+        );
+        assert_eq!(
+            args(video(CodecType::Hevc, "Main 10", 123)),
+            [
+                "-c:v",
+                "libx265",
+                "-profile:v",
+                "main10",
+                "-x265-params:v",
+                "level-idc=4.1"
+            ]
+        );
+        assert!(matching_video_args(&video(CodecType::H264, "High 10 Intra", 40)).is_err());
+        assert!(matching_video_args(&video(CodecType::Vp9, "Profile 0", 0)).is_err());
+    }
+}
@@ -0,0 +1,796 @@
+//! MPEG-DASH packaging through ffmpeg's `dash` muxer.
+//!
+//! Each [`Rendition`](crate::rendition::Rendition) becomes a video
+//! representation; renditions that share an audio codec and bitrate share one
+//! audio representation. Video and audio
+//! go into separate adaptation sets, with keyframes forced at every segment
+//! boundary. After the run the generated MPD is read back into a
+//! [`DashManifest`](crate::dash::DashManifest).
+//!
+//! ```rust,no_run
+//! use ffmpeg_light::dash::DashBuilder;
+//! use ffmpeg_light::rendition::Rendition;
+//!
+//! let manifest = DashBuilder::new()
+//!     .input("master.mov")
+//!     .rendition(Rendition::new(1920, 1080, 5000))
+//!     .rendition(Rendition::new(1280, 720, 2800))
+//!     .output("public/dash/manifest.mpd")
+//!     .run()?;
+//! for representation in manifest.representations() {
+//!     println!("{} at {} bps", representation.id, representation.bandwidth);
+//! }
+//! # Ok::<(), ffmpeg_light::Error>(())
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::command::{FfmpegBinaryPaths, FfmpegCommand};
+use crate::config::{FfmpegLocator, Tools};
+use crate::error::{Error, Result};
+use crate::keyframe::ForceKeyframes;
+use crate::logging::LogCapture;
+use crate::rendition::Rendition;
+use crate::runner::CommandRunner;
+use crate::types::StreamType;
+
+/// Segment duration used unless configured otherwise.
+const DEFAULT_SEGMENT_DURATION: Duration = Duration::from_secs(4);
+
+/// Init segment name used unless configured otherwise.
+const DEFAULT_INIT_SEGMENT_NAME: &str = "init-$RepresentationID$.$ext$";
+
+/// Media segment name used unless configured otherwise.
+const DEFAULT_MEDIA_SEGMENT_NAME: &str = "chunk-$RepresentationID$-$Number%05d$.$ext$";
+
+/// Builder for multi-rendition DASH packaging.
+#[derive(Debug)]
+pub struct DashBuilder {
+    tools: Tools,
+    input: Option<PathBuf>,
+    renditions: Vec<Rendition>,
+    output: Option<PathBuf>,
+    segment_duration: Duration,
+    use_template: bool,
+    use_timeline: bool,
+    init_segment_name: String,
+    media_segment_name: String,
+    single_file: bool,
+    preset: Option<String>,
+}
+
+impl Default for DashBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DashBuilder {
+    /// Create a builder for 4-second segments addressed by a template with a timeline.
+    pub fn new() -> Self {
+        Self {
+            tools: Tools::default(),
+            input: None,
+            renditions: Vec::new(),
+            output: None,
+            segment_duration: DEFAULT_SEGMENT_DURATION,
+            use_template: true,
+            use_timeline: true,
+            init_segment_name: DEFAULT_INIT_SEGMENT_NAME.into(),
+            media_segment_name: DEFAULT_MEDIA_SEGMENT_NAME.into(),
+            single_file: false,
+            preset: None,
+        }
+    }
+
+    /// Use pre-discovered binaries instead of searching PATH every call.
+    pub fn with_binaries(mut self, binaries: &FfmpegBinaryPaths) -> Self {
+        self.tools.binaries = Some(binaries.clone());
+        self
+    }
+
+    /// Pin the builder to a specific locator.
+    pub fn with_locator(mut self, locator: &FfmpegLocator) -> Self {
+        self.tools.locate(locator);
+        self
+    }
+
+    /// Execute ffmpeg through a custom [`CommandRunner`].
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.tools.runner = Some(runner);
+        self
+    }
+
+    /// Source media path.
+    pub fn input<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.input = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Add a video representation (and its audio, unless another rendition already has it).
+    pub fn rendition(mut self, rendition: Rendition) -> Self {
+        self.renditions.push(rendition);
+        self
+    }
+
+    /// Add several renditions, e.g. from a bitrate ladder.
+    pub fn renditions(mut self, renditions: impl IntoIterator<Item = Rendition>) -> Self {
+        self.renditions.extend(renditions);
+        self
+    }
+
+    /// Path of the MPD; segments are written next to it.
+    pub fn output<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.output = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Target segment duration (maps to `-seg_duration`, default 4 seconds).
+    pub fn segment_duration(mut self, duration: Duration) -> Self {
+        self.segment_duration = duration;
+        self
+    }
+
+    /// Address segments with a `SegmentTemplate` (default) instead of a `SegmentList`.
+    pub fn use_template(mut self, enabled: bool) -> Self {
+        self.use_template = enabled;
+        self
+    }
+
+    /// List exact segment durations in a `SegmentTimeline` (default).
+    pub fn use_timeline(mut self, enabled: bool) -> Self {
+        self.use_timeline = enabled;
+        self
+    }
+
+    /// Init segment name template (maps to `-init_seg_name`).
+    ///
+    /// Supports `$RepresentationID$` and `$ext$`; defaults to
+    /// `init-$RepresentationID$.$ext$`.
+    pub fn init_segment_name(mut self, name: impl Into<String>) -> Self {
+        self.init_segment_name = name.into();
+        self
+    }
+
+    /// Media segment name template (maps to `-media_seg_name`).
+    ///
+    /// Supports `$RepresentationID$`, `$Number%05d$`, `$Time$` and `$ext$`;
+    /// defaults to `chunk-$RepresentationID$-$Number%05d$.$ext$`.
+    pub fn media_segment_name(mut self, name: impl Into<String>) -> Self {
+        self.media_segment_name = name.into();
+        self
+    }
+
+    /// Write each representation into one file addressed by byte ranges (maps to `-single_file`).
+    ///
+    /// Segments are then listed in a `SegmentList`, so the template and the
+    /// segment names do not apply.
+    pub fn single_file(mut self, enabled: bool) -> Self {
+        self.single_file = enabled;
+        self
+    }
+
+    /// Apply a named encoder preset to every rendition (maps to `-preset`).
+    pub fn preset(mut self, preset: impl Into<String>) -> Self {
+        self.preset = Some(preset.into());
+        self
+    }
+
+    /// Abort the job with [`Error::TimeoutError`] if it runs longer than `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.tools.timeout = Some(timeout);
+        self
+    }
+
+    /// Keep ffmpeg's complete log in a file or attach it to the returned error.
+    pub fn log_capture(mut self, capture: LogCapture) -> Self {
+        self.tools.log = capture;
+        self
+    }
+
+    /// Accessor for the configured input path.
+    pub fn input_path(&self) -> Option<&Path> {
+        self.input.as_deref()
+    }
+
+    /// Accessor for the configured renditions.
+    pub fn renditions_ref(&self) -> &[Rendition] {
+        &self.renditions
+    }
+
+    /// Accessor for the configured MPD path.
+    pub fn output_path(&self) -> Option<&Path> {
+        self.output.as_deref()
+    }
+
+    /// Accessor for the target segment duration.
+    pub fn segment_duration_value(&self) -> Duration {
+        self.segment_duration
+    }
+
+    /// Returns whether single-file mode is enabled.
+    pub fn single_file_enabled(&self) -> bool {
+        self.single_file
+    }
+
+    /// Accessor for the configured timeout.
+    pub fn timeout_value(&self) -> Option<Duration> {
+        self.tools.timeout
+    }
+
+    /// Validate the configuration and return the ffmpeg command without running it.
+    pub fn build_command(self) -> Result<FfmpegCommand> {
+        self.command()
+    }
+
+    /// Package the input and read back the generated MPD.
+    pub fn run(self) -> Result<DashManifest> {
+        let cmd = self.command()?;
+        let output = self.mpd_path();
+        create_parent(output)?;
+        cmd.run()?;
+        DashManifest::read(output)
+    }
+
+    /// Async variant of [`run`](Self::run) (requires `tokio`).
+    ///
+    /// Dropping the future kills the ffmpeg process.
+    #[cfg(feature = "tokio")]
+    pub async fn run_async(self) -> Result<DashManifest> {
+        let cmd = self.command()?;
+        let output = self.mpd_path();
+        create_parent(output)?;
+        cmd.run_async().await?;
+        DashManifest::read(output)
+    }
+
+    fn mpd_path(&self) -> &Path {
+        self.output.as_deref().unwrap_or_else(|| Path::new("."))
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.input.is_none() {
+            return Err(Error::InvalidInput("input path is required".into()));
+        }
+        if self.output.is_none() {
+            return Err(Error::InvalidInput("output path is required".into()));
+        }
+        if self.renditions.is_empty() {
+            return Err(Error::InvalidInput(
+                "DASH packaging needs at least one rendition".into(),
+            ));
+        }
+        for rendition in &self.renditions {
+            rendition.validate()?;
+        }
+        if self.segment_duration.is_zero() {
+            return Err(Error::InvalidInput(
+                "segment duration must be positive".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Distinct audio encodings across the renditions, in first-use order.
+    fn audio_tracks(&self) -> Vec<&Rendition> {
+        let mut tracks: Vec<&Rendition> = Vec::new();
+        for rendition in self.renditions.iter().filter(|r| r.has_audio()) {
+            let shared = tracks.iter().any(|track| {
+                track.audio_codec_ref() == rendition.audio_codec_ref()
+                    && track.audio_bitrate_value() == rendition.audio_bitrate_value()
+            });
+            if !shared {
+                tracks.push(rendition);
+            }
+        }
+        tracks
+    }
+
+    fn command(&self) -> Result<FfmpegCommand> {
+        self.check()?;
+        let mut cmd = self.tools.ffmpeg()?;
+        cmd.arg("-y");
+        if let Some(input) = &self.input {
+            cmd.arg("-i").arg(input);
+        }
+
+        for (index, rendition) in self.renditions.iter().enumerate() {
+            cmd.arg("-map").arg("0:v:0");
+            cmd.args(&rendition.video_args(index));
+        }
+        let audio = self.audio_tracks();
+        for (index, rendition) in audio.iter().enumerate() {
+            cmd.arg("-map").arg("0:a:0");
+            cmd.args(&rendition.audio_args(index));
+        }
+        if let Some(preset) = &self.preset {
+            cmd.arg("-preset").arg(preset);
+        }
+        let keyframes = ForceKeyframes::Every(self.segment_duration);
+        cmd.arg("-force_key_frames:v").arg(keyframes.to_arg());
+
+        let mut adaptation_sets = "id=0,streams=v".to_string();
+        if !audio.is_empty() {
+            adaptation_sets.push_str(" id=1,streams=a");
+        }
+        cmd.arg("-f").arg("dash");
+        cmd.arg("-seg_duration")
+            .arg(self.segment_duration.as_secs_f64().to_string());
+        let use_template = self.use_template && !self.single_file;
+        cmd.arg("-use_template")
+            .arg(if use_template { "1" } else { "0" });
+        cmd.arg("-use_timeline")
+            .arg(if self.use_timeline { "1" } else { "0" });
+        if self.single_file {
+            cmd.arg("-single_file").arg("1");
+        } else {
+            cmd.arg("-init_seg_name").arg(&self.init_segment_name);
+            cmd.arg("-media_seg_name").arg(&self.media_segment_name);
+        }
+        cmd.arg("-adaptation_sets").arg(adaptation_sets);
+        cmd.arg(self.mpd_path());
+        Ok(cmd)
+    }
+}
+
+fn create_parent(path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => Ok(fs::create_dir_all(parent)?),
+        _ => Ok(()),
+    }
+}
+
+/// Summary of an MPD: its adaptation sets and their representations.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DashManifest {
+    /// `mediaPresentationDuration` of static presentations.
+    pub duration: Option<Duration>,
+    /// Longest segment (`maxSegmentDuration`).
+    pub max_segment_duration: Option<Duration>,
+    /// Buffer a client needs before playback (`minBufferTime`).
+    pub min_buffer_time: Option<Duration>,
+    /// Adaptation sets of every period, in document order.
+    pub adaptation_sets: Vec<DashAdaptationSet>,
+}
+
+/// One `AdaptationSet` of an MPD.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DashAdaptationSet {
+    /// `id` attribute.
+    pub id: Option<String>,
+    /// Media type, from `contentType` or the MIME type.
+    pub content_type: Option<StreamType>,
+    /// Representations in document order.
+    pub representations: Vec<DashRepresentation>,
+}
+
+/// One `Representation` of an MPD.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DashRepresentation {
+    /// `id` attribute, substituted for `$RepresentationID$`.
+    pub id: String,
+    /// MIME type such as `video/mp4`.
+    pub mime_type: Option<String>,
+    /// RFC 6381 codec string such as `avc1.64001f`.
+    pub codecs: Option<String>,
+    /// Peak bandwidth in bits per second.
+    pub bandwidth: u64,
+    /// Width in pixels.
+    pub width: Option<u32>,
+    /// Height in pixels.
+    pub height: Option<u32>,
+    /// Frame rate in frames per second.
+    pub frame_rate: Option<f64>,
+    /// Audio sample rate in Hz.
+    pub sample_rate: Option<u32>,
+    /// Init segment template or URL.
+    pub initialization: Option<String>,
+    /// Media segment template.
+    pub media: Option<String>,
+    /// File of a single-file representation (`BaseURL`).
+    pub base_url: Option<String>,
+    /// Number of segments listed by a timeline or segment list.
+    pub segments: Option<usize>,
+}
+
+impl DashManifest {
+    /// Parse the text of an MPD.
+    ///
+    /// This is a summary reader for the MPDs ffmpeg writes, not a general
+    /// XML or DASH validator: unknown elements are skipped.
+    pub fn parse(mpd: &str) -> Result<Self> {
+        let mut manifest = DashManifest::default();
+        let mut set: Option<(DashAdaptationSet, SetDefaults)> = None;
+        let mut representation: Option<DashRepresentation> = None;
+        let mut saw_mpd = false;
+
+        for tag in scan_tags(mpd)? {
+            let attr = |name: &str| tag.attr(name);
+            match (tag.name, tag.kind) {
+                ("MPD", TagKind::Open | TagKind::Empty) => {
+                    saw_mpd = true;
+                    manifest.duration = attr("mediaPresentationDuration").and_then(iso_duration);
+                    manifest.max_segment_duration =
+                        attr("maxSegmentDuration").and_then(iso_duration);
+                    manifest.min_buffer_time = attr("minBufferTime").and_then(iso_duration);
+                }
+                ("AdaptationSet", TagKind::Open | TagKind::Empty) => {
+                    let defaults = SetDefaults {
+                        mime_type: attr("mimeType").map(str::to_string),
+                        codecs: attr("codecs").map(str::to_string),
+                        frame_rate: attr("frameRate").and_then(frame_rate),
+                        ..SetDefaults::default()
+                    };
+                    let adaptation = DashAdaptationSet {
+                        id: attr("id").map(str::to_string),
+                        content_type: attr("contentType").and_then(content_type),
+                        representations: Vec::new(),
+                    };
+                    set = Some((adaptation, defaults));
+                    if tag.kind == TagKind::Empty {
+                        finish_set(&mut manifest, &mut set);
+                    }
+                }
+                ("AdaptationSet", TagKind::Close) => finish_set(&mut manifest, &mut set),
+                ("Representation", TagKind::Open | TagKind::Empty) => {
+                    let bandwidth = attr("bandwidth").unwrap_or_default();
+                    let parsed = DashRepresentation {
+                        id: attr("id").unwrap_or_default().to_string(),
+                        mime_type: attr("mimeType").map(str::to_string),
+                        codecs: attr("codecs").map(str::to_string),
+                        bandwidth: bandwidth.parse().map_err(|_| {
+                            Error::Parse(format!("invalid representation bandwidth `{bandwidth}`"))
+                        })?,
+                        width: attr("width").and_then(|value| value.parse().ok()),
+                        height: attr("height").and_then(|value| value.parse().ok()),
+                        frame_rate: attr("frameRate").and_then(frame_rate),
+                        sample_rate: attr("audioSamplingRate").and_then(|value| value.parse().ok()),
+                        ..DashRepresentation::default()
+                    };
+                    representation = Some(parsed);
+                    if tag.kind == TagKind::Empty {
+                        finish_representation(&mut set, &mut representation);
+                    }
+                }
+                ("Representation", TagKind::Close) => {
+                    finish_representation(&mut set, &mut representation)
+                }
+                ("SegmentTemplate", TagKind::Open | TagKind::Empty) => {
+                    let initialization = attr("initialization").map(str::to_string);
+                    let media = attr("media").map(str::to_string);
+                    match (&mut representation, &mut set) {
+                        (Some(rep), _) => {
+                            rep.initialization = initialization;
+                            rep.media = media;
+                        }
+                        (None, Some((_, defaults))) => {
+                            defaults.initialization = initialization;
+                            defaults.media = media;
+                        }
+                        (None, None) => {}
+                    }
+                }
+                ("Initialization", TagKind::Open | TagKind::Empty) => {
+                    if let (Some(rep), Some(url)) = (&mut representation, attr("sourceURL")) {
+                        rep.initialization = Some(url.to_string());
+                    }
+                }
+                ("S", TagKind::Open | TagKind::Empty) => {
+                    // `r="-1"` repeats until the end of the period; count it once.
+                    let repeat = attr("r")
+                        .and_then(|value| value.parse::<i64>().ok())
+                        .unwrap_or(0)
+                        .max(0) as usize;
+                    add_segments(&mut set, &mut representation, 1 + repeat);
+                }
+                ("SegmentURL", TagKind::Open | TagKind::Empty) => {
+                    add_segments(&mut set, &mut representation, 1);
+                }
+                ("BaseURL", TagKind::Open) => {
+                    if let Some(rep) = &mut representation {
+                        rep.base_url = Some(unescape(tag.text.trim()));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !saw_mpd {
+            return Err(Error::Parse("document has no MPD element".into()));
+        }
+        Ok(manifest)
+    }
+
+    /// Read and parse an MPD file.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Every representation across the adaptation sets.
+    pub fn representations(&self) -> impl Iterator<Item = &DashRepresentation> {
+        self.adaptation_sets
+            .iter()
+            .flat_map(|set| set.representations.iter())
+    }
+
+    /// Adaptation sets carrying `kind` streams.
+    pub fn adaptation_sets_of(&self, kind: StreamType) -> impl Iterator<Item = &DashAdaptationSet> {
+        self.adaptation_sets
+            .iter()
+            .filter(move |set| set.content_type.as_ref() == Some(&kind))
+    }
+}
+
+/// Values an adaptation set passes down to representations lacking them.
+#[derive(Default)]
+struct SetDefaults {
+    mime_type: Option<String>,
+    codecs: Option<String>,
+    frame_rate: Option<f64>,
+    initialization: Option<String>,
+    media: Option<String>,
+    segments: Option<usize>,
+}
+
+fn finish_representation(
+    set: &mut Option<(DashAdaptationSet, SetDefaults)>,
+    representation: &mut Option<DashRepresentation>,
+) {
+    if let (Some((adaptation, _)), Some(rep)) = (set.as_mut(), representation.take()) {
+        adaptation.representations.push(rep);
+    }
+}
+
+fn finish_set(manifest: &mut DashManifest, set: &mut Option<(DashAdaptationSet, SetDefaults)>) {
+    let Some((mut adaptation, defaults)) = set.take() else {
+        return;
+    };
+    for rep in &mut adaptation.representations {
+        rep.mime_type = rep.mime_type.take().or_else(|| defaults.mime_type.clone());
+        rep.codecs = rep.codecs.take().or_else(|| defaults.codecs.clone());
+        rep.frame_rate = rep.frame_rate.or(defaults.frame_rate);
+        rep.initialization = rep
+            .initialization
+            .take()
+            .or_else(|| defaults.initialization.clone());
+        rep.media = rep.media.take().or_else(|| defaults.media.clone());
+        rep.segments = rep.segments.or(defaults.segments);
+    }
+    if adaptation.content_type.is_none() {
+        adaptation.content_type = adaptation
+            .representations
+            .iter()
+            .find_map(|rep| rep.mime_type.as_deref())
+            .or(defaults.mime_type.as_deref())
+            .and_then(|mime| content_type(mime.split('/').next().unwrap_or_default()));
+    }
+    manifest.adaptation_sets.push(adaptation);
+}
+
+fn add_segments(
+    set: &mut Option<(DashAdaptationSet, SetDefaults)>,
+    representation: &mut Option<DashRepresentation>,
+    count: usize,
+) {
+    let segments = match (representation, set) {
+        (Some(rep), _) => &mut rep.segments,
+        (None, Some((_, defaults))) => &mut defaults.segments,
+        (None, None) => return,
+    };
+    *segments = Some(segments.unwrap_or(0) + count);
+}
+
+fn content_type(value: &str) -> Option<StreamType> {
+    match value {
+        "video" => Some(StreamType::Video),
+        "audio" => Some(StreamType::Audio),
+        "text" => Some(StreamType::Subtitle),
+        _ => None,
+    }
+}
+
+/// Frame rate written as `25`, `30000/1001` or `29.97`.
+fn frame_rate(value: &str) -> Option<f64> {
+    match value.split_once('/') {
+        Some((num, den)) => {
+            let den: f64 = den.parse().ok()?;
+            (den != 0.0).then_some(num.parse::<f64>().ok()? / den)
+        }
+        None => value.parse().ok(),
+    }
+}
+
+/// ISO 8601 duration such as `PT1M30.5S` or `P0Y0M0DT0H0M10.000S`.
+fn iso_duration(value: &str) -> Option<Duration> {
+    let rest = value.strip_prefix('P')?;
+    let (date, time) = rest.split_once('T').unwrap_or((rest, ""));
+    let mut seconds = 0.0;
+    for (part, units) in [
+        (
+            date,
+            &[
+                ('Y', 365.0 * 86_400.0),
+                ('M', 30.0 * 86_400.0),
+                ('D', 86_400.0),
+            ][..],
+        ),
+        (time, &[('H', 3_600.0), ('M', 60.0), ('S', 1.0)][..]),
+    ] {
+        let mut number = String::new();
+        for c in part.chars() {
+            if c.is_ascii_digit() || c == '.' {
+                number.push(c);
+                continue;
+            }
+            let scale = units.iter().find(|(unit, _)| *unit == c)?.1;
+            seconds += number.parse::<f64>().ok()? * scale;
+            number.clear();
+        }
+        if !number.is_empty() {
+            return None;
+        }
+    }
+    Some(Duration::from_secs_f64(seconds))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TagKind {
+    Open,
+    Close,
+    Empty,
+}
+
+/// An element tag with its attributes and the text up to the next tag.
+struct Tag<'a> {
+    name: &'a str,
+    kind: TagKind,
+    attrs: Vec<(&'a str, &'a str)>,
+    text: &'a str,
+}
+
+impl<'a> Tag<'a> {
+    fn attr(&self, name: &str) -> Option<&'a str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+/// Split a document into element tags, skipping declarations and comments.
+///
+/// Attribute values are returned raw; none of the ones read here contain entities.
+fn scan_tags(xml: &str) -> Result<Vec<Tag<'_>>> {
+    let malformed = || Error::Parse("malformed MPD document".into());
+    let mut tags = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        let skip_to = if rest.starts_with("<!--") {
+            Some("-->")
+        } else if rest.starts_with("<?") {
+            Some("?>")
+        } else if rest.starts_with("<!") {
+            Some(">")
+        } else {
+            None
+        };
+        if let Some(terminator) = skip_to {
+            let end = rest.find(terminator).ok_or_else(malformed)?;
+            rest = &rest[end + terminator.len()..];
+            continue;
+        }
+
+        let end = tag_end(rest).ok_or_else(malformed)?;
+        let body = &rest[1..end];
+        rest = &rest[end + 1..];
+        let text = &rest[..rest.find('<').unwrap_or(rest.len())];
+        if let Some(name) = body.strip_prefix('/') {
+            tags.push(Tag {
+                name: name.trim(),
+                kind: TagKind::Close,
+                attrs: Vec::new(),
+                text,
+            });
+            continue;
+        }
+        let (body, kind) = match body.strip_suffix('/') {
+            Some(body) => (body, TagKind::Empty),
+            None => (body, TagKind::Open),
+        };
+        let name_end = body
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(body.len());
+        tags.push(Tag {
+            name: &body[..name_end],
+            kind,
+            attrs: parse_attrs(&body[name_end..]).ok_or_else(malformed)?,
+            text,
+        });
+    }
+    Ok(tags)
+}
+
+/// Index of the `>` closing the tag at the start of `s`, ignoring quoted ones.
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if open == c => quote = None,
+            (None, '>') => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_attrs(mut s: &str) -> Option<Vec<(&str, &str)>> {
+    let mut attrs = Vec::new();
+    loop {
+        s = s.trim_start();
+        if s.is_empty() {
+            return Some(attrs);
+        }
+        let (name, rest) = s.split_once('=')?;
+        let rest = rest.trim_start();
+        let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value_end = rest[1..].find(quote)? + 1;
+        attrs.push((name.trim(), &rest[1..value_end]));
+        s = &rest[value_end + 1..];
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_iso_durations() {
+        assert_eq!(iso_duration("PT10.5S"), Some(Duration::from_millis(10_500)));
+        assert_eq!(iso_duration("PT1H2M3S"), Some(Duration::from_secs(3723)));
+        assert_eq!(
+            iso_duration("P0Y0M0DT0H1M0.000S"),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(iso_duration("10S"), None);
+        assert_eq!(iso_duration("PT10"), None);
+    }
+
+    #[test]
+    fn reads_single_file_segment_lists() {
+        let mpd = r#"<?xml version="1.0" encoding="utf-8"?>
+<MPD type="static" mediaPresentationDuration="PT8.0S">
+  <Period id="0">
+    <AdaptationSet id="0" mimeType="video/mp4">
+      <Representation id="0" codecs="avc1.64001f" bandwidth="800000" width="640" height="360">
+        <BaseURL>manifest-stream0.mp4</BaseURL>
+        <SegmentList timescale="12800" duration="51200">
+          <Initialization range="0-812" />
+          <SegmentURL mediaRange="813-40211" indexRange="813-856" />
+          <SegmentURL mediaRange="40212-80110" indexRange="40212-40255" />
+        </SegmentList>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+        let manifest = DashManifest::parse(mpd).unwrap();
+        let set = &manifest.adaptation_sets[0];
+        assert_eq!(set.content_type, Some(StreamType::Video));
+        let rep = &set.representations[0];
+        assert_eq!(rep.mime_type.as_deref(), Some("video/mp4"));
+        assert_eq!(rep.base_url.as_deref(), Some("manifest-stream0.mp4"));
+        assert_eq!(rep.segments, Some(2));
+        assert!(DashManifest::parse("<html></html>").is_err());
+    }
+}
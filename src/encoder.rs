@@ -0,0 +1,1061 @@
+//! Typed options for common encoders.
+//!
+//! Each struct renders to the encoder's own flags and checks value ranges
+//! before ffmpeg runs, so a typo in `-x264-params` or an out-of-range
+//! `-cpu-used` surfaces as [`Error::InvalidInput`] instead of an ffmpeg abort.
+//! Attach them with [`TranscodeBuilder::encoder_options`](crate::TranscodeBuilder::encoder_options).
+
+use std::ffi::OsString;
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::rate_control::StreamArgs;
+use crate::types::{CodecType, StreamType};
+
+/// Options for one of the supported encoders.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EncoderOptions {
+    /// `libx264` options.
+    X264(X264Options),
+    /// `libx265` options.
+    X265(X265Options),
+    /// `libvpx-vp9` options.
+    Vp9(Vp9Options),
+    /// `libaom-av1` options.
+    Aom(AomOptions),
+    /// `libsvtav1` options.
+    SvtAv1(SvtAv1Options),
+    /// `aac` / `libfdk_aac` options.
+    Aac(AacOptions),
+    /// `libopus` options.
+    Opus(OpusOptions),
+}
+
+impl EncoderOptions {
+    /// Encoder name passed to `-c:v`/`-c:a`.
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            EncoderOptions::X264(_) => "libx264",
+            EncoderOptions::X265(_) => "libx265",
+            EncoderOptions::Vp9(_) => "libvpx-vp9",
+            EncoderOptions::Aom(_) => "libaom-av1",
+            EncoderOptions::SvtAv1(_) => "libsvtav1",
+            EncoderOptions::Aac(options) if options.fdk => "libfdk_aac",
+            EncoderOptions::Aac(_) => "aac",
+            EncoderOptions::Opus(_) => "libopus",
+        }
+    }
+
+    /// Codec produced by the encoder.
+    pub fn codec(&self) -> CodecType {
+        match self {
+            EncoderOptions::X264(_) => CodecType::H264,
+            EncoderOptions::X265(_) => CodecType::Hevc,
+            EncoderOptions::Vp9(_) => CodecType::Vp9,
+            EncoderOptions::Aom(_) | EncoderOptions::SvtAv1(_) => CodecType::Av1,
+            EncoderOptions::Aac(_) => CodecType::Aac,
+            EncoderOptions::Opus(_) => CodecType::Opus,
+        }
+    }
+
+    /// Whether the options configure the video or the audio encoder.
+    pub fn stream_type(&self) -> StreamType {
+        match self {
+            EncoderOptions::Aac(_) | EncoderOptions::Opus(_) => StreamType::Audio,
+            _ => StreamType::Video,
+        }
+    }
+
+    /// Whether the options set a keyframe interval of their own.
+    pub(crate) fn has_keyint(&self) -> bool {
+        match self {
+            EncoderOptions::X264(options) => options.keyint.or(options.min_keyint).is_some(),
+            EncoderOptions::X265(options) => options.keyint.or(options.min_keyint).is_some(),
+            EncoderOptions::Vp9(options) => options.keyint.or(options.min_keyint).is_some(),
+            EncoderOptions::Aom(options) => options.keyint.is_some(),
+            EncoderOptions::SvtAv1(options) => options.keyint.is_some(),
+            EncoderOptions::Aac(_) | EncoderOptions::Opus(_) => false,
+        }
+    }
+
+    /// Check every configured value against the encoder's accepted range.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            EncoderOptions::X264(options) => options.validate(),
+            EncoderOptions::X265(options) => options.validate(),
+            EncoderOptions::Vp9(options) => options.validate(),
+            EncoderOptions::Aom(options) => options.validate(),
+            EncoderOptions::SvtAv1(options) => options.validate(),
+            EncoderOptions::Aac(options) => options.validate(),
+            EncoderOptions::Opus(options) => options.validate(),
+        }
+    }
+
+    /// Render the flags (without `-c`), applied to streams matching `spec`.
+    pub fn to_args(&self, spec: &str) -> Vec<OsString> {
+        let mut args = StreamArgs::new(spec);
+        match self {
+            EncoderOptions::X264(options) => options.render(&mut args),
+            EncoderOptions::X265(options) => options.render(&mut args),
+            EncoderOptions::Vp9(options) => options.render(&mut args),
+            EncoderOptions::Aom(options) => options.render(&mut args),
+            EncoderOptions::SvtAv1(options) => options.render(&mut args),
+            EncoderOptions::Aac(options) => options.render(&mut args),
+            EncoderOptions::Opus(options) => options.render(&mut args),
+        }
+        args.into_inner()
+    }
+}
+
+/// H.264 profiles accepted by `libx264`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum H264Profile {
+    /// Constrained baseline (no B-frames or CABAC).
+    Baseline,
+    /// Main profile.
+    Main,
+    /// High profile.
+    High,
+    /// High 10 (10-bit).
+    High10,
+    /// High 4:2:2.
+    High422,
+    /// High 4:4:4 Predictive.
+    High444,
+}
+
+impl H264Profile {
+    /// Name used by `-profile:v`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            H264Profile::Baseline => "baseline",
+            H264Profile::Main => "main",
+            H264Profile::High => "high",
+            H264Profile::High10 => "high10",
+            H264Profile::High422 => "high422",
+            H264Profile::High444 => "high444",
+        }
+    }
+}
+
+/// HEVC profiles accepted by `libx265`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HevcProfile {
+    /// Main (8-bit 4:2:0).
+    Main,
+    /// Main 10 (10-bit 4:2:0).
+    Main10,
+    /// Main Still Picture.
+    MainStillPicture,
+    /// Main 4:2:2 10.
+    Main422_10,
+    /// Main 4:4:4 8.
+    Main444_8,
+    /// Main 4:4:4 10.
+    Main444_10,
+}
+
+impl HevcProfile {
+    /// Name used by `-profile:v`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HevcProfile::Main => "main",
+            HevcProfile::Main10 => "main10",
+            HevcProfile::MainStillPicture => "mainstillpicture",
+            HevcProfile::Main422_10 => "main422-10",
+            HevcProfile::Main444_8 => "main444-8",
+            HevcProfile::Main444_10 => "main444-10",
+        }
+    }
+}
+
+/// Content tunings shared by x264 and x265.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tune {
+    /// Live action film (x264 only).
+    Film,
+    /// Animated content.
+    Animation,
+    /// Preserve film grain.
+    Grain,
+    /// Slideshow-like content (x264 only).
+    StillImage,
+    /// Cheaper decoding.
+    FastDecode,
+    /// Low-latency streaming.
+    ZeroLatency,
+    /// Optimise for PSNR benchmarks.
+    Psnr,
+    /// Optimise for SSIM benchmarks.
+    Ssim,
+}
+
+impl Tune {
+    /// Name used by `-tune`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Tune::Film => "film",
+            Tune::Animation => "animation",
+            Tune::Grain => "grain",
+            Tune::StillImage => "stillimage",
+            Tune::FastDecode => "fastdecode",
+            Tune::ZeroLatency => "zerolatency",
+            Tune::Psnr => "psnr",
+            Tune::Ssim => "ssim",
+        }
+    }
+}
+
+impl fmt::Display for Tune {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+const H264_LEVELS: &[&str] = &[
+    "1", "1b", "1.1", "1.2", "1.3", "2", "2.1", "2.2", "3", "3.1", "3.2", "4", "4.1", "4.2", "5",
+    "5.1", "5.2", "6", "6.1", "6.2",
+];
+
+const HEVC_LEVELS: &[&str] = &[
+    "1", "2", "2.1", "3", "3.1", "4", "4.1", "5", "5.1", "5.2", "6", "6.1", "6.2",
+];
+
+/// Options for `libx264`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct X264Options {
+    profile: Option<H264Profile>,
+    level: Option<String>,
+    tune: Option<Tune>,
+    keyint: Option<u32>,
+    min_keyint: Option<u32>,
+    scenecut: Option<u32>,
+    bframes: Option<u32>,
+    refs: Option<u32>,
+    params: Vec<(String, String)>,
+}
+
+impl X264Options {
+    /// Empty option set (encoder defaults).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// H.264 profile.
+    pub fn profile(mut self, profile: H264Profile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// H.264 level such as `4.1`.
+    pub fn level(mut self, level: impl Into<String>) -> Self {
+        self.level = Some(level.into());
+        self
+    }
+
+    /// Content tuning (`stillimage` and `film` are x264-specific).
+    pub fn tune(mut self, tune: Tune) -> Self {
+        self.tune = Some(tune);
+        self
+    }
+
+    /// Maximum GOP length in frames (maps to `-g`).
+    pub fn keyint(mut self, frames: u32) -> Self {
+        self.keyint = Some(frames);
+        self
+    }
+
+    /// Minimum GOP length in frames (maps to `-keyint_min`).
+    pub fn min_keyint(mut self, frames: u32) -> Self {
+        self.min_keyint = Some(frames);
+        self
+    }
+
+    /// Scene-cut sensitivity 0-100; `0` disables scene-cut keyframes.
+    pub fn scenecut(mut self, threshold: u32) -> Self {
+        self.scenecut = Some(threshold);
+        self
+    }
+
+    /// Maximum consecutive B-frames (0-16).
+    pub fn bframes(mut self, frames: u32) -> Self {
+        self.bframes = Some(frames);
+        self
+    }
+
+    /// Reference frames (1-16).
+    pub fn refs(mut self, frames: u32) -> Self {
+        self.refs = Some(frames);
+        self
+    }
+
+    /// Add a raw `-x264-params` entry.
+    pub fn param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.params.push((key.into(), value.into()));
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        check_level("x264", self.level.as_deref(), H264_LEVELS)?;
+        check_range("x264", "scenecut", self.scenecut, 0, 100)?;
+        check_range("x264", "bframes", self.bframes, 0, 16)?;
+        check_range("x264", "refs", self.refs, 1, 16)?;
+        check_keyint("x264", self.keyint, self.min_keyint)?;
+        if self.profile == Some(H264Profile::Baseline) && self.bframes.unwrap_or(0) > 0 {
+            return Err(Error::InvalidInput(
+                "x264 baseline profile does not support B-frames".into(),
+            ));
+        }
+        check_params("x264", &self.params)
+    }
+
+    fn render(&self, args: &mut StreamArgs) {
+        if let Some(profile) = self.profile {
+            args.push("profile", profile.as_str());
+        }
+        if let Some(level) = &self.level {
+            args.push("level", level);
+        }
+        if let Some(tune) = self.tune {
+            args.push("tune", tune);
+        }
+        if let Some(keyint) = self.keyint {
+            args.push("g", keyint);
+        }
+        if let Some(min_keyint) = self.min_keyint {
+            args.push("keyint_min", min_keyint);
+        }
+        if let Some(scenecut) = self.scenecut {
+            args.push("sc_threshold", scenecut);
+        }
+        if let Some(bframes) = self.bframes {
+            args.push("bf", bframes);
+        }
+        if let Some(refs) = self.refs {
+            args.push("refs", refs);
+        }
+        if !self.params.is_empty() {
+            args.push("x264-params", join_params(&self.params));
+        }
+    }
+}
+
+/// Options for `libx265`.
+///
+/// GOP settings are passed through `-x265-params`, which x265 reads directly.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct X265Options {
+    profile: Option<HevcProfile>,
+    level: Option<String>,
+    tune: Option<Tune>,
+    keyint: Option<u32>,
+    min_keyint: Option<u32>,
+    scenecut: Option<u32>,
+    bframes: Option<u32>,
+    refs: Option<u32>,
+    params: Vec<(String, String)>,
+}
+
+impl X265Options {
+    /// Empty option set (encoder defaults).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// HEVC profile.
+    pub fn profile(mut self, profile: HevcProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// HEVC level such as `5.1`.
+    pub fn level(mut self, level: impl Into<String>) -> Self {
+        self.level = Some(level.into());
+        self
+    }
+
+    /// Content tuning (`film` and `stillimage` are rejected).
+    pub fn tune(mut self, tune: Tune) -> Self {
+        self.tune = Some(tune);
+        self
+    }
+
+    /// Maximum GOP length in frames.
+    pub fn keyint(mut self, frames: u32) -> Self {
+        self.keyint = Some(frames);
+        self
+    }
+
+    /// Minimum GOP length in frames.
+    pub fn min_keyint(mut self, frames: u32) -> Self {
+        self.min_keyint = Some(frames);
+        self
+    }
+
+    /// Scene-cut sensitivity 0-100; `0` disables scene-cut keyframes.
+    pub fn scenecut(mut self, threshold: u32) -> Self {
+        self.scenecut = Some(threshold);
+        self
+    }
+
+    /// Maximum consecutive B-frames (0-16).
+    pub fn bframes(mut self, frames: u32) -> Self {
+        self.bframes = Some(frames);
+        self
+    }
+
+    /// Reference frames (1-16).
+    pub fn refs(mut self, frames: u32) -> Self {
+        self.refs = Some(frames);
+        self
+    }
+
+    /// Add a raw `-x265-params` entry.
+    pub fn param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.params.push((key.into(), value.into()));
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        check_level("x265", self.level.as_deref(), HEVC_LEVELS)?;
+        if let Some(tune @ (Tune::Film | Tune::StillImage)) = self.tune {
+            return Err(Error::InvalidInput(format!("x265 has no `{tune}` tune")));
+        }
+        check_range("x265", "scenecut", self.scenecut, 0, 100)?;
+        check_range("x265", "bframes", self.bframes, 0, 16)?;
+        check_range("x265", "refs", self.refs, 1, 16)?;
+        check_keyint("x265", self.keyint, self.min_keyint)?;
+        check_params("x265", &self.params)
+    }
+
+    fn render(&self, args: &mut StreamArgs) {
+        if let Some(profile) = self.profile {
+            args.push("profile", profile.as_str());
+        }
+        if let Some(tune) = self.tune {
+            args.push("tune", tune);
+        }
+        let mut params = Vec::new();
+        let mut param = |key: &str, value: Option<String>| {
+            if let Some(value) = value {
+                params.push((key.to_string(), value));
+            }
+        };
+        param("level-idc", self.level.clone());
+        param("keyint", self.keyint.map(|v| v.to_string()));
+        param("min-keyint", self.min_keyint.map(|v| v.to_string()));
+        param("scenecut", self.scenecut.map(|v| v.to_string()));
+        param("bframes", self.bframes.map(|v| v.to_string()));
+        param("ref", self.refs.map(|v| v.to_string()));
+        params.extend(self.params.iter().cloned());
+        if !params.is_empty() {
+            args.push("x265-params", join_params(&params));
+        }
+    }
+}
+
+/// libvpx quality/speed trade-off.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Deadline {
+    /// Default; use with `cpu_used` 0-5.
+    Good,
+    /// Slowest, best quality.
+    Best,
+    /// Live encoding; use with `cpu_used` 5-8.
+    Realtime,
+}
+
+impl Deadline {
+    /// Name used by `-deadline`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Deadline::Good => "good",
+            Deadline::Best => "best",
+            Deadline::Realtime => "realtime",
+        }
+    }
+}
+
+/// Options for `libvpx-vp9`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Vp9Options {
+    profile: Option<u32>,
+    deadline: Option<Deadline>,
+    cpu_used: Option<i32>,
+    row_mt: Option<bool>,
+    tile_columns: Option<u32>,
+    tile_rows: Option<u32>,
+    lag_in_frames: Option<u32>,
+    keyint: Option<u32>,
+    min_keyint: Option<u32>,
+}
+
+impl Vp9Options {
+    /// Empty option set (encoder defaults).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// VP9 profile 0-3 (1 and 3 carry 4:2:2/4:4:4, 2 and 3 high bit depth).
+    pub fn profile(mut self, profile: u32) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Quality/speed trade-off.
+    pub fn deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Speed preset -8 to 8; higher is faster.
+    pub fn cpu_used(mut self, speed: i32) -> Self {
+        self.cpu_used = Some(speed);
+        self
+    }
+
+    /// Row-based multithreading.
+    pub fn row_mt(mut self, enabled: bool) -> Self {
+        self.row_mt = Some(enabled);
+        self
+    }
+
+    /// Tile columns as log2 (0-6).
+    pub fn tile_columns(mut self, log2: u32) -> Self {
+        self.tile_columns = Some(log2);
+        self
+    }
+
+    /// Tile rows as log2 (0-2).
+    pub fn tile_rows(mut self, log2: u32) -> Self {
+        self.tile_rows = Some(log2);
+        self
+    }
+
+    /// Look-ahead frames (0-25).
+    pub fn lag_in_frames(mut self, frames: u32) -> Self {
+        self.lag_in_frames = Some(frames);
+        self
+    }
+
+    /// Maximum GOP length in frames (maps to `-g`).
+    pub fn keyint(mut self, frames: u32) -> Self {
+        self.keyint = Some(frames);
+        self
+    }
+
+    /// Minimum GOP length in frames (maps to `-keyint_min`).
+    pub fn min_keyint(mut self, frames: u32) -> Self {
+        self.min_keyint = Some(frames);
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        check_range("vp9", "profile", self.profile, 0, 3)?;
+        check_range("vp9", "cpu-used", self.cpu_used, -8, 8)?;
+        check_range("vp9", "tile-columns", self.tile_columns, 0, 6)?;
+        check_range("vp9", "tile-rows", self.tile_rows, 0, 2)?;
+        check_range("vp9", "lag-in-frames", self.lag_in_frames, 0, 25)?;
+        check_keyint("vp9", self.keyint, self.min_keyint)
+    }
+
+    fn render(&self, args: &mut StreamArgs) {
+        if let Some(profile) = self.profile {
+            args.push("profile", profile);
+        }
+        if let Some(deadline) = self.deadline {
+            args.push("deadline", deadline.as_str());
+        }
+        if let Some(cpu_used) = self.cpu_used {
+            args.push("cpu-used", cpu_used);
+        }
+        if let Some(row_mt) = self.row_mt {
+            args.push("row-mt", u8::from(row_mt));
+        }
+        if let Some(tile_columns) = self.tile_columns {
+            args.push("tile-columns", tile_columns);
+        }
+        if let Some(tile_rows) = self.tile_rows {
+            args.push("tile-rows", tile_rows);
+        }
+        if let Some(lag) = self.lag_in_frames {
+            args.push("lag-in-frames", lag);
+        }
+        if let Some(keyint) = self.keyint {
+            args.push("g", keyint);
+        }
+        if let Some(min_keyint) = self.min_keyint {
+            args.push("keyint_min", min_keyint);
+        }
+    }
+}
+
+/// Options for `libaom-av1`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AomOptions {
+    cpu_used: Option<u32>,
+    row_mt: Option<bool>,
+    tile_columns: Option<u32>,
+    tile_rows: Option<u32>,
+    lag_in_frames: Option<u32>,
+    keyint: Option<u32>,
+    params: Vec<(String, String)>,
+}
+
+impl AomOptions {
+    /// Empty option set (encoder defaults).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Speed preset 0-8; higher is faster.
+    pub fn cpu_used(mut self, speed: u32) -> Self {
+        self.cpu_used = Some(speed);
+        self
+    }
+
+    /// Row-based multithreading.
+    pub fn row_mt(mut self, enabled: bool) -> Self {
+        self.row_mt = Some(enabled);
+        self
+    }
+
+    /// Tile columns as log2 (0-6).
+    pub fn tile_columns(mut self, log2: u32) -> Self {
+        self.tile_columns = Some(log2);
+        self
+    }
+
+    /// Tile rows as log2 (0-6).
+    pub fn tile_rows(mut self, log2: u32) -> Self {
+        self.tile_rows = Some(log2);
+        self
+    }
+
+    /// Look-ahead frames (0-70).
+    pub fn lag_in_frames(mut self, frames: u32) -> Self {
+        self.lag_in_frames = Some(frames);
+        self
+    }
+
+    /// Maximum GOP length in frames (maps to `-g`).
+    pub fn keyint(mut self, frames: u32) -> Self {
+        self.keyint = Some(frames);
+        self
+    }
+
+    /// Add a raw `-aom-params` entry.
+    pub fn param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.params.push((key.into(), value.into()));
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        check_range("aom", "cpu-used", self.cpu_used, 0, 8)?;
+        check_range("aom", "tile-columns", self.tile_columns, 0, 6)?;
+        check_range("aom", "tile-rows", self.tile_rows, 0, 6)?;
+        check_range("aom", "lag-in-frames", self.lag_in_frames, 0, 70)?;
+        check_keyint("aom", self.keyint, None)?;
+        check_params("aom", &self.params)
+    }
+
+    fn render(&self, args: &mut StreamArgs) {
+        if let Some(cpu_used) = self.cpu_used {
+            args.push("cpu-used", cpu_used);
+        }
+        if let Some(row_mt) = self.row_mt {
+            args.push("row-mt", u8::from(row_mt));
+        }
+        if let Some(tile_columns) = self.tile_columns {
+            args.push("tile-columns", tile_columns);
+        }
+        if let Some(tile_rows) = self.tile_rows {
+            args.push("tile-rows", tile_rows);
+        }
+        if let Some(lag) = self.lag_in_frames {
+            args.push("lag-in-frames", lag);
+        }
+        if let Some(keyint) = self.keyint {
+            args.push("g", keyint);
+        }
+        if !self.params.is_empty() {
+            args.push("aom-params", join_params(&self.params));
+        }
+    }
+}
+
+/// Options for `libsvtav1`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SvtAv1Options {
+    preset: Option<u32>,
+    keyint: Option<u32>,
+    film_grain: Option<u32>,
+    params: Vec<(String, String)>,
+}
+
+impl SvtAv1Options {
+    /// Empty option set (encoder defaults).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Speed preset 0-13; higher is faster.
+    pub fn preset(mut self, preset: u32) -> Self {
+        self.preset = Some(preset);
+        self
+    }
+
+    /// Maximum GOP length in frames (maps to `-g`).
+    pub fn keyint(mut self, frames: u32) -> Self {
+        self.keyint = Some(frames);
+        self
+    }
+
+    /// Film grain synthesis strength 0-50.
+    pub fn film_grain(mut self, level: u32) -> Self {
+        self.film_grain = Some(level);
+        self
+    }
+
+    /// Add a raw `-svtav1-params` entry.
+    pub fn param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.params.push((key.into(), value.into()));
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        check_range("svt-av1", "preset", self.preset, 0, 13)?;
+        check_range("svt-av1", "film-grain", self.film_grain, 0, 50)?;
+        check_keyint("svt-av1", self.keyint, None)?;
+        check_params("svt-av1", &self.params)
+    }
+
+    fn render(&self, args: &mut StreamArgs) {
+        if let Some(preset) = self.preset {
+            args.push("preset", preset);
+        }
+        if let Some(keyint) = self.keyint {
+            args.push("g", keyint);
+        }
+        let mut params = Vec::new();
+        if let Some(level) = self.film_grain {
+            params.push(("film-grain".to_string(), level.to_string()));
+        }
+        params.extend(self.params.iter().cloned());
+        if !params.is_empty() {
+            args.push("svtav1-params", join_params(&params));
+        }
+    }
+}
+
+/// AAC object types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AacProfile {
+    /// Low Complexity, the common default.
+    Lc,
+    /// High Efficiency (SBR); requires `libfdk_aac`.
+    He,
+    /// High Efficiency v2 (SBR + PS, stereo only); requires `libfdk_aac`.
+    HeV2,
+    /// Low Delay; requires `libfdk_aac`.
+    Ld,
+    /// Enhanced Low Delay; requires `libfdk_aac`.
+    Eld,
+}
+
+impl AacProfile {
+    /// Name used by `-profile:a`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AacProfile::Lc => "aac_low",
+            AacProfile::He => "aac_he",
+            AacProfile::HeV2 => "aac_he_v2",
+            AacProfile::Ld => "aac_ld",
+            AacProfile::Eld => "aac_eld",
+        }
+    }
+}
+
+/// Options for the native `aac` encoder or `libfdk_aac`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AacOptions {
+    fdk: bool,
+    profile: Option<AacProfile>,
+    cutoff: Option<u32>,
+}
+
+impl AacOptions {
+    /// Empty option set for the native `aac` encoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `libfdk_aac` instead of the native encoder.
+    pub fn fdk(mut self, enabled: bool) -> Self {
+        self.fdk = enabled;
+        self
+    }
+
+    /// AAC object type.
+    pub fn profile(mut self, profile: AacProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Low-pass cutoff frequency in Hz (1-20000).
+    pub fn cutoff(mut self, hz: u32) -> Self {
+        self.cutoff = Some(hz);
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self.profile {
+            Some(
+                profile @ (AacProfile::He | AacProfile::HeV2 | AacProfile::Ld | AacProfile::Eld),
+            ) if !self.fdk => Err(Error::InvalidInput(format!(
+                "AAC profile {} requires libfdk_aac; enable AacOptions::fdk",
+                profile.as_str()
+            ))),
+            _ => check_range("aac", "cutoff", self.cutoff, 1, 20_000),
+        }
+    }
+
+    fn render(&self, args: &mut StreamArgs) {
+        if let Some(profile) = self.profile {
+            args.push("profile", profile.as_str());
+        }
+        if let Some(cutoff) = self.cutoff {
+            args.push("cutoff", cutoff);
+        }
+    }
+}
+
+/// libopus tuning target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpusApplication {
+    /// Speech intelligibility.
+    Voip,
+    /// Music and general audio (default).
+    Audio,
+    /// Lowest latency, disabling speech modes.
+    LowDelay,
+}
+
+impl OpusApplication {
+    /// Name used by `-application`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OpusApplication::Voip => "voip",
+            OpusApplication::Audio => "audio",
+            OpusApplication::LowDelay => "lowdelay",
+        }
+    }
+}
+
+/// Frame durations libopus accepts, in milliseconds.
+const OPUS_FRAME_DURATIONS: &[f32] = &[2.5, 5.0, 10.0, 20.0, 40.0, 60.0, 80.0, 100.0, 120.0];
+
+/// Options for `libopus`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OpusOptions {
+    application: Option<OpusApplication>,
+    frame_duration: Option<f32>,
+    compression_level: Option<u32>,
+    packet_loss: Option<u32>,
+}
+
+impl OpusOptions {
+    /// Empty option set (encoder defaults).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tuning target.
+    pub fn application(mut self, application: OpusApplication) -> Self {
+        self.application = Some(application);
+        self
+    }
+
+    /// Frame duration in milliseconds (2.5, 5, 10, 20, 40, 60, 80, 100 or 120).
+    pub fn frame_duration(mut self, ms: f32) -> Self {
+        self.frame_duration = Some(ms);
+        self
+    }
+
+    /// Encoder complexity 0-10; higher is slower and better.
+    pub fn compression_level(mut self, level: u32) -> Self {
+        self.compression_level = Some(level);
+        self
+    }
+
+    /// Expected packet loss percentage (0-100) to tune in-band FEC for.
+    pub fn packet_loss(mut self, percent: u32) -> Self {
+        self.packet_loss = Some(percent);
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(ms) = self.frame_duration {
+            if !OPUS_FRAME_DURATIONS.contains(&ms) {
+                return Err(Error::InvalidInput(format!(
+                    "opus frame duration must be one of 2.5, 5, 10, 20, 40, 60, 80, 100 or 120 ms (got {ms})"
+                )));
+            }
+        }
+        check_range("opus", "compression level", self.compression_level, 0, 10)?;
+        check_range("opus", "packet loss", self.packet_loss, 0, 100)
+    }
+
+    fn render(&self, args: &mut StreamArgs) {
+        if let Some(application) = self.application {
+            args.push("application", application.as_str());
+        }
+        if let Some(ms) = self.frame_duration {
+            args.push("frame_duration", ms);
+        }
+        if let Some(level) = self.compression_level {
+            args.push("compression_level", level);
+        }
+        if let Some(percent) = self.packet_loss {
+            args.push("packet_loss", percent);
+        }
+    }
+}
+
+macro_rules! impl_from_options {
+    ($($options:ident => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$options> for EncoderOptions {
+                fn from(options: $options) -> Self {
+                    EncoderOptions::$variant(options)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_options! {
+    X264Options => X264,
+    X265Options => X265,
+    Vp9Options => Vp9,
+    AomOptions => Aom,
+    SvtAv1Options => SvtAv1,
+    AacOptions => Aac,
+    OpusOptions => Opus,
+}
+
+fn check_range<T>(encoder: &str, name: &str, value: Option<T>, min: T, max: T) -> Result<()>
+where
+    T: PartialOrd + fmt::Display,
+{
+    match value {
+        Some(value) if value < min || value > max => Err(Error::InvalidInput(format!(
+            "{encoder} {name} must be between {min} and {max} (got {value})"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn check_level(encoder: &str, level: Option<&str>, levels: &[&str]) -> Result<()> {
+    match level {
+        Some(level) if !levels.contains(&level) => Err(Error::InvalidInput(format!(
+            "{encoder} level `{level}` is not one of {}",
+            levels.join(", ")
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn check_keyint(encoder: &str, keyint: Option<u32>, min_keyint: Option<u32>) -> Result<()> {
+    if keyint == Some(0) {
+        return Err(Error::InvalidInput(format!(
+            "{encoder} keyint must be at least 1"
+        )));
+    }
+    match (keyint, min_keyint) {
+        (Some(max), Some(min)) if min > max => Err(Error::InvalidInput(format!(
+            "{encoder} min keyint ({min}) exceeds keyint ({max})"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Reject keys and values that would break the `key=value:key=value` syntax.
+fn check_params(encoder: &str, params: &[(String, String)]) -> Result<()> {
+    for (key, value) in params {
+        if key.is_empty() || key.contains([':', '=']) || value.contains(':') {
+            return Err(Error::InvalidInput(format!(
+                "invalid {encoder} parameter `{key}={value}`"
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn join_params(params: &[(String, String)]) -> String {
+    params
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(options: impl Into<EncoderOptions>, spec: &str) -> Vec<String> {
+        options
+            .into()
+            .to_args(spec)
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn renders_x265_gop_through_params() {
+        let options = X265Options::new()
+            .profile(HevcProfile::Main10)
+            .keyint(120)
+            .min_keyint(24)
+            .param("no-open-gop", "1");
+        assert_eq!(
+            render(options, "v"),
+            [
+                "-profile:v",
+                "main10",
+                "-x265-params:v",
+                "keyint=120:min-keyint=24:no-open-gop=1"
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        let invalid: Vec<EncoderOptions> = vec![
+            X264Options::new().bframes(17).into(),
+            X264Options::new().level("4.7").into(),
+            X264Options::new()
+                .profile(H264Profile::Baseline)
+                .bframes(2)
+                .into(),
+            X264Options::new().keyint(24).min_keyint(48).into(),
+            X265Options::new().tune(Tune::Film).into(),
+            Vp9Options::new().cpu_used(9).into(),
+            SvtAv1Options::new().preset(14).into(),
+            AacOptions::new().profile(AacProfile::He).into(),
+            OpusOptions::new().frame_duration(30.0).into(),
+            X264Options::new().param("keyint", "1:ref=3").into(),
+        ];
+        for options in invalid {
+            assert!(
+                options.validate().is_err(),
+                "{options:?} should be rejected"
+            );
+        }
+        let fdk = EncoderOptions::from(AacOptions::new().fdk(true).profile(AacProfile::HeV2));
+        assert!(fdk.validate().is_ok());
+        assert_eq!(fdk.encoder(), "libfdk_aac");
+    }
+}
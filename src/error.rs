@@ -1,7 +1,10 @@
 use std::io;
+use std::time::Duration;
 
 use thiserror::Error;
 
+use crate::logging::split_level;
+
 /// Result alias used throughout the crate.
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -53,6 +56,46 @@ pub enum Error {
     /// Placeholder for functionality that has not yet been implemented.
     #[error("unsupported operation: {0}")]
     Unsupported(String),
+
+    /// An error together with the captured ffmpeg log (see [`LogCapture`](crate::logging::LogCapture)).
+    #[error("{error}")]
+    WithLog {
+        /// The underlying error.
+        error: Box<Error>,
+        /// Last lines of ffmpeg's stderr, oldest first.
+        log: Vec<String>,
+    },
+
+    /// A target-size encode stayed above the limit after every attempt.
+    #[error(
+        "output is {actual} bytes, above the {target} byte target after {attempts} attempt(s)"
+    )]
+    TargetSizeExceeded {
+        /// Requested maximum size in bytes.
+        target: u64,
+        /// Size of the last attempt in bytes.
+        actual: u64,
+        /// Number of encodes that were run.
+        attempts: u32,
+    },
+
+    /// A clipped output does not have the requested length.
+    #[error("output lasts {actual:?} instead of the requested {expected:?}")]
+    DurationMismatch {
+        /// Requested length.
+        expected: Duration,
+        /// Length probed from the output.
+        actual: Duration,
+    },
+
+    /// One pass of a multi-pass encode failed.
+    #[error("pass {pass} failed: {error}")]
+    PassFailed {
+        /// The failing pass (1-based).
+        pass: usize,
+        /// The error reported by that pass.
+        error: Box<Error>,
+    },
 }
 
 impl Error {
@@ -66,9 +109,43 @@ impl Error {
         }
     }
 
+    /// Captured log lines when log capture was enabled.
+    pub fn log(&self) -> Option<&[String]> {
+        match self {
+            Error::WithLog { log, .. } => Some(log),
+            Error::PassFailed { error, .. } => error.log(),
+            _ => None,
+        }
+    }
+
+    /// The pass of a multi-pass encode this error came from.
+    pub fn pass(&self) -> Option<usize> {
+        match self {
+            Error::PassFailed { pass, .. } => Some(*pass),
+            Error::WithLog { error, .. } => error.pass(),
+            _ => None,
+        }
+    }
+
+    /// The error without any attached log.
+    pub fn without_log(&self) -> &Error {
+        match self {
+            Error::WithLog { error, .. } => error.without_log(),
+            other => other,
+        }
+    }
+
+    /// The underlying error, without log or pass context.
+    fn cause(&self) -> &Error {
+        match self {
+            Error::WithLog { error, .. } | Error::PassFailed { error, .. } => error.cause(),
+            other => other,
+        }
+    }
+
     /// Suggestion for resolving this error (if available).
     pub fn suggestion(&self) -> Option<String> {
-        match self {
+        match self.cause() {
             Error::FFmpegNotFound { suggestion } => suggestion.clone(),
             Error::InvalidInput(msg) => {
                 if msg.contains("input path") {
@@ -79,8 +156,17 @@ impl Error {
                     Some("check your parameters".to_string())
                 }
             }
-            Error::ProcessingError { .. } => {
-                Some("check FFmpeg is installed and your parameters are valid".to_string())
+            Error::ProcessingError { message, .. } => match FailureKind::classify(message) {
+                FailureKind::Other => {
+                    Some("check FFmpeg is installed and your parameters are valid".to_string())
+                }
+                kind => Some(kind.suggestion()),
+            },
+            Error::TargetSizeExceeded { .. } => Some(
+                "lower the audio bitrate, shorten the input, or allow more attempts".to_string(),
+            ),
+            Error::DurationMismatch { .. } => {
+                Some("make sure the clip range lies within the input".to_string())
             }
             Error::FilterError(msg) => {
                 if msg.contains("unsupported") || msg.contains("not supported") {
@@ -92,14 +178,281 @@ impl Error {
             _ => None,
         }
     }
+
+    /// Classify a `ProcessingError` by the ffmpeg diagnostics it carries.
+    ///
+    /// Returns `None` for every other variant.
+    pub fn failure_kind(&self) -> Option<FailureKind> {
+        match self.cause() {
+            Error::ProcessingError { message, .. } => Some(FailureKind::classify(message)),
+            _ => None,
+        }
+    }
+
+    /// Like [`failure_kind`](Self::failure_kind), but only reports a missing file as
+    /// [`InputNotFound`](FailureKind::InputNotFound) when its path is one of `inputs`.
+    pub fn failure_kind_with_inputs<S: AsRef<str>>(&self, inputs: &[S]) -> Option<FailureKind> {
+        match self.cause() {
+            Error::ProcessingError { message, .. } => {
+                Some(FailureKind::classify_with_inputs(message, inputs))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether running the same job again could succeed without changes.
+    pub fn is_retryable(&self) -> bool {
+        match self.cause() {
+            Error::TimeoutError(_) => true,
+            Error::ProcessingError { message, .. } => FailureKind::classify(message).is_retryable(),
+            Error::Io(err) => matches!(
+                err.kind(),
+                io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ),
+            _ => false,
+        }
+    }
 }
 
+/// Cause of an ffmpeg failure, recognised from its stderr output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FailureKind {
+    /// The input file does not exist.
+    InputNotFound {
+        /// Path reported by ffmpeg, when it could be extracted.
+        path: Option<String>,
+    },
+    /// A file could not be opened due to missing permissions.
+    PermissionDenied {
+        /// Path reported by ffmpeg, when it could be extracted.
+        path: Option<String>,
+    },
+    /// The requested encoder is not part of this ffmpeg build.
+    UnknownEncoder {
+        /// Encoder or codec name, when it could be extracted.
+        name: Option<String>,
+    },
+    /// The input is corrupt or not a media file ffmpeg understands.
+    InvalidData,
+    /// The output container cannot store one of the selected codecs.
+    UnsupportedCodecForContainer {
+        /// Offending codec, when it could be extracted.
+        codec: Option<String>,
+    },
+    /// A filter graph failed to parse or initialize.
+    FilterGraph {
+        /// Offending filter, when it could be extracted.
+        filter: Option<String>,
+    },
+    /// The output device ran out of space.
+    DiskFull,
+    /// The output exists and overwriting was disabled (`-n`).
+    OutputExists,
+    /// A network input or output failed (connection refused, 5xx, DNS, ...).
+    Network,
+    /// Anything not recognised above.
+    Other,
+}
+
+impl FailureKind {
+    /// Classify ffmpeg stderr output.
+    ///
+    /// Lines may carry the `[error]`-style tags printed with `-loglevel level+info`.
+    /// A missing file is reported as [`InputNotFound`](Self::InputNotFound) unless
+    /// ffmpeg says it failed to open an output; older builds print only
+    /// `path: No such file or directory`, which is taken to be an input. Use
+    /// [`classify_with_inputs`](Self::classify_with_inputs) when the inputs are known.
+    pub fn classify(stderr: &str) -> Self {
+        Self::classify_paths(stderr, None)
+    }
+
+    /// Classify ffmpeg stderr output, reporting a missing file as
+    /// [`InputNotFound`](Self::InputNotFound) only when its path is one of `inputs`.
+    pub fn classify_with_inputs<S: AsRef<str>>(stderr: &str, inputs: &[S]) -> Self {
+        let inputs: Vec<&str> = inputs.iter().map(AsRef::as_ref).collect();
+        Self::classify_paths(stderr, Some(&inputs))
+    }
+
+    fn classify_paths(stderr: &str, inputs: Option<&[&str]>) -> Self {
+        let stripped: Vec<String> = stderr
+            .lines()
+            .map(|line| split_level(line).map_or_else(|| line.to_string(), |(_, line)| line))
+            .collect();
+        let stderr = stripped.join("\n");
+        let stderr = stderr.as_str();
+        let find = |needle: &str| stderr.lines().find(|line| line.contains(needle));
+
+        if find("already exists. Exiting").is_some() || find("Not overwriting").is_some() {
+            return FailureKind::OutputExists;
+        }
+        if find("No space left on device").is_some() {
+            return FailureKind::DiskFull;
+        }
+        if let Some(line) = find("Permission denied") {
+            return FailureKind::PermissionDenied {
+                path: path_before(line, ": Permission denied"),
+            };
+        }
+        if let Some(line) = find("Unknown encoder") {
+            return FailureKind::UnknownEncoder { name: quoted(line) };
+        }
+        if let Some(line) = find("Encoder (codec ") {
+            let name = between(line, "Encoder (codec ", ")");
+            return FailureKind::UnknownEncoder { name };
+        }
+        if let Some(line) = find("Could not find tag for codec") {
+            let codec = between(line, "Could not find tag for codec ", " ");
+            return FailureKind::UnsupportedCodecForContainer { codec };
+        }
+        if find("not currently supported in container").is_some() {
+            return FailureKind::UnsupportedCodecForContainer { codec: None };
+        }
+        if let Some(line) = find("No such filter:") {
+            return FailureKind::FilterGraph {
+                filter: quoted(line),
+            };
+        }
+        if let Some(line) = find("Error initializing filter") {
+            return FailureKind::FilterGraph {
+                filter: quoted(line),
+            };
+        }
+        let graph = [
+            "Error parsing filterchain",
+            "Error parsing a filter description",
+            "Error reinitializing filters",
+            "Error initializing complex filters",
+        ];
+        if graph.iter().any(|needle| find(needle).is_some()) {
+            return FailureKind::FilterGraph {
+                filter: stderr.lines().find_map(parsed_filter_name),
+            };
+        }
+        let network = [
+            "Connection refused",
+            "Connection timed out",
+            "Connection reset by peer",
+            "Server returned 5",
+            "Temporary failure in name resolution",
+            "Resource temporarily unavailable",
+        ];
+        if network.iter().any(|needle| find(needle).is_some()) {
+            return FailureKind::Network;
+        }
+        if let Some(line) = find("No such file or directory") {
+            // Missing output directories and segment files look the same.
+            let opened = stderr
+                .lines()
+                .find_map(|line| line.split_once("Error opening input file "))
+                .map(|(_, path)| path.trim_end().trim_end_matches('.').to_string());
+            let path = opened
+                .clone()
+                .or_else(|| path_before(line, ": No such file or directory"));
+            let is_input = match inputs {
+                Some(inputs) => path.as_deref().is_some_and(|path| inputs.contains(&path)),
+                None => opened.is_some() || find("Error opening output").is_none(),
+            };
+            if is_input {
+                return FailureKind::InputNotFound { path };
+            }
+        }
+        let corrupt = [
+            "Invalid data found when processing input",
+            "moov atom not found",
+            "EBML header parsing failed",
+        ];
+        if corrupt.iter().any(|needle| find(needle).is_some()) {
+            return FailureKind::InvalidData;
+        }
+        FailureKind::Other
+    }
+
+    /// Human readable hint for resolving the failure.
+    pub fn suggestion(&self) -> String {
+        match self {
+            FailureKind::InputNotFound { path } => match path {
+                Some(path) => format!("check that `{path}` exists and the path is correct"),
+                None => "check that the input file exists and the path is correct".to_string(),
+            },
+            FailureKind::PermissionDenied { .. } => {
+                "check read permissions on the input and write permissions on the output directory"
+                    .to_string()
+            }
+            FailureKind::UnknownEncoder { name } => format!(
+                "encoder {}is not installed in this ffmpeg build; pick one listed by `ffmpeg -encoders` or install a build that includes it",
+                name.as_deref().map(|name| format!("`{name}` ")).unwrap_or_default()
+            ),
+            FailureKind::InvalidData => {
+                "the input looks corrupt or is not a media file; verify it plays and was fully downloaded".to_string()
+            }
+            FailureKind::UnsupportedCodecForContainer { .. } => {
+                "choose a codec the output container supports or change the output extension"
+                    .to_string()
+            }
+            FailureKind::FilterGraph { filter } => match filter {
+                Some(filter) => format!("review the syntax and parameters of the `{filter}` filter"),
+                None => "review filter parameters and syntax".to_string(),
+            },
+            FailureKind::DiskFull => "free up disk space on the output device and retry".to_string(),
+            FailureKind::OutputExists => {
+                "remove the existing output or enable overwrite(true)".to_string()
+            }
+            FailureKind::Network => {
+                "the network resource was unreachable; retry later or check the URL".to_string()
+            }
+            FailureKind::Other => {
+                "check FFmpeg is installed and your parameters are valid".to_string()
+            }
+        }
+    }
+
+    /// Whether the failure is transient, i.e. retrying the same job may succeed.
+    ///
+    /// A full disk is not: the job only succeeds once space has been freed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, FailureKind::Network)
+    }
+}
+
+/// First single-quoted token in `line`.
+fn quoted(line: &str) -> Option<String> {
+    between(line, "'", "'")
+}
+
+fn between(line: &str, start: &str, end: &str) -> Option<String> {
+    let rest = &line[line.find(start)? + start.len()..];
+    let value = &rest[..rest.find(end).unwrap_or(rest.len())];
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Path from lines such as `input.mp4: No such file or directory`.
+fn path_before(line: &str, suffix: &str) -> Option<String> {
+    let path = line[..line.find(suffix)?].trim();
+    // Lines starting with a `[context @ 0x..]` prefix do not carry a path.
+    (!path.is_empty() && !path.starts_with('[')).then(|| path.to_string())
+}
+
+/// Filter name from log prefixes such as `[Parsed_scale_0 @ 0x55d0]`.
+fn parsed_filter_name(line: &str) -> Option<String> {
+    let rest = &line[line.find("[Parsed_")? + "[Parsed_".len()..];
+    let name = rest.split_whitespace().next()?;
+    let (name, index) = name.rsplit_once('_')?;
+    index
+        .chars()
+        .all(|c| c.is_ascii_digit())
+        .then(|| name.to_string())
+}
+
+/// Keep the tail of stderr: ffmpeg prints the fatal error last.
 fn truncate(message: &[u8]) -> String {
     const MAX: usize = 4096;
-    let mut text = String::from_utf8_lossy(message).into_owned();
-    if text.len() > MAX {
-        text.truncate(MAX);
-        text.push_str("…");
+    let text = String::from_utf8_lossy(message);
+    if text.len() <= MAX {
+        return text.into_owned();
+    }
+    let mut start = text.len() - MAX;
+    while !text.is_char_boundary(start) {
+        start += 1;
     }
-    text
+    format!("…{}", &text[start..])
 }
@@ -1,44 +1,61 @@
-//! Video and audio filter definitions. Filters can be composed into chains for complex processing.
+//! Video and audio filter definitions. Filters can be composed into chains for complex processing,
+//! or into a [`FilterGraph`] for `-filter_complex`.
 
+use std::ffi::OsString;
 use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-use crate::types::Time;
+use crate::error::{Error, Result};
+use crate::types::{StreamType, Time};
 
 /// Video filters for common editing tasks.
 #[derive(Clone, Debug, PartialEq)]
 pub enum VideoFilter {
     /// Scale video to the provided width/height. Use -1 for one dimension to preserve aspect ratio.
     Scale {
+        /// Output width in pixels.
         width: u32,
+        /// Output height in pixels.
         height: u32,
     },
     /// Trim video between start and optional end timestamps.
     Trim {
+        /// Start of the kept range.
         start: Time,
+        /// Optional end of the kept range.
         end: Option<Time>,
     },
     /// Crop video to specified dimensions at given offset.
     Crop {
+        /// Width of the cropped region.
         width: u32,
+        /// Height of the cropped region.
         height: u32,
+        /// Horizontal offset of the region.
         x: u32,
+        /// Vertical offset of the region.
         y: u32,
     },
     /// Rotate video by angle in degrees. Typically 90, 180, or 270.
     Rotate {
+        /// Rotation angle in degrees.
         degrees: f64,
     },
     /// Flip video horizontally or vertically. 'h' for horizontal, 'v' for vertical.
     Flip {
+        /// Flip direction (`'h'` or `'v'`).
         direction: char,
     },
     /// Adjust brightness and contrast. Brightness range: -1.0 to 1.0, Contrast: 0.0 to 2.0.
     BrightnessContrast {
+        /// Brightness adjustment.
         brightness: Option<f32>,
+        /// Contrast multiplier.
         contrast: Option<f32>,
     },
     /// Remove noise with denoise filter (light, medium, heavy).
     Denoise {
+        /// Denoise preset strength.
         strength: DenoiseStrength,
     },
     /// Deinterlace interlaced video (useful for old TV recordings).
@@ -50,13 +67,16 @@ pub enum VideoFilter {
 /// Denoise filter strength options.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DenoiseStrength {
+    /// Subtle noise reduction.
     Light,
+    /// Balanced noise reduction.
     Medium,
+    /// Aggressive noise reduction.
     Heavy,
 }
 
 impl DenoiseStrength {
-    fn to_filter_value(&self) -> &'static str {
+    fn to_filter_value(self) -> &'static str {
         match self {
             DenoiseStrength::Light => "hqdn3d=1.5:1.5:6:6",
             DenoiseStrength::Medium => "hqdn3d=3:3:6:6",
@@ -74,7 +94,12 @@ impl VideoFilter {
                 Some(end) => format!("trim=start={start}:end={end}"),
                 None => format!("trim=start={start}"),
             },
-            VideoFilter::Crop { width, height, x, y } => {
+            VideoFilter::Crop {
+                width,
+                height,
+                x,
+                y,
+            } => {
                 format!("crop={width}:{height}:{x}:{y}")
             }
             VideoFilter::Rotate { degrees } => {
@@ -124,20 +149,26 @@ pub enum AudioFilter {
     Volume(f32),
     /// Equalization with bass, mid, and treble adjustments in dB.
     Equalizer {
+        /// Bass gain in dB.
         bass: Option<f32>,
+        /// Mid gain in dB.
         mid: Option<f32>,
+        /// Treble gain in dB.
         treble: Option<f32>,
     },
     /// Normalize audio to prevent clipping. Target level in dBFS.
     Normalization {
+        /// Integrated loudness target.
         target_level: f32,
     },
     /// High-pass filter to remove low frequencies. Frequency in Hz.
     HighPass {
+        /// Cutoff frequency in Hz.
         frequency: f32,
     },
     /// Low-pass filter to remove high frequencies. Frequency in Hz.
     LowPass {
+        /// Cutoff frequency in Hz.
         frequency: f32,
     },
     /// Custom audio filter for advanced use-cases. FFmpeg syntax.
@@ -185,3 +216,647 @@ impl fmt::Display for AudioFilter {
         write!(f, "{}", self.to_filter_string())
     }
 }
+
+/// A node of a [`FilterGraph`], typed by the pads it consumes and produces.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GraphFilter {
+    /// One video filter (or `,`-chain) with a single video input and output.
+    Video(VideoFilter),
+    /// One audio filter (or `,`-chain) with a single audio input and output.
+    Audio(AudioFilter),
+    /// Duplicate a video stream into `n` outputs.
+    Split(usize),
+    /// Duplicate an audio stream into `n` outputs.
+    ASplit(usize),
+    /// Draw the second video input over the first at the given position expressions.
+    Overlay {
+        /// Horizontal position, e.g. `W-w-10`.
+        x: String,
+        /// Vertical position, e.g. `10`.
+        y: String,
+    },
+    /// Place `n` video inputs side by side.
+    HStack(usize),
+    /// Place `n` video inputs on top of each other.
+    VStack(usize),
+    /// Mix `n` audio inputs into one.
+    AMix(usize),
+    /// Join `segments` segments of `video` video and `audio` audio streams each.
+    Concat {
+        /// Number of segments to join.
+        segments: usize,
+        /// Video streams per segment.
+        video: usize,
+        /// Audio streams per segment.
+        audio: usize,
+    },
+    /// Any other filter, with its pads declared explicitly. FFmpeg syntax.
+    Custom {
+        /// Filter string, e.g. `amerge=inputs=2`.
+        filter: String,
+        /// Kinds of the consumed pads, in order.
+        inputs: Vec<StreamType>,
+        /// Kinds of the produced pads, in order.
+        outputs: Vec<StreamType>,
+    },
+}
+
+impl GraphFilter {
+    /// Kinds of the pads the filter consumes, in order.
+    pub fn input_kinds(&self) -> Vec<StreamType> {
+        match self {
+            GraphFilter::Video(_) | GraphFilter::Split(_) => vec![StreamType::Video],
+            GraphFilter::Audio(_) | GraphFilter::ASplit(_) => vec![StreamType::Audio],
+            GraphFilter::Overlay { .. } => vec![StreamType::Video; 2],
+            GraphFilter::HStack(n) | GraphFilter::VStack(n) => vec![StreamType::Video; *n],
+            GraphFilter::AMix(n) => vec![StreamType::Audio; *n],
+            GraphFilter::Concat {
+                segments,
+                video,
+                audio,
+            } => (0..*segments)
+                .flat_map(|_| segment_kinds(*video, *audio))
+                .collect(),
+            GraphFilter::Custom { inputs, .. } => inputs.clone(),
+        }
+    }
+
+    /// Kinds of the pads the filter produces, in order.
+    pub fn output_kinds(&self) -> Vec<StreamType> {
+        match self {
+            GraphFilter::Video(_)
+            | GraphFilter::Overlay { .. }
+            | GraphFilter::HStack(_)
+            | GraphFilter::VStack(_) => vec![StreamType::Video],
+            GraphFilter::Audio(_) | GraphFilter::AMix(_) => vec![StreamType::Audio],
+            GraphFilter::Split(n) => vec![StreamType::Video; *n],
+            GraphFilter::ASplit(n) => vec![StreamType::Audio; *n],
+            GraphFilter::Concat { video, audio, .. } => segment_kinds(*video, *audio),
+            GraphFilter::Custom { outputs, .. } => outputs.clone(),
+        }
+    }
+
+    /// Convert the node to its FFmpeg filter string, without pads.
+    pub fn to_filter_string(&self) -> String {
+        match self {
+            GraphFilter::Video(filter) => filter.to_filter_string(),
+            GraphFilter::Audio(filter) => filter.to_filter_string(),
+            GraphFilter::Split(n) => format!("split={n}"),
+            GraphFilter::ASplit(n) => format!("asplit={n}"),
+            GraphFilter::Overlay { x, y } => format!("overlay={x}:{y}"),
+            GraphFilter::HStack(n) => format!("hstack=inputs={n}"),
+            GraphFilter::VStack(n) => format!("vstack=inputs={n}"),
+            GraphFilter::AMix(n) => format!("amix=inputs={n}"),
+            GraphFilter::Concat {
+                segments,
+                video,
+                audio,
+            } => format!("concat=n={segments}:v={video}:a={audio}"),
+            GraphFilter::Custom { filter, .. } => filter.clone(),
+        }
+    }
+
+    /// Reject pad counts ffmpeg would refuse.
+    fn check(&self) -> Result<()> {
+        let (minimum, count) = match self {
+            GraphFilter::Split(n) | GraphFilter::ASplit(n) | GraphFilter::AMix(n) => (1, *n),
+            GraphFilter::HStack(n) | GraphFilter::VStack(n) => (2, *n),
+            GraphFilter::Concat {
+                segments,
+                video,
+                audio,
+            } if *video + *audio > 0 => (1, *segments),
+            GraphFilter::Concat { .. } => (1, 0),
+            GraphFilter::Custom { filter, .. } if filter.trim().is_empty() => (1, 0),
+            _ => return Ok(()),
+        };
+        if count < minimum {
+            return Err(Error::FilterError(format!(
+                "`{self}` needs at least {minimum} pad(s)"
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for GraphFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_filter_string())
+    }
+}
+
+impl From<VideoFilter> for GraphFilter {
+    fn from(filter: VideoFilter) -> Self {
+        GraphFilter::Video(filter)
+    }
+}
+
+impl From<AudioFilter> for GraphFilter {
+    fn from(filter: AudioFilter) -> Self {
+        GraphFilter::Audio(filter)
+    }
+}
+
+fn segment_kinds(video: usize, audio: usize) -> Vec<StreamType> {
+    let mut kinds = vec![StreamType::Video; video];
+    kinds.extend(vec![StreamType::Audio; audio]);
+    kinds
+}
+
+/// A labeled edge of a [`FilterGraph`]: an input stream or a node output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pad {
+    label: String,
+    kind: StreamType,
+    origin: PadOrigin,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum PadOrigin {
+    Input(usize),
+    Node { graph: u64, node: usize },
+}
+
+impl Pad {
+    /// Label of the pad without brackets, e.g. `0:v` or `v3`.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Kind of stream carried by the pad.
+    pub fn kind(&self) -> &StreamType {
+        &self.kind
+    }
+
+    /// Input file the pad reads from, if it is an input stream.
+    pub fn input_index(&self) -> Option<usize> {
+        match self.origin {
+            PadOrigin::Input(index) => Some(index),
+            PadOrigin::Node { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Pad {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}]", self.label)
+    }
+}
+
+static NEXT_GRAPH_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A `-filter_complex` graph built from typed nodes joined by labeled pads.
+///
+/// Input streams and node outputs are [`Pad`]s. Every node output must be
+/// consumed by exactly one other node or mapped to the output file; use
+/// [`GraphFilter::Split`] to feed one stream to several nodes.
+///
+/// ```rust
+/// use ffmpeg_light::filter::{FilterGraph, GraphFilter, VideoFilter};
+/// use ffmpeg_light::types::StreamType;
+///
+/// let mut graph = FilterGraph::new();
+/// let main = graph.input(0, StreamType::Video);
+/// let logo = graph.input(1, StreamType::Video);
+/// let small = graph.apply(VideoFilter::Scale { width: 120, height: 68 }, &[&logo])?;
+/// let video = graph.apply(
+///     GraphFilter::Overlay { x: "W-w-10".into(), y: "10".into() },
+///     &[&main, &small],
+/// )?;
+/// graph.map(&video);
+/// assert_eq!(
+///     graph.to_filter_string()?,
+///     "[1:v]scale=120:68[v0];[0:v][v0]overlay=W-w-10:10[v1]"
+/// );
+/// # Ok::<(), ffmpeg_light::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct FilterGraph {
+    id: u64,
+    nodes: Vec<GraphNode>,
+    outputs: Vec<Pad>,
+    labels: usize,
+}
+
+#[derive(Clone, Debug)]
+struct GraphNode {
+    filter: GraphFilter,
+    inputs: Vec<Pad>,
+    outputs: Vec<Pad>,
+}
+
+impl FilterGraph {
+    /// Create an empty graph.
+    pub fn new() -> Self {
+        Self {
+            id: NEXT_GRAPH_ID.fetch_add(1, Ordering::Relaxed),
+            nodes: Vec::new(),
+            outputs: Vec::new(),
+            labels: 0,
+        }
+    }
+
+    /// The first stream of `kind` in input file `input` (`[0:v]`).
+    pub fn input(&self, input: usize, kind: StreamType) -> Pad {
+        let label = format!("{input}:{}", kind_specifier(&kind));
+        Pad {
+            label,
+            kind,
+            origin: PadOrigin::Input(input),
+        }
+    }
+
+    /// The `index`-th stream of `kind` in input file `input` (`[0:a:1]`).
+    pub fn input_stream(&self, input: usize, kind: StreamType, index: usize) -> Pad {
+        let label = format!("{input}:{}:{index}", kind_specifier(&kind));
+        Pad {
+            label,
+            kind,
+            origin: PadOrigin::Input(input),
+        }
+    }
+
+    /// Add a node fed by `inputs` and return its output pads, in order.
+    pub fn add(&mut self, filter: impl Into<GraphFilter>, inputs: &[&Pad]) -> Vec<Pad> {
+        self.push(filter.into(), inputs)
+    }
+
+    /// Add a node with exactly one output and return that pad.
+    ///
+    /// Filters with any other number of outputs are rejected without being
+    /// added; use [`add`](Self::add) for them.
+    pub fn apply(&mut self, filter: impl Into<GraphFilter>, inputs: &[&Pad]) -> Result<Pad> {
+        let filter = filter.into();
+        let count = filter.output_kinds().len();
+        if count != 1 {
+            return Err(Error::FilterError(format!(
+                "`{filter}` has {count} output pads; add it with add()"
+            )));
+        }
+        Ok(self.push(filter, inputs).swap_remove(0))
+    }
+
+    /// Send a node output to the output file (`-map [label]`), in call order.
+    pub fn map(&mut self, pad: &Pad) {
+        self.outputs.push(pad.clone());
+    }
+
+    /// Pads mapped to the output file.
+    pub fn outputs_ref(&self) -> &[Pad] {
+        &self.outputs
+    }
+
+    /// Check pad kinds and counts, and that every node output is used exactly once.
+    pub fn validate(&self) -> Result<()> {
+        if self.outputs.is_empty() {
+            return Err(Error::FilterError(
+                "filter graph has no mapped outputs".into(),
+            ));
+        }
+        let mut uses: Vec<Vec<usize>> = self
+            .nodes
+            .iter()
+            .map(|node| vec![0; node.outputs.len()])
+            .collect();
+        for node in &self.nodes {
+            node.filter.check()?;
+            let expected = node.filter.input_kinds();
+            if node.inputs.len() != expected.len() {
+                return Err(Error::FilterError(format!(
+                    "`{}` takes {} input pad(s) but {} were given",
+                    node.filter,
+                    expected.len(),
+                    node.inputs.len()
+                )));
+            }
+            for (position, (pad, kind)) in node.inputs.iter().zip(&expected).enumerate() {
+                if pad.kind != *kind {
+                    return Err(Error::FilterError(format!(
+                        "`{}` input {} expects {} but {pad} is {}",
+                        node.filter,
+                        position + 1,
+                        kind_name(kind),
+                        kind_name(&pad.kind)
+                    )));
+                }
+                self.count_use(&mut uses, pad)?;
+            }
+            if let Some(pad) = node
+                .outputs
+                .iter()
+                .find(|pad| !matches!(pad.kind, StreamType::Video | StreamType::Audio))
+            {
+                return Err(Error::FilterError(format!(
+                    "`{}` output {pad} is {}; only video and audio pads are supported",
+                    node.filter,
+                    kind_name(&pad.kind)
+                )));
+            }
+        }
+        for pad in &self.outputs {
+            if pad.input_index().is_some() {
+                return Err(Error::FilterError(format!(
+                    "{pad} is an input stream; map it with StreamMap instead"
+                )));
+            }
+            self.count_use(&mut uses, pad)?;
+        }
+        for (node, counts) in self.nodes.iter().zip(&uses) {
+            for (pad, count) in node.outputs.iter().zip(counts) {
+                match count {
+                    1 => {}
+                    0 => {
+                        return Err(Error::FilterError(format!(
+                            "{pad} of `{}` is neither consumed nor mapped",
+                            node.filter
+                        )))
+                    }
+                    n => {
+                        return Err(Error::FilterError(format!(
+                            "{pad} of `{}` is used {n} times; split it first",
+                            node.filter
+                        )))
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate the graph and render it for `-filter_complex`.
+    pub fn to_filter_string(&self) -> Result<String> {
+        self.validate()?;
+        let chains: Vec<String> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let pads = |pads: &[Pad]| pads.iter().map(Pad::to_string).collect::<String>();
+                format!(
+                    "{}{}{}",
+                    pads(&node.inputs),
+                    node.filter,
+                    pads(&node.outputs)
+                )
+            })
+            .collect();
+        Ok(chains.join(";"))
+    }
+
+    /// Validate the graph and render `-filter_complex` plus one `-map` per mapped pad.
+    pub fn to_args(&self) -> Result<Vec<OsString>> {
+        let mut args = vec![
+            OsString::from("-filter_complex"),
+            OsString::from(self.to_filter_string()?),
+        ];
+        for pad in &self.outputs {
+            args.push("-map".into());
+            args.push(pad.to_string().into());
+        }
+        Ok(args)
+    }
+
+    /// Highest input file index referenced by the graph.
+    pub(crate) fn max_input_index(&self) -> Option<usize> {
+        self.nodes
+            .iter()
+            .flat_map(|node| &node.inputs)
+            .filter_map(Pad::input_index)
+            .max()
+    }
+
+    fn push(&mut self, filter: GraphFilter, inputs: &[&Pad]) -> Vec<Pad> {
+        let node = self.nodes.len();
+        let outputs: Vec<Pad> = filter
+            .output_kinds()
+            .into_iter()
+            .map(|kind| {
+                let prefix = match kind {
+                    StreamType::Video => "v",
+                    StreamType::Audio => "a",
+                    StreamType::Subtitle | StreamType::Data => "x",
+                };
+                let label = format!("{prefix}{}", self.labels);
+                self.labels += 1;
+                Pad {
+                    label,
+                    kind,
+                    origin: PadOrigin::Node {
+                        graph: self.id,
+                        node,
+                    },
+                }
+            })
+            .collect();
+        self.nodes.push(GraphNode {
+            filter,
+            inputs: inputs.iter().map(|pad| (*pad).clone()).collect(),
+            outputs: outputs.clone(),
+        });
+        outputs
+    }
+
+    /// Record one use of `pad`, which must be an input stream or an output of this graph.
+    fn count_use(&self, uses: &mut [Vec<usize>], pad: &Pad) -> Result<()> {
+        match pad.origin {
+            PadOrigin::Input(_) if matches!(pad.kind, StreamType::Video | StreamType::Audio) => {
+                Ok(())
+            }
+            PadOrigin::Input(_) => Err(Error::FilterError(format!(
+                "{pad} is a {} stream; only video and audio pads are supported",
+                kind_name(&pad.kind)
+            ))),
+            PadOrigin::Node { graph, node } => {
+                let slot = (graph == self.id)
+                    .then(|| self.nodes.get(node))
+                    .flatten()
+                    .and_then(|owner| owner.outputs.iter().position(|out| out == pad));
+                match slot {
+                    Some(index) => {
+                        uses[node][index] += 1;
+                        Ok(())
+                    }
+                    None => Err(Error::FilterError(format!(
+                        "{pad} does not belong to this filter graph"
+                    ))),
+                }
+            }
+        }
+    }
+}
+
+impl Clone for FilterGraph {
+    /// Copy the graph under a fresh id, so pads of the copy are not accepted
+    /// by the original and vice versa.
+    fn clone(&self) -> Self {
+        let id = NEXT_GRAPH_ID.fetch_add(1, Ordering::Relaxed);
+        let adopt = |pad: &Pad| {
+            let mut pad = pad.clone();
+            if let PadOrigin::Node { graph, .. } = &mut pad.origin {
+                if *graph == self.id {
+                    *graph = id;
+                }
+            }
+            pad
+        };
+        let adopt_all = |pads: &[Pad]| pads.iter().map(adopt).collect();
+        Self {
+            id,
+            nodes: self
+                .nodes
+                .iter()
+                .map(|node| GraphNode {
+                    filter: node.filter.clone(),
+                    inputs: adopt_all(&node.inputs),
+                    outputs: adopt_all(&node.outputs),
+                })
+                .collect(),
+            outputs: adopt_all(&self.outputs),
+            labels: self.labels,
+        }
+    }
+}
+
+impl Default for FilterGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn kind_specifier(kind: &StreamType) -> &'static str {
+    match kind {
+        StreamType::Video => "v",
+        StreamType::Audio => "a",
+        StreamType::Subtitle => "s",
+        StreamType::Data => "d",
+    }
+}
+
+fn kind_name(kind: &StreamType) -> &'static str {
+    match kind {
+        StreamType::Video => "video",
+        StreamType::Audio => "audio",
+        StreamType::Subtitle => "subtitle",
+        StreamType::Data => "data",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_split_and_merge() {
+        let mut graph = FilterGraph::new();
+        let video = graph.input(0, StreamType::Video);
+        let [left, right] = <[Pad; 2]>::try_from(graph.add(GraphFilter::Split(2), &[&video]))
+            .expect("split has two outputs");
+        let mirrored = graph
+            .apply(VideoFilter::Flip { direction: 'h' }, &[&right])
+            .unwrap();
+        let stacked = graph
+            .apply(GraphFilter::HStack(2), &[&left, &mirrored])
+            .unwrap();
+        let voice = graph.input(0, StreamType::Audio);
+        let music = graph.input_stream(1, StreamType::Audio, 1);
+        let quiet = graph.apply(AudioFilter::Volume(0.3), &[&music]).unwrap();
+        let mixed = graph
+            .apply(GraphFilter::AMix(2), &[&voice, &quiet])
+            .unwrap();
+        graph.map(&stacked);
+        graph.map(&mixed);
+
+        assert_eq!(
+            graph.to_filter_string().unwrap(),
+            "[0:v]split=2[v0][v1];[v1]hflip[v2];[v0][v2]hstack=inputs=2[v3];\
+             [1:a:1]volume=0.3[a4];[0:a][a4]amix=inputs=2[a5]"
+        );
+        assert_eq!(graph.max_input_index(), Some(1));
+        assert_eq!(
+            graph.to_args().unwrap()[2..],
+            ["-map", "[v3]", "-map", "[a5]"]
+        );
+    }
+
+    #[test]
+    fn rejects_dangling_and_mistyped_pads() {
+        let mut graph = FilterGraph::new();
+        let video = graph.input(0, StreamType::Video);
+        let audio = graph.input(0, StreamType::Audio);
+        let outputs = graph.add(GraphFilter::Split(2), &[&video]);
+        graph.map(&outputs[0]);
+        let err = graph.validate().unwrap_err().to_string();
+        assert!(
+            err.contains("[v1] of `split=2` is neither consumed nor mapped"),
+            "{err}"
+        );
+
+        graph.map(&outputs[1]);
+        graph.map(&outputs[1]);
+        assert!(graph
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("used 2 times"));
+
+        let mut graph = FilterGraph::new();
+        let wrong = graph
+            .apply(
+                GraphFilter::Overlay {
+                    x: "0".into(),
+                    y: "0".into(),
+                },
+                &[&video, &audio],
+            )
+            .unwrap();
+        graph.map(&wrong);
+        let err = graph.validate().unwrap_err().to_string();
+        assert!(
+            err.contains("input 2 expects video but [0:a] is audio"),
+            "{err}"
+        );
+
+        let mut other = FilterGraph::new();
+        let foreign = other.apply(VideoFilter::Deinterlace, &[&video]).unwrap();
+        let mut graph = FilterGraph::new();
+        graph.map(&foreign);
+        assert!(graph.validate().is_err());
+        let mut graph = FilterGraph::new();
+        graph.map(&video);
+        assert!(graph.validate().is_err());
+        assert!(FilterGraph::new().validate().is_err());
+    }
+
+    #[test]
+    fn apply_rejects_filters_without_one_output() {
+        let mut graph = FilterGraph::new();
+        let video = graph.input(0, StreamType::Video);
+        let err = graph
+            .apply(GraphFilter::Split(2), &[&video])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("`split=2` has 2 output pads"), "{err}");
+        assert!(graph.nodes.is_empty());
+    }
+
+    #[test]
+    fn clones_do_not_share_pads() {
+        let mut graph = FilterGraph::new();
+        let video = graph.input(0, StreamType::Video);
+        let flipped = graph
+            .apply(VideoFilter::Flip { direction: 'v' }, &[&video])
+            .unwrap();
+        graph.map(&flipped);
+        let mut copy = graph.clone();
+        assert_eq!(copy.to_filter_string().unwrap(), "[0:v]vflip[v0]");
+
+        let mut original = graph.clone();
+        let from_copy = copy.apply(VideoFilter::Deinterlace, &[&video]).unwrap();
+        original.map(&from_copy);
+        let err = original.validate().unwrap_err().to_string();
+        assert!(
+            err.contains("does not belong to this filter graph"),
+            "{err}"
+        );
+        assert!(graph.clone().validate().is_ok());
+    }
+}
@@ -0,0 +1,868 @@
+//! HLS VOD packaging of several renditions in a single ffmpeg run.
+//!
+//! Every rendition is encoded from the same input and muxed by ffmpeg's `hls`
+//! muxer through `-var_stream_map`, which writes one media playlist per
+//! variant plus a master playlist. Keyframes are forced at every segment
+//! boundary so segments line up across renditions.
+//!
+//! Segments can be AES-128 encrypted with keys generated locally, see
+//! [`HlsEncryption`](crate::hls::HlsEncryption). SAMPLE-AES is not offered:
+//! ffmpeg's `hls` muxer cannot produce sample-level encryption.
+//!
+//! ```rust,no_run
+//! use ffmpeg_light::hls::{HlsBuilder, HlsSegmentType};
+//! use ffmpeg_light::rendition::Rendition;
+//!
+//! let manifest = HlsBuilder::new()
+//!     .input("master.mov")
+//!     .rendition(Rendition::new(1920, 1080, 5000))
+//!     .rendition(Rendition::new(1280, 720, 2800))
+//!     .segment_type(HlsSegmentType::Fmp4)
+//!     .output_dir("public/hls")
+//!     .run()?;
+//! println!("master playlist: {}", manifest.master_playlist.display());
+//! # Ok::<(), ffmpeg_light::Error>(())
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::command::{FfmpegBinaryPaths, FfmpegCommand};
+use crate::config::{FfmpegLocator, Tools};
+use crate::error::{Error, Result};
+use crate::keyframe::ForceKeyframes;
+use crate::logging::LogCapture;
+use crate::progress::Progress;
+use crate::rendition::Rendition;
+use crate::runner::CommandRunner;
+
+/// Segment duration used unless configured otherwise.
+const DEFAULT_SEGMENT_DURATION: Duration = Duration::from_secs(6);
+
+/// Media playlist location used unless configured otherwise.
+const DEFAULT_PLAYLIST_TEMPLATE: &str = "%v/index.m3u8";
+
+/// Key info file written next to the keys and passed to `-hls_key_info_file`.
+const KEY_INFO_FILE: &str = "hls.keyinfo";
+
+/// Container of the media segments.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HlsSegmentType {
+    /// MPEG-2 transport stream segments (`.ts`).
+    #[default]
+    MpegTs,
+    /// Fragmented MP4 / CMAF segments (`.m4s`) with an init segment per variant.
+    Fmp4,
+}
+
+impl HlsSegmentType {
+    /// Value of ffmpeg's `-hls_segment_type` option.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HlsSegmentType::MpegTs => "mpegts",
+            HlsSegmentType::Fmp4 => "fmp4",
+        }
+    }
+
+    /// File extension of the segments.
+    pub fn extension(self) -> &'static str {
+        match self {
+            HlsSegmentType::MpegTs => "ts",
+            HlsSegmentType::Fmp4 => "m4s",
+        }
+    }
+}
+
+/// How segments are encrypted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HlsEncryptionMethod {
+    /// Whole segments encrypted with AES-128-CBC.
+    Aes128,
+}
+
+/// Segment encryption with locally generated keys.
+///
+/// Every key is 16 random bytes written to its own file in the key
+/// directory (the output directory unless configured), with a random IV.
+/// The key info file `hls.keyinfo` next to them holds the current key's URI,
+/// path and IV and is passed to ffmpeg with `-hls_key_info_file`, so ffmpeg
+/// encrypts each segment as it writes it and never leaves a clear one on
+/// disk. Playlists reference each key as the key URL prefix followed by the
+/// file name, e.g. `https://keys.example.com/movie/key-0.key`.
+///
+/// With [`rotate_every`](Self::rotate_every), ffmpeg re-reads the key info
+/// file before each segment (`-hls_flags periodic_rekey`) and the next key is
+/// staged from its progress reports while the last segment of a group is
+/// written. Progress arrives every half second, so an encode that writes a
+/// whole segment between two reports can start a key a segment late or skip
+/// it; [`HlsManifest::keys`] is therefore read back from the `#EXT-X-KEY`
+/// tags of the written media playlists, and staged keys no playlist
+/// references are deleted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HlsEncryption {
+    method: HlsEncryptionMethod,
+    key_url: String,
+    key_dir: Option<PathBuf>,
+    rotate_every: Option<u32>,
+}
+
+impl HlsEncryption {
+    /// AES-128 with key files served under `key_url` (a URL or a path relative to the media playlists).
+    pub fn aes128(key_url: impl Into<String>) -> Self {
+        Self {
+            method: HlsEncryptionMethod::Aes128,
+            key_url: key_url.into(),
+            key_dir: None,
+            rotate_every: None,
+        }
+    }
+
+    /// Directory receiving the key files instead of the output directory.
+    pub fn key_dir<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.key_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Switch to a new key every `segments` segments (`-hls_flags periodic_rekey`).
+    pub fn rotate_every(mut self, segments: u32) -> Self {
+        self.rotate_every = Some(segments);
+        self
+    }
+
+    /// Configured encryption method.
+    pub fn method(&self) -> HlsEncryptionMethod {
+        self.method
+    }
+
+    /// Prefix of the key URIs written to the playlists.
+    pub fn key_url_ref(&self) -> &str {
+        &self.key_url
+    }
+
+    /// Configured key directory.
+    pub fn key_dir_ref(&self) -> Option<&Path> {
+        self.key_dir.as_deref()
+    }
+
+    /// Configured rotation period in segments.
+    pub fn rotate_every_value(&self) -> Option<u32> {
+        self.rotate_every
+    }
+}
+
+/// A generated encryption key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HlsKey {
+    /// Key file.
+    pub path: PathBuf,
+    /// URI written to the playlists' `#EXT-X-KEY` tag.
+    pub uri: String,
+    /// Key bytes, as stored in the key file.
+    pub key: [u8; 16],
+    /// Initialisation vector, written to the `#EXT-X-KEY` tag.
+    pub iv: [u8; 16],
+    /// Index of the first segment encrypted with this key, as written to the
+    /// media playlists (the earliest one across variants).
+    pub first_segment: usize,
+}
+
+impl HlsKey {
+    /// IV as written in the `#EXT-X-KEY` tag (`0x` followed by hex digits).
+    pub fn iv_hex(&self) -> String {
+        format!("0x{}", hex(&self.iv))
+    }
+}
+
+/// Files written for one variant stream.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HlsVariant {
+    /// Rendition encoded into this variant.
+    pub rendition: Rendition,
+    /// Media playlist.
+    pub playlist: PathBuf,
+    /// Segment file pattern, with ffmpeg's sequence number placeholder (e.g. `%05d`).
+    pub segment_pattern: PathBuf,
+    /// Init segment of fMP4 variants.
+    pub init_segment: Option<PathBuf>,
+}
+
+/// Playlists and segments produced by an [`HlsBuilder`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct HlsManifest {
+    /// Master playlist referencing every variant.
+    pub master_playlist: PathBuf,
+    /// Container of the media segments.
+    pub segment_type: HlsSegmentType,
+    /// Target segment duration.
+    pub segment_duration: Duration,
+    /// One entry per rendition, in the order they were added.
+    pub variants: Vec<HlsVariant>,
+    /// Keys referenced by the media playlists, in rotation order (empty before the run).
+    pub keys: Vec<HlsKey>,
+}
+
+/// Builder for multi-rendition HLS packaging.
+#[derive(Debug)]
+pub struct HlsBuilder {
+    tools: Tools,
+    input: Option<PathBuf>,
+    renditions: Vec<Rendition>,
+    output_dir: Option<PathBuf>,
+    segment_type: HlsSegmentType,
+    segment_duration: Duration,
+    segment_template: Option<String>,
+    playlist_template: String,
+    init_segment_name: String,
+    master_playlist: String,
+    preset: Option<String>,
+    encryption: Option<HlsEncryption>,
+}
+
+impl Default for HlsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HlsBuilder {
+    /// Create a builder for TS segments of 6 seconds under `%v/` per variant.
+    pub fn new() -> Self {
+        Self {
+            tools: Tools::default(),
+            input: None,
+            renditions: Vec::new(),
+            output_dir: None,
+            segment_type: HlsSegmentType::default(),
+            segment_duration: DEFAULT_SEGMENT_DURATION,
+            segment_template: None,
+            playlist_template: DEFAULT_PLAYLIST_TEMPLATE.into(),
+            init_segment_name: "init.mp4".into(),
+            master_playlist: "master.m3u8".into(),
+            preset: None,
+            encryption: None,
+        }
+    }
+
+    /// Use pre-discovered binaries instead of searching PATH every call.
+    pub fn with_binaries(mut self, binaries: &FfmpegBinaryPaths) -> Self {
+        self.tools.binaries = Some(binaries.clone());
+        self
+    }
+
+    /// Pin the builder to a specific locator.
+    pub fn with_locator(mut self, locator: &FfmpegLocator) -> Self {
+        self.tools.locate(locator);
+        self
+    }
+
+    /// Execute ffmpeg through a custom [`CommandRunner`].
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.tools.runner = Some(runner);
+        self
+    }
+
+    /// Source media path.
+    pub fn input<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.input = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Add a variant stream.
+    pub fn rendition(mut self, rendition: Rendition) -> Self {
+        self.renditions.push(rendition);
+        self
+    }
+
+    /// Add several variant streams, e.g. from a bitrate ladder.
+    pub fn renditions(mut self, renditions: impl IntoIterator<Item = Rendition>) -> Self {
+        self.renditions.extend(renditions);
+        self
+    }
+
+    /// Directory receiving the playlists and segments; templates are relative to it.
+    pub fn output_dir<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.output_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Segment container (maps to `-hls_segment_type`).
+    pub fn segment_type(mut self, segment_type: HlsSegmentType) -> Self {
+        self.segment_type = segment_type;
+        self
+    }
+
+    /// Target segment duration (maps to `-hls_time`, default 6 seconds).
+    pub fn segment_duration(mut self, duration: Duration) -> Self {
+        self.segment_duration = duration;
+        self
+    }
+
+    /// Segment file name template (maps to `-hls_segment_filename`).
+    ///
+    /// `%v` is replaced by the rendition name and a `%d` style placeholder
+    /// by the segment number. Defaults to `%v/segment_%05d.ts` (or `.m4s`).
+    pub fn segment_template(mut self, template: impl Into<String>) -> Self {
+        self.segment_template = Some(template.into());
+        self
+    }
+
+    /// Media playlist template, with `%v` for the rendition name (default `%v/index.m3u8`).
+    pub fn playlist_template(mut self, template: impl Into<String>) -> Self {
+        self.playlist_template = template.into();
+        self
+    }
+
+    /// Init segment name of fMP4 variants, written next to their playlist (default `init.mp4`).
+    ///
+    /// Without `%v`, ffmpeg appends `_<index>` when there are several renditions.
+    pub fn init_segment_name(mut self, name: impl Into<String>) -> Self {
+        self.init_segment_name = name.into();
+        self
+    }
+
+    /// Master playlist file name (default `master.m3u8`).
+    pub fn master_playlist(mut self, name: impl Into<String>) -> Self {
+        self.master_playlist = name.into();
+        self
+    }
+
+    /// Apply a named encoder preset to every rendition (maps to `-preset`).
+    pub fn preset(mut self, preset: impl Into<String>) -> Self {
+        self.preset = Some(preset.into());
+        self
+    }
+
+    /// Encrypt the segments (maps to `-hls_key_info_file`).
+    pub fn encryption(mut self, encryption: HlsEncryption) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    /// Abort the job with [`Error::TimeoutError`] if it runs longer than `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.tools.timeout = Some(timeout);
+        self
+    }
+
+    /// Keep ffmpeg's complete log in a file or attach it to the returned error.
+    pub fn log_capture(mut self, capture: LogCapture) -> Self {
+        self.tools.log = capture;
+        self
+    }
+
+    /// Accessor for the configured input path.
+    pub fn input_path(&self) -> Option<&Path> {
+        self.input.as_deref()
+    }
+
+    /// Accessor for the configured renditions.
+    pub fn renditions_ref(&self) -> &[Rendition] {
+        &self.renditions
+    }
+
+    /// Accessor for the configured output directory.
+    pub fn output_dir_ref(&self) -> Option<&Path> {
+        self.output_dir.as_deref()
+    }
+
+    /// Accessor for the segment container.
+    pub fn segment_type_value(&self) -> HlsSegmentType {
+        self.segment_type
+    }
+
+    /// Accessor for the target segment duration.
+    pub fn segment_duration_value(&self) -> Duration {
+        self.segment_duration
+    }
+
+    /// Accessor for the configured encryption.
+    pub fn encryption_ref(&self) -> Option<&HlsEncryption> {
+        self.encryption.as_ref()
+    }
+
+    /// Accessor for the configured timeout.
+    pub fn timeout_value(&self) -> Option<Duration> {
+        self.tools.timeout
+    }
+
+    /// Describe the playlists and segments the job will write, without running it.
+    pub fn manifest(&self) -> Result<HlsManifest> {
+        self.check()?;
+        Ok(self.describe())
+    }
+
+    /// Validate the configuration and return the ffmpeg command without running it.
+    ///
+    /// Encrypted jobs reference `hls.keyinfo` in the key directory, which
+    /// only [`run`](Self::run) writes along with the keys.
+    pub fn build_command(self) -> Result<FfmpegCommand> {
+        self.check()?;
+        self.command()
+    }
+
+    /// Package the input, creating the variant directories first.
+    pub fn run(self) -> Result<HlsManifest> {
+        self.check()?;
+        let mut manifest = self.describe();
+        create_dirs(&manifest)?;
+        let cmd = self.command()?;
+        let Some(mut keys) = self.key_ring()? else {
+            cmd.run()?;
+            return Ok(manifest);
+        };
+        if keys.rotates() {
+            cmd.run_with_progress(|progress| keys.advance(progress))?;
+        } else {
+            cmd.run()?;
+        }
+        manifest.keys = keys.finish(&manifest.variants)?;
+        Ok(manifest)
+    }
+
+    /// Async variant of [`run`](Self::run) (requires `tokio`).
+    ///
+    /// Dropping the future kills the ffmpeg process.
+    #[cfg(feature = "tokio")]
+    pub async fn run_async(self) -> Result<HlsManifest> {
+        self.check()?;
+        let mut manifest = self.describe();
+        create_dirs(&manifest)?;
+        let cmd = self.command()?;
+        let Some(mut keys) = self.key_ring()? else {
+            cmd.run_async().await?;
+            return Ok(manifest);
+        };
+        if keys.rotates() {
+            cmd.run_with_progress_async(|progress| keys.advance(progress))
+                .await?;
+        } else {
+            cmd.run_async().await?;
+        }
+        manifest.keys = keys.finish(&manifest.variants)?;
+        Ok(manifest)
+    }
+
+    /// Key ring with the first key staged, for encrypted jobs.
+    fn key_ring(&self) -> Result<Option<KeyRing>> {
+        let (Some(encryption), Some(key_dir)) = (&self.encryption, self.key_dir()) else {
+            return Ok(None);
+        };
+        fs::create_dir_all(&key_dir)?;
+        KeyRing::new(encryption, key_dir, self.segment_duration).map(Some)
+    }
+
+    /// Directory receiving the keys and the key info file, for encrypted jobs.
+    fn key_dir(&self) -> Option<PathBuf> {
+        let encryption = self.encryption.as_ref()?;
+        Some(
+            encryption
+                .key_dir
+                .clone()
+                .unwrap_or_else(|| self.dir().to_path_buf()),
+        )
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.input.is_none() {
+            return Err(Error::InvalidInput("input path is required".into()));
+        }
+        if self.output_dir.is_none() {
+            return Err(Error::InvalidInput("output directory is required".into()));
+        }
+        if self.renditions.is_empty() {
+            return Err(Error::InvalidInput(
+                "HLS packaging needs at least one rendition".into(),
+            ));
+        }
+        let mut names = HashSet::new();
+        for rendition in &self.renditions {
+            rendition.validate()?;
+            if !names.insert(rendition.name_ref()) {
+                return Err(Error::InvalidInput(format!(
+                    "rendition name `{}` is used twice",
+                    rendition.name_ref()
+                )));
+            }
+        }
+        if self.segment_duration.is_zero() {
+            return Err(Error::InvalidInput(
+                "segment duration must be positive".into(),
+            ));
+        }
+        let segment_template = self.segment_pattern();
+        if !has_sequence_number(&segment_template) {
+            return Err(Error::InvalidInput(format!(
+                "segment template `{segment_template}` needs a sequence number such as %05d"
+            )));
+        }
+        if let Some(encryption) = &self.encryption {
+            if encryption.rotate_every == Some(0) {
+                return Err(Error::InvalidInput(
+                    "keys must be rotated every 1 or more segments".into(),
+                ));
+            }
+        }
+        if self.renditions.len() > 1 {
+            for template in [&segment_template, &self.playlist_template] {
+                if !template.contains("%v") {
+                    return Err(Error::InvalidInput(format!(
+                        "template `{template}` needs %v to keep the renditions apart"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn segment_pattern(&self) -> String {
+        self.segment_template
+            .clone()
+            .unwrap_or_else(|| format!("%v/segment_%05d.{}", self.segment_type.extension()))
+    }
+
+    fn dir(&self) -> &Path {
+        self.output_dir.as_deref().unwrap_or_else(|| Path::new("."))
+    }
+
+    fn describe(&self) -> HlsManifest {
+        let dir = self.dir();
+        let segment_template = self.segment_pattern();
+        let variants = self
+            .renditions
+            .iter()
+            .enumerate()
+            .map(|(index, rendition)| {
+                let name = rendition.name_ref();
+                let playlist = dir.join(self.playlist_template.replace("%v", name));
+                let init_segment = (self.segment_type == HlsSegmentType::Fmp4).then(|| {
+                    let parent = playlist.parent().unwrap_or(dir);
+                    parent.join(self.init_segment_file(index, name))
+                });
+                HlsVariant {
+                    rendition: rendition.clone(),
+                    segment_pattern: dir.join(segment_template.replace("%v", name)),
+                    playlist,
+                    init_segment,
+                }
+            })
+            .collect();
+        // Like ffmpeg, place the master playlist above a per-variant directory.
+        let mut master_dir = dir.join(&self.playlist_template);
+        master_dir.pop();
+        if master_dir.to_string_lossy().contains("%v") {
+            master_dir.pop();
+        }
+        HlsManifest {
+            master_playlist: master_dir.join(&self.master_playlist),
+            segment_type: self.segment_type,
+            segment_duration: self.segment_duration,
+            variants,
+            keys: Vec::new(),
+        }
+    }
+
+    /// Init segment name as ffmpeg resolves it for the `index`-th variant.
+    fn init_segment_file(&self, index: usize, name: &str) -> String {
+        let template = &self.init_segment_name;
+        if template.contains("%v") {
+            return template.replace("%v", name);
+        }
+        if self.renditions.len() == 1 {
+            return template.clone();
+        }
+        match template.rsplit_once('.') {
+            Some((stem, extension)) => format!("{stem}_{index}.{extension}"),
+            None => format!("{template}_{index}"),
+        }
+    }
+
+    fn command(&self) -> Result<FfmpegCommand> {
+        let dir = self.dir();
+
+        let mut cmd = self.tools.ffmpeg()?;
+        cmd.arg("-y");
+        if let Some(input) = &self.input {
+            cmd.arg("-i").arg(input);
+        }
+
+        let mut stream_map = Vec::new();
+        let mut audio_index = 0;
+        for (video_index, rendition) in self.renditions.iter().enumerate() {
+            cmd.arg("-map").arg("0:v:0");
+            cmd.args(&rendition.video_args(video_index));
+            let mut entry = format!("v:{video_index}");
+            if rendition.has_audio() {
+                cmd.arg("-map").arg("0:a:0");
+                cmd.args(&rendition.audio_args(audio_index));
+                entry.push_str(&format!(",a:{audio_index}"));
+                audio_index += 1;
+            }
+            entry.push_str(&format!(",name:{}", rendition.name_ref()));
+            stream_map.push(entry);
+        }
+        if let Some(preset) = &self.preset {
+            cmd.arg("-preset").arg(preset);
+        }
+        let keyframes = ForceKeyframes::Every(self.segment_duration);
+        cmd.arg("-force_key_frames:v").arg(keyframes.to_arg());
+
+        cmd.arg("-f").arg("hls");
+        cmd.arg("-hls_time")
+            .arg(self.segment_duration.as_secs_f64().to_string());
+        cmd.arg("-hls_playlist_type").arg("vod");
+        cmd.arg("-hls_segment_type").arg(self.segment_type.as_str());
+        if self.segment_type == HlsSegmentType::Fmp4 {
+            cmd.arg("-hls_fmp4_init_filename")
+                .arg(&self.init_segment_name);
+        }
+        if let (Some(encryption), Some(key_dir)) = (&self.encryption, self.key_dir()) {
+            cmd.arg("-hls_key_info_file")
+                .arg(key_dir.join(KEY_INFO_FILE));
+            if encryption.rotate_every.is_some() {
+                cmd.arg("-hls_flags").arg("periodic_rekey");
+            }
+        }
+        cmd.arg("-hls_segment_filename")
+            .arg(dir.join(self.segment_pattern()));
+        cmd.arg("-master_pl_name").arg(&self.master_playlist);
+        cmd.arg("-var_stream_map").arg(stream_map.join(" "));
+        cmd.arg(dir.join(&self.playlist_template));
+        Ok(cmd)
+    }
+}
+
+/// Keys of an encrypted job and the key info file ffmpeg reads them from.
+struct KeyRing {
+    key_url: String,
+    key_dir: PathBuf,
+    rotate_every: Option<u32>,
+    segment_duration: Duration,
+    keys: Vec<HlsKey>,
+    staged: usize,
+    error: Option<Error>,
+}
+
+impl KeyRing {
+    fn new(
+        encryption: &HlsEncryption,
+        key_dir: PathBuf,
+        segment_duration: Duration,
+    ) -> Result<Self> {
+        let mut ring = Self {
+            key_url: encryption.key_url.clone(),
+            key_dir,
+            rotate_every: encryption.rotate_every,
+            segment_duration,
+            keys: Vec::new(),
+            staged: 0,
+            error: None,
+        };
+        ring.stage(0)?;
+        Ok(ring)
+    }
+
+    fn rotates(&self) -> bool {
+        self.rotate_every.is_some()
+    }
+
+    /// Stage the key of the segment after the one being written.
+    fn advance(&mut self, progress: &Progress) {
+        let (Some(every), Some(out_time)) = (self.rotate_every, progress.out_time) else {
+            return;
+        };
+        let segment =
+            (out_time.as_duration().as_secs_f64() / self.segment_duration.as_secs_f64()) as usize;
+        let wanted = (segment + 1) / every as usize;
+        if wanted > self.staged && self.error.is_none() {
+            if let Err(err) = self.stage(wanted) {
+                self.error = Some(err);
+            }
+        }
+    }
+
+    /// Generate the `index`-th key and point the key info file at it.
+    fn stage(&mut self, index: usize) -> Result<()> {
+        let name = format!("key-{index}.key");
+        let key = HlsKey {
+            path: self.key_dir.join(&name),
+            uri: format!("{}{name}", self.key_url),
+            key: random_block()?,
+            iv: random_block()?,
+            first_segment: index * self.rotate_every.unwrap_or(0) as usize,
+        };
+        fs::write(&key.path, key.key)?;
+        let info = format!("{}\n{}\n{}\n", key.uri, key.path.display(), hex(&key.iv));
+        // Replace the file in one step so ffmpeg never reads a partial one.
+        let staging = self.key_dir.join(format!("{KEY_INFO_FILE}.tmp"));
+        fs::write(&staging, info)?;
+        fs::rename(&staging, self.key_dir.join(KEY_INFO_FILE))?;
+        self.keys.push(key);
+        self.staged = index;
+        Ok(())
+    }
+
+    /// Keys the written playlists reference, deleting the ones ffmpeg never used.
+    fn finish(mut self, variants: &[HlsVariant]) -> Result<Vec<HlsKey>> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        let mut first_segments: HashMap<String, usize> = HashMap::new();
+        for variant in variants {
+            let playlist = fs::read_to_string(&variant.playlist)?;
+            for (uri, segment) in key_tags(&playlist) {
+                let first = first_segments.entry(uri).or_insert(segment);
+                *first = (*first).min(segment);
+            }
+        }
+        let mut used = Vec::new();
+        for mut key in std::mem::take(&mut self.keys) {
+            match first_segments.get(&key.uri) {
+                Some(&segment) => {
+                    key.first_segment = segment;
+                    used.push(key);
+                }
+                None => fs::remove_file(&key.path)?,
+            }
+        }
+        used.sort_by_key(|key| key.first_segment);
+        Ok(used)
+    }
+}
+
+/// URI of every `#EXT-X-KEY` tag in a media playlist with the index of the
+/// first segment it applies to.
+fn key_tags(playlist: &str) -> Vec<(String, usize)> {
+    let mut tags = Vec::new();
+    let mut pending = None;
+    let mut segment = 0;
+    for line in playlist.lines().map(str::trim) {
+        if let Some(attributes) = line.strip_prefix("#EXT-X-KEY:") {
+            pending = attribute(attributes, "URI");
+        } else if !line.is_empty() && !line.starts_with('#') {
+            if let Some(uri) = pending.take() {
+                tags.push((uri, segment));
+            }
+            segment += 1;
+        }
+    }
+    tags
+}
+
+/// Value of `name` in an attribute list such as `METHOD=AES-128,URI="key.key"`.
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    while !rest.is_empty() {
+        let (key, value) = rest.split_once('=')?;
+        let (value, tail) = match value.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"')?,
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        if key.trim() == name {
+            return Some(value.to_string());
+        }
+        rest = tail.trim_start_matches(',');
+    }
+    None
+}
+
+fn random_block() -> Result<[u8; 16]> {
+    let mut block = [0u8; 16];
+    getrandom::getrandom(&mut block)
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+    Ok(block)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut out, byte| {
+        let _ = write!(out, "{byte:02x}");
+        out
+    })
+}
+
+/// ffmpeg does not create the per-variant directories itself.
+fn create_dirs(manifest: &HlsManifest) -> Result<()> {
+    for variant in &manifest.variants {
+        for path in [&variant.playlist, &variant.segment_pattern] {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether `template` has a `%d`-style placeholder for the segment number.
+fn has_sequence_number(template: &str) -> bool {
+    template.split('%').skip(1).any(|rest| {
+        let rest = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+        rest.starts_with('d')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_key_tags_from_written_playlists() {
+        let playlist = "#EXTM3U\n\
+            #EXT-X-VERSION:6\n\
+            #EXT-X-TARGETDURATION:6\n\
+            #EXT-X-MEDIA-SEQUENCE:0\n\
+            #EXT-X-PLAYLIST-TYPE:VOD\n\
+            #EXT-X-MAP:URI=\"init_0.mp4\"\n\
+            #EXT-X-KEY:METHOD=AES-128,URI=\"https://k.example/key-0.key\",IV=0x00ff\n\
+            #EXTINF:6.006000,\n\
+            segment_00000.m4s\n\
+            #EXTINF:6.006000,\n\
+            segment_00001.m4s\n\
+            #EXT-X-KEY:METHOD=AES-128,URI=\"https://k.example/key-2.key\",IV=0x01ff\n\
+            #EXTINF:4.004000,\n\
+            segment_00002.m4s\n\
+            #EXT-X-ENDLIST\n";
+        assert_eq!(
+            key_tags(playlist),
+            [
+                ("https://k.example/key-0.key".to_string(), 0),
+                ("https://k.example/key-2.key".to_string(), 2)
+            ]
+        );
+        assert_eq!(
+            attribute("METHOD=AES-128,URI=\"a,b=c\",IV=0x1", "IV").as_deref(),
+            Some("0x1")
+        );
+    }
+
+    #[test]
+    fn resolves_variant_paths() {
+        let builder = HlsBuilder::new()
+            .input("in.mp4")
+            .output_dir("out")
+            .segment_type(HlsSegmentType::Fmp4)
+            .rendition(Rendition::new(1280, 720, 3000))
+            .rendition(Rendition::new(640, 360, 800).no_audio());
+        let manifest = builder.manifest().unwrap();
+        let variant = &manifest.variants[1];
+        assert_eq!(manifest.master_playlist, Path::new("out/master.m3u8"));
+        assert_eq!(variant.playlist, Path::new("out/360p/index.m3u8"));
+        assert_eq!(
+            variant.segment_pattern,
+            Path::new("out/360p/segment_%05d.m4s")
+        );
+        assert_eq!(
+            variant.init_segment.as_deref(),
+            Some(Path::new("out/360p/init_1.mp4"))
+        );
+
+        let err = builder
+            .segment_template("seg_%v.m4s")
+            .manifest()
+            .unwrap_err();
+        assert!(err.to_string().contains("sequence number"));
+    }
+}
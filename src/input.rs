@@ -0,0 +1,326 @@
+//! Input files and stream selection for multi-input jobs.
+
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::types::{StreamType, Time};
+
+/// An ffmpeg input together with the options that apply to it.
+///
+/// Options are emitted before the matching `-i`, so they affect how the
+/// input is read (e.g. seeking before decoding).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Input {
+    path: PathBuf,
+    seek: Option<Time>,
+    duration: Option<Time>,
+    offset: Option<Time>,
+    format: Option<String>,
+    stream_loop: Option<i32>,
+    extra_args: Vec<OsString>,
+}
+
+impl Input {
+    /// Read from a file path or URL.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            seek: None,
+            duration: None,
+            offset: None,
+            format: None,
+            stream_loop: None,
+            extra_args: Vec::new(),
+        }
+    }
+
+    /// Start reading at `time` (maps to `-ss`).
+    pub fn seek(mut self, time: Time) -> Self {
+        self.seek = Some(time);
+        self
+    }
+
+    /// Read at most `duration` of the input (maps to `-t`).
+    pub fn duration(mut self, duration: Time) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Shift the input's timestamps by `offset` (maps to `-itsoffset`).
+    pub fn offset(mut self, offset: Time) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Force the input format (maps to `-f`, e.g. `lavfi` or `concat`).
+    pub fn format(mut self, format: impl Into<String>) -> Self {
+        self.format = Some(format.into());
+        self
+    }
+
+    /// Loop the input `count` extra times; `-1` loops forever (maps to `-stream_loop`).
+    pub fn stream_loop(mut self, count: i32) -> Self {
+        self.stream_loop = Some(count);
+        self
+    }
+
+    /// Pass a raw input option for advanced cases.
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.extra_args.push(arg.into());
+        self
+    }
+
+    /// Path or URL of the input.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Configured seek position.
+    pub fn seek_value(&self) -> Option<Time> {
+        self.seek
+    }
+
+    /// Configured read duration.
+    pub fn duration_value(&self) -> Option<Time> {
+        self.duration
+    }
+
+    /// Configured timestamp offset.
+    pub fn offset_value(&self) -> Option<Time> {
+        self.offset
+    }
+
+    /// Forced input format.
+    pub fn format_value(&self) -> Option<&str> {
+        self.format.as_deref()
+    }
+
+    /// Arguments for this input, ending with `-i <path>`.
+    pub fn to_args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = Vec::new();
+        if let Some(count) = self.stream_loop {
+            args.extend(["-stream_loop".into(), count.to_string().into()]);
+        }
+        if let Some(format) = &self.format {
+            args.extend(["-f".into(), format.into()]);
+        }
+        if let Some(seek) = self.seek {
+            args.extend(["-ss".into(), seek.to_ffmpeg_timestamp().into()]);
+        }
+        if let Some(duration) = self.duration {
+            args.extend(["-t".into(), duration.to_ffmpeg_timestamp().into()]);
+        }
+        if let Some(offset) = self.offset {
+            args.extend(["-itsoffset".into(), offset.to_ffmpeg_timestamp().into()]);
+        }
+        args.extend(self.extra_args.iter().cloned());
+        args.extend(["-i".into(), self.path.clone().into_os_string()]);
+        args
+    }
+}
+
+impl<P: AsRef<Path>> From<P> for Input {
+    fn from(path: P) -> Self {
+        Input::new(path)
+    }
+}
+
+/// Which streams of an input a [`StreamMap`] selects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StreamSelector {
+    /// Every stream of the input.
+    All,
+    /// Stream at an absolute index within the input.
+    Index(usize),
+    /// All streams of one type.
+    Type(StreamType),
+    /// The n-th stream of one type (0-based).
+    TypeIndex(StreamType, usize),
+    /// Streams whose `language` tag matches, optionally restricted to a type.
+    Language(Option<StreamType>, String),
+}
+
+/// A typed `-map` option.
+///
+/// ```rust
+/// use ffmpeg_light::input::StreamMap;
+/// use ffmpeg_light::StreamType;
+///
+/// assert_eq!(StreamMap::of_type(1, StreamType::Audio).nth(1).to_string(), "1:a:1");
+/// assert_eq!(StreamMap::language(0, "eng").to_string(), "0:m:language:eng");
+/// assert_eq!(StreamMap::of_type(0, StreamType::Subtitle).exclude().to_string(), "-0:s");
+/// assert_eq!(StreamMap::stream(2, 3).optional().to_string(), "2:3?");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamMap {
+    input: usize,
+    selector: StreamSelector,
+    exclude: bool,
+    optional: bool,
+}
+
+impl StreamMap {
+    /// Select every stream of input `input`.
+    pub fn input(input: usize) -> Self {
+        Self::with_selector(input, StreamSelector::All)
+    }
+
+    /// Select the stream at absolute index `index` of input `input`.
+    pub fn stream(input: usize, index: usize) -> Self {
+        Self::with_selector(input, StreamSelector::Index(index))
+    }
+
+    /// Select all streams of `kind` from input `input`.
+    pub fn of_type(input: usize, kind: StreamType) -> Self {
+        Self::with_selector(input, StreamSelector::Type(kind))
+    }
+
+    /// Select streams of input `input` tagged with `language` (e.g. `eng`).
+    pub fn language(input: usize, language: impl Into<String>) -> Self {
+        Self::with_selector(input, StreamSelector::Language(None, language.into()))
+    }
+
+    /// Select streams of input `input` using an explicit selector.
+    pub fn with_selector(input: usize, selector: StreamSelector) -> Self {
+        Self {
+            input,
+            selector,
+            exclude: false,
+            optional: false,
+        }
+    }
+
+    /// Narrow a type selection to its `n`-th stream.
+    pub fn nth(mut self, n: usize) -> Self {
+        self.selector = match self.selector {
+            StreamSelector::Type(kind) | StreamSelector::TypeIndex(kind, _) => {
+                StreamSelector::TypeIndex(kind, n)
+            }
+            other => other,
+        };
+        self
+    }
+
+    /// Narrow a type selection to streams tagged with `language`.
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        let kind = match &self.selector {
+            StreamSelector::Type(kind) | StreamSelector::TypeIndex(kind, _) => Some(kind.clone()),
+            StreamSelector::Language(kind, _) => kind.clone(),
+            _ => None,
+        };
+        self.selector = StreamSelector::Language(kind, language.into());
+        self
+    }
+
+    /// Turn this into a negative map that removes previously mapped streams.
+    pub fn exclude(mut self) -> Self {
+        self.exclude = true;
+        self
+    }
+
+    /// Ignore the map instead of failing when no stream matches (`?` suffix).
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    /// Index of the input this map refers to.
+    pub fn input_index(&self) -> usize {
+        self.input
+    }
+
+    /// Stream selection of this map.
+    pub fn selector(&self) -> &StreamSelector {
+        &self.selector
+    }
+
+    /// Whether this is a negative map.
+    pub fn is_excluded(&self) -> bool {
+        self.exclude
+    }
+
+    /// Whether this map is optional.
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+}
+
+impl fmt::Display for StreamMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.exclude {
+            f.write_str("-")?;
+        }
+        write!(f, "{}", self.input)?;
+        match &self.selector {
+            StreamSelector::All => {}
+            StreamSelector::Index(index) => write!(f, ":{index}")?,
+            StreamSelector::Type(kind) => write!(f, ":{}", stream_specifier(kind))?,
+            StreamSelector::TypeIndex(kind, n) => write!(f, ":{}:{n}", stream_specifier(kind))?,
+            StreamSelector::Language(kind, language) => {
+                if let Some(kind) = kind {
+                    write!(f, ":{}", stream_specifier(kind))?;
+                }
+                write!(f, ":m:language:{language}")?;
+            }
+        }
+        if self.optional {
+            f.write_str("?")?;
+        }
+        Ok(())
+    }
+}
+
+/// Stream specifier letter used by ffmpeg for a stream type.
+pub(crate) fn stream_specifier(kind: &StreamType) -> &'static str {
+    match kind {
+        StreamType::Video => "v",
+        StreamType::Audio => "a",
+        StreamType::Subtitle => "s",
+        StreamType::Data => "d",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_options_precede_path() {
+        let input = Input::new("music.m4a")
+            .seek(Time::from_seconds(5))
+            .offset(Time::from_seconds(1))
+            .stream_loop(-1);
+        let args: Vec<_> = input
+            .to_args()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            args,
+            [
+                "-stream_loop",
+                "-1",
+                "-ss",
+                "00:00:05.000",
+                "-itsoffset",
+                "00:00:01.000",
+                "-i",
+                "music.m4a"
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_map_specifiers() {
+        assert_eq!(StreamMap::input(1).to_string(), "1");
+        assert_eq!(
+            StreamMap::of_type(0, StreamType::Audio)
+                .with_language("fre")
+                .optional()
+                .to_string(),
+            "0:a:m:language:fre?"
+        );
+        assert_eq!(StreamMap::stream(0, 4).exclude().to_string(), "-0:4");
+    }
+}
@@ -0,0 +1,268 @@
+//! GOP structure and keyframe placement for segment-aligned outputs.
+//!
+//! Adaptive streaming needs keyframes at the same timestamps in every
+//! rendition. A fixed GOP with scene-cut keyframes disabled gives that for a
+//! constant frame rate; `-force_key_frames` pins keyframes to times instead
+//! of frame counts. Use [`probe::keyframe_timestamps`](crate::probe::keyframe_timestamps)
+//! to check the result.
+
+use std::ffi::OsString;
+use std::time::Duration;
+
+use crate::encoder::X265Options;
+use crate::error::{Error, Result};
+use crate::rate_control::StreamArgs;
+use crate::types::Time;
+
+/// Distance between keyframes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GopInterval {
+    /// A keyframe every `n` frames.
+    Frames(u32),
+    /// A keyframe every `n` seconds.
+    Seconds(f64),
+}
+
+/// Where `-force_key_frames` places keyframes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ForceKeyframes {
+    /// At every multiple of the interval.
+    Every(Duration),
+    /// At the listed timestamps.
+    At(Vec<Time>),
+    /// A raw `-force_key_frames` value such as `expr:gte(t,n_forced*4)`.
+    Expr(String),
+}
+
+impl ForceKeyframes {
+    /// Value passed to `-force_key_frames`.
+    pub fn to_arg(&self) -> String {
+        match self {
+            ForceKeyframes::Every(interval) => {
+                format!("expr:gte(t,n_forced*{})", interval.as_secs_f64())
+            }
+            ForceKeyframes::At(times) => times
+                .iter()
+                .map(|time| time.to_ffmpeg_timestamp())
+                .collect::<Vec<_>>()
+                .join(","),
+            ForceKeyframes::Expr(expr) => expr.clone(),
+        }
+    }
+}
+
+/// GOP settings of the video encoder, configured through
+/// [`TranscodeBuilder`](crate::TranscodeBuilder).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GopSettings {
+    pub(crate) interval: Option<GopInterval>,
+    pub(crate) closed: bool,
+    pub(crate) no_scenecut: bool,
+    pub(crate) force: Option<ForceKeyframes>,
+}
+
+impl GopSettings {
+    /// Fixed keyframe interval.
+    pub fn interval(&self) -> Option<GopInterval> {
+        self.interval
+    }
+
+    /// Whether GOPs are closed (no references across keyframes).
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Whether scene-cut keyframes are left enabled.
+    pub fn scenecut_enabled(&self) -> bool {
+        !self.no_scenecut
+    }
+
+    /// Forced keyframe placement.
+    pub fn forced_keyframes(&self) -> Option<&ForceKeyframes> {
+        self.force.as_ref()
+    }
+
+    /// Whether nothing was configured.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check the interval and, for a seconds-based GOP without a known frame
+    /// rate, that no other forced placement competes with the derived one.
+    pub(crate) fn validate(&self, frame_rate: Option<f64>) -> Result<()> {
+        match self.interval {
+            Some(GopInterval::Frames(0)) => Err(Error::InvalidInput(
+                "GOP length must be at least 1 frame".into(),
+            )),
+            Some(GopInterval::Seconds(seconds)) if !seconds.is_finite() || seconds <= 0.0 => Err(
+                Error::InvalidInput(format!("GOP length must be positive (got {seconds}s)")),
+            ),
+            Some(GopInterval::Seconds(_)) if frame_rate.is_none() && self.force.is_some() => {
+                Err(Error::InvalidInput(
+                    "a GOP in seconds without frame_rate() already forces keyframes; \
+                     drop force_key_frames() or set a frame rate"
+                        .into(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// GOP length in frames, when it can be expressed as one.
+    fn keyint(&self, frame_rate: Option<f64>) -> Option<u32> {
+        match self.interval? {
+            GopInterval::Frames(frames) => Some(frames),
+            GopInterval::Seconds(seconds) => {
+                frame_rate.map(|fps| (seconds * fps).round().max(1.0) as u32)
+            }
+        }
+    }
+
+    /// Keyframe placement to force: explicit, or derived from a seconds-based
+    /// GOP when the frame rate is unknown.
+    fn forced(&self, frame_rate: Option<f64>) -> Option<ForceKeyframes> {
+        match (&self.force, self.interval) {
+            (Some(force), _) => Some(force.clone()),
+            (None, Some(GopInterval::Seconds(seconds))) if frame_rate.is_none() => {
+                Some(ForceKeyframes::Every(Duration::from_secs_f64(seconds)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Fold the GOP into x265's own parameters, which `libx265` reads instead
+    /// of ffmpeg's generic `-g`/`-sc_threshold`/`-flags +cgop`.
+    ///
+    /// The result is the only `-x265-params` of the command; rate-control
+    /// entries such as CBR's `hrd=1` are added to the same options.
+    pub(crate) fn apply_x265(
+        &self,
+        mut options: X265Options,
+        frame_rate: Option<f64>,
+    ) -> X265Options {
+        if let Some(keyint) = self.keyint(frame_rate) {
+            options = options.keyint(keyint).min_keyint(keyint);
+        }
+        if self.no_scenecut {
+            options = options.scenecut(0);
+        }
+        if self.closed {
+            options = options.param("open-gop", "0");
+        }
+        options
+    }
+
+    /// Render the flags for `encoder` (without the x265 parameters).
+    pub(crate) fn to_args(&self, encoder: Option<&str>, frame_rate: Option<f64>) -> Vec<OsString> {
+        let mut args = StreamArgs::new("v");
+        let x265 = encoder == Some("libx265");
+        if !x265 {
+            if let Some(keyint) = self.keyint(frame_rate) {
+                args.push("g", keyint).push("keyint_min", keyint);
+            }
+            if self.no_scenecut {
+                match encoder {
+                    Some(name) if name.ends_with("_nvenc") => {
+                        args.push("no-scenecut", 1);
+                    }
+                    // These only place keyframes by distance once min = max GOP.
+                    Some("libvpx" | "libvpx-vp9" | "libaom-av1" | "libsvtav1") => {}
+                    _ => {
+                        args.push("sc_threshold", 0);
+                    }
+                }
+            }
+            if self.closed {
+                args.push("flags", "+cgop");
+            }
+        }
+        if let Some(force) = self.forced(frame_rate) {
+            args.push("force_key_frames", force.to_arg());
+        }
+        args.into_inner()
+    }
+}
+
+/// Keyframe timestamps of either rendition without a counterpart in the other.
+///
+/// Timestamps within `tolerance` of each other count as aligned; an empty
+/// result means every keyframe lines up.
+pub fn misaligned_keyframes(
+    reference: &[Duration],
+    other: &[Duration],
+    tolerance: Duration,
+) -> Vec<Duration> {
+    let unmatched = |from: &[Duration], against: &[Duration]| -> Vec<Duration> {
+        from.iter()
+            .copied()
+            .filter(|time| {
+                !against.iter().any(|candidate| {
+                    let delta = if candidate > time {
+                        *candidate - *time
+                    } else {
+                        *time - *candidate
+                    };
+                    delta <= tolerance
+                })
+            })
+            .collect()
+    };
+    let mut misaligned = unmatched(reference, other);
+    misaligned.extend(unmatched(other, reference));
+    misaligned.sort();
+    misaligned.dedup();
+    misaligned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(gop: &GopSettings, encoder: &str, frame_rate: Option<f64>) -> Vec<String> {
+        gop.to_args(Some(encoder), frame_rate)
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn seconds_gop_uses_frame_rate_or_forced_keyframes() {
+        let gop = GopSettings {
+            interval: Some(GopInterval::Seconds(2.0)),
+            no_scenecut: true,
+            ..GopSettings::default()
+        };
+        assert_eq!(
+            render(&gop, "libx264", Some(29.97)),
+            ["-g:v", "60", "-keyint_min:v", "60", "-sc_threshold:v", "0"]
+        );
+        assert_eq!(
+            render(&gop, "libx264", None),
+            [
+                "-sc_threshold:v",
+                "0",
+                "-force_key_frames:v",
+                "expr:gte(t,n_forced*2)"
+            ]
+        );
+    }
+
+    #[test]
+    fn forced_keyframes_at_times() {
+        let force = ForceKeyframes::At(vec![Time::from_seconds(4), Time::from_seconds_f64(8.5)]);
+        assert_eq!(force.to_arg(), "00:00:04.000,00:00:08.500");
+    }
+
+    #[test]
+    fn reports_unmatched_keyframes() {
+        let ms = Duration::from_millis;
+        let reference = [ms(0), ms(2000), ms(4000)];
+        let other = [ms(0), ms(2010), ms(3000), ms(4000)];
+        assert!(misaligned_keyframes(&reference, &reference, ms(0)).is_empty());
+        assert_eq!(misaligned_keyframes(&reference, &other, ms(20)), [ms(3000)]);
+        assert_eq!(
+            misaligned_keyframes(&reference, &other, ms(5)),
+            [ms(2000), ms(2010), ms(3000)]
+        );
+    }
+}
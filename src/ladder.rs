@@ -0,0 +1,226 @@
+//! Adaptive-bitrate ladders derived from a probed source.
+//!
+//! A policy lists rungs by the short side of the frame (the height of a
+//! landscape video). Rungs above the source are dropped so nothing is
+//! upscaled, widths follow the source aspect ratio rounded to even numbers,
+//! and bitrates are capped at the source's own.
+//!
+//! ```rust,no_run
+//! use ffmpeg_light::hls::HlsBuilder;
+//! use ffmpeg_light::ladder::{self, LadderPolicy};
+//!
+//! let source = ffmpeg_light::probe("master.mov")?;
+//! let renditions = ladder::generate(&source, &LadderPolicy::AppleHls)?;
+//! HlsBuilder::new()
+//!     .input("master.mov")
+//!     .renditions(renditions)
+//!     .output_dir("public/hls")
+//!     .run()?;
+//! # Ok::<(), ffmpeg_light::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::rendition::Rendition;
+use crate::types::ProbeResult;
+
+/// One entry of a ladder table.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LadderRung {
+    short_side: u32,
+    video_kbps: u32,
+    max_kbps: Option<u32>,
+    max_frame_rate: Option<f64>,
+}
+
+impl LadderRung {
+    /// A rung whose short side (height for landscape sources) is `short_side` pixels.
+    pub fn new(short_side: u32, video_kbps: u32) -> Self {
+        Self {
+            short_side,
+            video_kbps,
+            max_kbps: None,
+            max_frame_rate: None,
+        }
+    }
+
+    /// Peak video bitrate in kbps.
+    pub fn max_bitrate(mut self, kbps: u32) -> Self {
+        self.max_kbps = Some(kbps);
+        self
+    }
+
+    /// Halve (or further divide) faster sources until they are at most `fps`.
+    pub fn max_frame_rate(mut self, fps: f64) -> Self {
+        self.max_frame_rate = Some(fps);
+        self
+    }
+
+    /// Configured short side in pixels.
+    pub fn short_side(&self) -> u32 {
+        self.short_side
+    }
+
+    /// Configured average video bitrate in kbps.
+    pub fn video_bitrate_value(&self) -> u32 {
+        self.video_kbps
+    }
+
+    /// Configured peak video bitrate in kbps.
+    pub fn max_bitrate_value(&self) -> Option<u32> {
+        self.max_kbps
+    }
+
+    /// Configured frame rate ceiling.
+    pub fn max_frame_rate_value(&self) -> Option<f64> {
+        self.max_frame_rate
+    }
+}
+
+/// Which rungs to offer.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LadderPolicy {
+    /// The H.264 ladder of Apple's HLS authoring specification, with peaks at 110%.
+    AppleHls,
+    /// A custom table, in any order.
+    Custom(Vec<LadderRung>),
+}
+
+impl LadderPolicy {
+    /// Rungs of the policy, lowest first.
+    pub fn rungs(&self) -> Vec<LadderRung> {
+        let mut rungs = match self {
+            LadderPolicy::AppleHls => [
+                (234, 145, Some(30.0)),
+                (360, 365, Some(30.0)),
+                (432, 730, Some(30.0)),
+                (432, 1100, Some(30.0)),
+                (540, 2000, None),
+                (720, 3000, None),
+                (720, 4500, None),
+                (1080, 6000, None),
+                (1080, 7800, None),
+            ]
+            .into_iter()
+            .map(|(short_side, kbps, fps)| {
+                let rung = LadderRung::new(short_side, kbps).max_bitrate(kbps * 11 / 10);
+                match fps {
+                    Some(fps) => rung.max_frame_rate(fps),
+                    None => rung,
+                }
+            })
+            .collect(),
+            LadderPolicy::Custom(rungs) => rungs.clone(),
+        };
+        rungs.sort_by_key(|rung| (rung.short_side, rung.video_kbps));
+        rungs
+    }
+}
+
+/// Renditions of `policy` that fit the first video stream of `source`.
+///
+/// A source smaller than every rung still gets one rendition at its own
+/// size with the lowest rung's bitrate. Renditions are named after their
+/// height (`720p`), with the bitrate appended when a height repeats
+/// (`432p-730k`).
+pub fn generate(source: &ProbeResult, policy: &LadderPolicy) -> Result<Vec<Rendition>> {
+    let video = source
+        .first_video()
+        .ok_or_else(|| Error::InvalidInput("source has no video stream".into()))?;
+    let (width, height) = match (video.width, video.height) {
+        (Some(width), Some(height)) if width > 0 && height > 0 => (width, height),
+        _ => {
+            return Err(Error::InvalidInput(
+                "source video dimensions are unknown".into(),
+            ))
+        }
+    };
+    let rungs = policy.rungs();
+    let lowest = *rungs
+        .first()
+        .ok_or_else(|| Error::InvalidInput("ladder policy has no rungs".into()))?;
+    let source_kbps = video
+        .bit_rate
+        .or(source.format().bit_rate)
+        .map(|bps| (bps / 1000).max(1) as u32);
+    let short_side = width.min(height);
+
+    let mut fitting: Vec<LadderRung> = rungs
+        .into_iter()
+        .filter(|rung| rung.short_side <= short_side)
+        .collect();
+    if fitting.is_empty() {
+        fitting.push(LadderRung {
+            short_side,
+            ..lowest
+        });
+    }
+
+    let mut sized: Vec<(u32, u32, LadderRung)> = Vec::new();
+    for mut rung in fitting {
+        if let Some(cap) = source_kbps.filter(|cap| rung.video_kbps > *cap) {
+            rung.max_kbps = rung
+                .max_kbps
+                .map(|max| (u64::from(max) * u64::from(cap) / u64::from(rung.video_kbps)) as u32);
+            rung.video_kbps = cap;
+        }
+        let scale = f64::from(rung.short_side) / f64::from(short_side);
+        // Rounding must not push an odd-sized source's full-size rung past it.
+        let (w, h) = (
+            even(f64::from(width) * scale).min(floor_even(width)),
+            even(f64::from(height) * scale).min(floor_even(height)),
+        );
+        let duplicate = sized
+            .iter()
+            .any(|(sw, sh, other)| (*sw, *sh, other.video_kbps) == (w, h, rung.video_kbps));
+        if !duplicate {
+            sized.push((w, h, rung));
+        }
+    }
+
+    let renditions = sized
+        .iter()
+        .map(|&(w, h, rung)| {
+            let repeated = sized.iter().filter(|(_, other, _)| *other == h).count() > 1;
+            let name = if repeated {
+                format!("{h}p-{}k", rung.video_kbps)
+            } else {
+                format!("{h}p")
+            };
+            let mut rendition = Rendition::new(w, h, rung.video_kbps).name(name);
+            if let Some(max) = rung.max_kbps {
+                rendition = rendition.max_bitrate(max.max(rung.video_kbps));
+            }
+            if let (Some(limit), Some(fps)) = (rung.max_frame_rate, video.frame_rate) {
+                if fps > limit {
+                    let divisor = (fps / limit).ceil();
+                    rendition = rendition.frame_rate(fps / divisor);
+                }
+            }
+            rendition
+        })
+        .collect();
+    Ok(renditions)
+}
+
+/// Round to the nearest even number of pixels, at least 2.
+fn even(pixels: f64) -> u32 {
+    ((pixels / 2.0).round() as u32 * 2).max(2)
+}
+
+/// Largest even number of pixels not above `pixels`, at least 2.
+fn floor_even(pixels: u32) -> u32 {
+    (pixels - pixels % 2).max(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_to_even_pixels() {
+        assert_eq!(even(426.67), 426);
+        assert_eq!(even(415.0), 416);
+        assert_eq!(even(0.4), 2);
+        assert_eq!(floor_even(1079), 1078);
+    }
+}
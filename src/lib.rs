@@ -40,27 +40,67 @@
 //! # Ok::<(), ffmpeg_light::Error>(())
 //! ```
 
+/// Discovery of encoders, formats and filters supported by the installed ffmpeg.
+pub mod capabilities;
 /// Low-level process helpers for interacting with ffmpeg and ffprobe.
 pub mod command;
+/// Joining files with the concat demuxer or the concat filter.
+pub mod concat;
 /// Configuration helpers for locating ffmpeg binaries.
 pub mod config;
+/// Keyframe-aware stream-copy cutting with an optional smart-cut mode.
+pub mod cut;
+/// MPEG-DASH packaging of multiple renditions and MPD summaries.
+pub mod dash;
+/// Typed options for common encoders (x264, x265, VP9, AV1, AAC, Opus).
+pub mod encoder;
 /// Shared error type and `Result` alias used by the crate.
 pub mod error;
 /// Small collection of filter helpers used by transcoding.
 pub mod filter;
+/// HLS packaging of multiple renditions with a master playlist.
+pub mod hls;
+/// Multiple inputs and typed `-map` stream selection.
+pub mod input;
+/// GOP structure and keyframe placement for segment-aligned outputs.
+pub mod keyframe;
+/// Adaptive-bitrate ladders derived from probe results.
+pub mod ladder;
+/// Capture of ffmpeg's stderr log and forwarding to `tracing`.
+pub mod logging;
+/// Per-stream output options such as codecs, bitrates and language tags.
+pub mod output;
 /// Media probing API built on top of `ffprobe` JSON output.
 pub mod probe;
+/// Progress reports parsed from ffmpeg's `-progress` output.
+pub mod progress;
+/// Rate-control modes (CRF, capped CRF, CBR, constant QP, audio VBR).
+pub mod rate_control;
+/// Renditions of an adaptive-bitrate stream (size, bitrates, codecs).
+pub mod rendition;
+/// Pluggable process execution used by every command.
+pub mod runner;
+/// Self-cleaning scratch directories for intermediate files.
+mod tempdir;
+/// Recording mock runner for unit tests (requires the `testing` feature).
+#[cfg(feature = "testing")]
+pub mod testing;
 /// Thumbnail generation helpers.
 pub mod thumbnail;
 /// Builder API around common transcoding flows.
 pub mod transcode;
 /// Shared domain types (timecodes, codecs, stream metadata).
 pub mod types;
+/// Pre-flight validation of jobs against ffmpeg capabilities.
+pub mod validation;
 
 // Re-export main types for convenience
-pub use error::{Error, Result};
-pub use filter::{AudioFilter, VideoFilter};
+pub use capabilities::FfmpegCapabilities;
+pub use error::{Error, FailureKind, Result};
+pub use filter::{AudioFilter, FilterGraph, GraphFilter, VideoFilter};
 pub use probe::probe;
+pub use progress::Progress;
+pub use rate_control::RateControl;
 pub use thumbnail::generate as generate_thumbnail;
 pub use transcode::TranscodeBuilder;
 pub use types::*;
@@ -0,0 +1,395 @@
+//! Capture of ffmpeg's stderr log and forwarding to `tracing`.
+//!
+//! With the `tracing` feature every invocation runs inside an `ffmpeg` span
+//! (binary, argv, duration, exit status) and each stderr line becomes an event.
+//! ffmpeg runs with `-loglevel level+info`, so levels follow the `[error]`,
+//! `[warning]`, ... prefixes it prints; unprefixed lines are logged at `DEBUG`.
+//!
+//! Independently of `tracing`, [`LogCapture`](crate::logging::LogCapture)
+//! keeps the complete log, which is otherwise cut down to 4KB in
+//! [`Error::ProcessingError`].
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::process::Output;
+#[cfg(feature = "tokio")]
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+use crate::error::{Error, Result};
+use crate::runner::Invocation;
+
+/// Where the complete stderr log of an invocation is kept.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum LogCapture {
+    /// Only the truncated stderr in errors (default).
+    #[default]
+    Disabled,
+    /// Write the full log to a file (overwritten on every run).
+    File(PathBuf),
+    /// Attach the last `lines` log lines to the returned error as [`Error::WithLog`].
+    ///
+    /// Timeouts stay a bare [`Error::TimeoutError`] without the log; use
+    /// [`File`](Self::File) to keep the log of a job that timed out.
+    Memory {
+        /// Maximum number of lines kept.
+        lines: usize,
+    },
+}
+
+impl LogCapture {
+    /// Keep the last `lines` lines in memory and attach them to errors.
+    pub fn memory(lines: usize) -> Self {
+        LogCapture::Memory { lines }
+    }
+
+    /// Tee the complete log to `path`.
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        LogCapture::File(path.into())
+    }
+
+    /// Capture for one pass of a multi-pass job: files get `-pass<n>` appended to their stem.
+    pub(crate) fn for_pass(&self, pass: usize) -> Self {
+        match self {
+            LogCapture::File(path) => {
+                let mut name = path.file_stem().unwrap_or_default().to_os_string();
+                name.push(format!("-pass{pass}"));
+                if let Some(extension) = path.extension() {
+                    name.push(".");
+                    name.push(extension);
+                }
+                LogCapture::File(path.with_file_name(name))
+            }
+            other => other.clone(),
+        }
+    }
+
+    /// Persist the log of a finished invocation and attach it to `result`'s error.
+    pub(crate) fn apply(&self, output: &Output, result: Result<()>) -> Result<()> {
+        match self {
+            LogCapture::Disabled => result,
+            LogCapture::File(path) => {
+                fs::write(path, &output.stderr)?;
+                result
+            }
+            LogCapture::Memory { lines } => result.map_err(|error| Error::WithLog {
+                error: Box::new(error),
+                log: tail_lines(&output.stderr, *lines),
+            }),
+        }
+    }
+
+    /// Persist the partial log a runner attached to a timeout (see [`with_partial_log`]).
+    ///
+    /// The timeout itself is returned as [`Error::TimeoutError`] in every mode;
+    /// other errors are returned unchanged.
+    pub(crate) fn apply_to_error(&self, error: Error) -> Error {
+        let (error, log) = match error {
+            Error::WithLog { error, log } if matches!(*error, Error::TimeoutError(_)) => {
+                (*error, log)
+            }
+            other => return other,
+        };
+        match self {
+            LogCapture::Disabled => error,
+            LogCapture::File(path) => {
+                let mut text = log.join("\n");
+                text.push('\n');
+                match fs::write(path, text) {
+                    Ok(()) => error,
+                    Err(err) => err.into(),
+                }
+            }
+            LogCapture::Memory { .. } => error,
+        }
+    }
+}
+
+/// Attach everything logged before a timeout so [`LogCapture::apply_to_error`] can keep it.
+pub(crate) fn with_partial_log(error: Error, stderr: &[u8]) -> Error {
+    Error::WithLog {
+        error: Box::new(error),
+        log: tail_lines(stderr, usize::MAX),
+    }
+}
+
+/// Last `limit` lines of `log`, using a ring buffer so huge logs stay cheap.
+fn tail_lines(log: &[u8], limit: usize) -> Vec<String> {
+    let mut ring = VecDeque::with_capacity(limit.min(1024));
+    for line in split_lines(&String::from_utf8_lossy(log)) {
+        if ring.len() == limit {
+            ring.pop_front();
+        }
+        if limit > 0 {
+            ring.push_back(line.to_string());
+        }
+    }
+    ring.into()
+}
+
+/// Split on `\n` as well as the `\r` ffmpeg uses for in-place status updates.
+fn split_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.split(['\n', '\r'])
+        .filter(|line| !line.trim().is_empty())
+}
+
+/// Severity parsed from ffmpeg's `[level]` line prefixes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+impl LogLevel {
+    /// Level of a line such as `[h264 @ 0x55d0] [error] no frame!`.
+    #[cfg(test)]
+    pub(crate) fn of_line(line: &str) -> Option<Self> {
+        split_level(line).map(|(level, _)| level)
+    }
+}
+
+/// Split the `[level]` tag off a line, keeping any `[context @ 0x..]` prefix.
+///
+/// `[h264 @ 0x55d0] [error] no frame!` becomes `Error` and
+/// `[h264 @ 0x55d0] no frame!`. Only the leading bracketed tags are looked at.
+pub(crate) fn split_level(line: &str) -> Option<(LogLevel, String)> {
+    let mut offset = 0;
+    while let Some(rest) = line[offset..].strip_prefix('[') {
+        let end = offset + 1 + rest.find(']')?;
+        let level = match &line[offset + 1..end] {
+            "panic" | "fatal" | "error" => Some(LogLevel::Error),
+            "warning" => Some(LogLevel::Warning),
+            "info" => Some(LogLevel::Info),
+            "verbose" | "debug" => Some(LogLevel::Debug),
+            "trace" => Some(LogLevel::Trace),
+            _ => None,
+        };
+        if let Some(level) = level {
+            let message = line[end + 1..].trim_start();
+            return Some((level, format!("{}{message}", &line[..offset])));
+        }
+        offset = end + 1;
+        offset += line[offset..].len() - line[offset..].trim_start().len();
+    }
+    None
+}
+
+/// Read a stderr pipe to the end, emitting one event per line.
+pub(crate) fn read_stderr(pipe: impl Read) -> io::Result<Vec<u8>> {
+    let mut reader = BufReader::new(pipe);
+    let mut log = Vec::new();
+    let mut chunk = Vec::new();
+    loop {
+        chunk.clear();
+        if reader.read_until(b'\n', &mut chunk)? == 0 {
+            return Ok(log);
+        }
+        for line in split_lines(&String::from_utf8_lossy(&chunk)) {
+            emit_line(line);
+        }
+        log.extend_from_slice(&chunk);
+    }
+}
+
+/// Read a stderr pipe on a task in the current span, emitting one event per line.
+///
+/// Lines are appended to `log` as they arrive, so whatever was read is still
+/// there when the process is abandoned on a timeout.
+#[cfg(feature = "tokio")]
+pub(crate) fn spawn_stderr_reader<R>(
+    pipe: R,
+    log: Arc<Mutex<Vec<u8>>>,
+) -> tokio::task::JoinHandle<io::Result<()>>
+where
+    R: tokio::io::AsyncRead + Unpin + Send + 'static,
+{
+    use tokio::io::AsyncBufReadExt;
+
+    let read = async move {
+        let mut reader = tokio::io::BufReader::new(pipe);
+        let mut chunk = Vec::new();
+        loop {
+            chunk.clear();
+            if reader.read_until(b'\n', &mut chunk).await? == 0 {
+                return Ok(());
+            }
+            for line in split_lines(&String::from_utf8_lossy(&chunk)) {
+                emit_line(line);
+            }
+            log.lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .extend_from_slice(&chunk);
+        }
+    };
+    #[cfg(feature = "tracing")]
+    let read = tracing::Instrument::in_current_span(read);
+    tokio::spawn(read)
+}
+
+/// Take the bytes collected by [`spawn_stderr_reader`].
+#[cfg(feature = "tokio")]
+pub(crate) fn take_log(log: &Mutex<Vec<u8>>) -> Vec<u8> {
+    std::mem::take(&mut *log.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+}
+
+#[cfg(feature = "tracing")]
+fn emit_line(line: &str) {
+    let (level, line) = split_level(line).unwrap_or((LogLevel::Debug, line.to_string()));
+    match level {
+        LogLevel::Error => tracing::error!(target: "ffmpeg_light::ffmpeg", "{line}"),
+        LogLevel::Warning => tracing::warn!(target: "ffmpeg_light::ffmpeg", "{line}"),
+        LogLevel::Info => tracing::info!(target: "ffmpeg_light::ffmpeg", "{line}"),
+        LogLevel::Debug => tracing::debug!(target: "ffmpeg_light::ffmpeg", "{line}"),
+        LogLevel::Trace => tracing::trace!(target: "ffmpeg_light::ffmpeg", "{line}"),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+fn emit_line(_line: &str) {}
+
+/// Span covering one process invocation (a no-op without `tracing`).
+pub(crate) struct InvocationSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    started: Instant,
+}
+
+impl InvocationSpan {
+    #[cfg(feature = "tracing")]
+    pub(crate) fn new(invocation: &Invocation) -> Self {
+        let span = tracing::info_span!(
+            "ffmpeg",
+            binary = %invocation.binary.display(),
+            argv = %invocation.to_shell_string(),
+            duration_ms = tracing::field::Empty,
+            exit_code = tracing::field::Empty,
+        );
+        Self {
+            span,
+            started: Instant::now(),
+        }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    pub(crate) fn new(_invocation: &Invocation) -> Self {
+        Self {}
+    }
+
+    /// Run `f` inside the span; reader threads pick it up via [`in_current_span`].
+    pub(crate) fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.in_scope(f)
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            f()
+        }
+    }
+
+    /// Record duration and exit status once the process is done.
+    pub(crate) fn finish(&self, result: &Result<Output>) {
+        #[cfg(feature = "tracing")]
+        {
+            let elapsed = self.started.elapsed().as_millis() as u64;
+            self.span.record("duration_ms", elapsed);
+            self.span.in_scope(|| match result {
+                Ok(output) => {
+                    if let Some(code) = output.status.code() {
+                        self.span.record("exit_code", code);
+                    }
+                    tracing::debug!(status = %output.status, "process exited");
+                }
+                Err(err) => tracing::warn!(error = %err, "process did not complete"),
+            });
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = result;
+    }
+}
+
+/// Carry the current span into a reader thread.
+pub(crate) fn in_current_span<T>(f: impl FnOnce() -> T) -> impl FnOnce() -> T {
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::Span::current();
+        move || span.in_scope(f)
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        f
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_loglevel_prefixes() {
+        assert_eq!(
+            LogLevel::of_line("[h264 @ 0x55d0] [error] no frame!"),
+            Some(LogLevel::Error)
+        );
+        assert_eq!(
+            LogLevel::of_line("[warning] deprecated pixel format"),
+            Some(LogLevel::Warning)
+        );
+        assert_eq!(
+            LogLevel::of_line("[info] Stream mapping:"),
+            Some(LogLevel::Info)
+        );
+        assert_eq!(LogLevel::of_line("Stream mapping:"), None);
+        assert_eq!(LogLevel::of_line("out [error].mp4: done"), None);
+        assert_eq!(
+            split_level("[in#0 @ 0x55d0] [error] Error opening input: No such file"),
+            Some((
+                LogLevel::Error,
+                "[in#0 @ 0x55d0] Error opening input: No such file".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn partial_log_follows_capture_mode() {
+        let timeout = || {
+            with_partial_log(
+                Error::TimeoutError("slow".into()),
+                b"[info] one\n[info] two\n",
+            )
+        };
+        assert!(matches!(
+            LogCapture::Disabled.apply_to_error(timeout()),
+            Error::TimeoutError(_)
+        ));
+        assert!(matches!(
+            LogCapture::memory(1).apply_to_error(timeout()),
+            Error::TimeoutError(_)
+        ));
+        let dir = crate::tempdir::TempDir::new("ffmpeg-light-test").unwrap();
+        let path = dir.path().join("ffmpeg.log");
+        assert!(matches!(
+            LogCapture::file(&path).apply_to_error(timeout()),
+            Error::TimeoutError(_)
+        ));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[info] one\n[info] two\n"
+        );
+    }
+
+    #[test]
+    fn tail_keeps_last_lines() {
+        let log = b"one\ntwo\rthree\n\nfour\n";
+        assert_eq!(tail_lines(log, 2), ["three", "four"]);
+        assert!(tail_lines(log, 0).is_empty());
+    }
+}
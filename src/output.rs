@@ -0,0 +1,257 @@
+//! Per-stream output configuration (`-c:a:1`, `-b:a:1`, `-metadata:s:a:1`, ...).
+
+use std::ffi::OsString;
+use std::fmt;
+
+use crate::input::stream_specifier;
+use crate::types::StreamType;
+
+/// Disposition flags marking the role of an output stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Disposition {
+    /// Preferred stream of its type.
+    Default,
+    /// Dubbed audio.
+    Dub,
+    /// Original-language audio.
+    Original,
+    /// Commentary track.
+    Comment,
+    /// Subtitles that must always be shown.
+    Forced,
+    /// Track for the hearing impaired (e.g. SDH subtitles).
+    HearingImpaired,
+    /// Track for the visually impaired (e.g. audio description).
+    VisualImpaired,
+    /// Cover art or another attached picture.
+    AttachedPic,
+}
+
+impl Disposition {
+    /// Name used by ffmpeg's `-disposition` option.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Disposition::Default => "default",
+            Disposition::Dub => "dub",
+            Disposition::Original => "original",
+            Disposition::Comment => "comment",
+            Disposition::Forced => "forced",
+            Disposition::HearingImpaired => "hearing_impaired",
+            Disposition::VisualImpaired => "visual_impaired",
+            Disposition::AttachedPic => "attached_pic",
+        }
+    }
+}
+
+impl fmt::Display for Disposition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Options for one output stream, addressed by type and index.
+///
+/// ```rust
+/// use ffmpeg_light::output::OutputStream;
+///
+/// let stream = OutputStream::audio(1).codec("ac3").bitrate(448).channels(6).language("fre");
+/// let args: Vec<String> = stream
+///     .to_args()
+///     .iter()
+///     .map(|arg| arg.to_string_lossy().into_owned())
+///     .collect();
+/// assert_eq!(
+///     args,
+///     ["-c:a:1", "ac3", "-b:a:1", "448k", "-ac:a:1", "6", "-metadata:s:a:1", "language=fre"]
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputStream {
+    kind: StreamType,
+    index: Option<usize>,
+    codec: Option<String>,
+    bitrate: Option<u32>,
+    quality: Option<f32>,
+    channels: Option<u32>,
+    channel_layout: Option<String>,
+    sample_rate: Option<u32>,
+    dispositions: Option<Vec<Disposition>>,
+    metadata: Vec<(String, String)>,
+}
+
+impl OutputStream {
+    /// Configure the `index`-th output stream of `kind`.
+    pub fn new(kind: StreamType, index: usize) -> Self {
+        Self::with_index(kind, Some(index))
+    }
+
+    /// Configure every output stream of `kind`.
+    pub fn all(kind: StreamType) -> Self {
+        Self::with_index(kind, None)
+    }
+
+    /// Configure the `index`-th video output stream.
+    pub fn video(index: usize) -> Self {
+        Self::new(StreamType::Video, index)
+    }
+
+    /// Configure the `index`-th audio output stream.
+    pub fn audio(index: usize) -> Self {
+        Self::new(StreamType::Audio, index)
+    }
+
+    /// Configure the `index`-th subtitle output stream.
+    pub fn subtitle(index: usize) -> Self {
+        Self::new(StreamType::Subtitle, index)
+    }
+
+    fn with_index(kind: StreamType, index: Option<usize>) -> Self {
+        Self {
+            kind,
+            index,
+            codec: None,
+            bitrate: None,
+            quality: None,
+            channels: None,
+            channel_layout: None,
+            sample_rate: None,
+            dispositions: None,
+            metadata: Vec::new(),
+        }
+    }
+
+    /// Encoder for this stream (e.g. `aac`, `ac3`, `copy`).
+    pub fn codec(mut self, codec: impl Into<String>) -> Self {
+        self.codec = Some(codec.into());
+        self
+    }
+
+    /// Target bitrate in kbps.
+    pub fn bitrate(mut self, kbps: u32) -> Self {
+        self.bitrate = Some(kbps);
+        self
+    }
+
+    /// Encoder-specific quality scale (maps to `-q`).
+    pub fn quality(mut self, quality: f32) -> Self {
+        self.quality = Some(quality);
+        self
+    }
+
+    /// Number of audio channels (maps to `-ac`).
+    pub fn channels(mut self, channels: u32) -> Self {
+        self.channels = Some(channels);
+        self
+    }
+
+    /// Audio channel layout such as `stereo` or `5.1` (maps to `-ch_layout`, ffmpeg 5.1+).
+    pub fn channel_layout(mut self, layout: impl Into<String>) -> Self {
+        self.channel_layout = Some(layout.into());
+        self
+    }
+
+    /// Audio sample rate in Hz (maps to `-ar`).
+    pub fn sample_rate(mut self, hz: u32) -> Self {
+        self.sample_rate = Some(hz);
+        self
+    }
+
+    /// Add a disposition flag.
+    pub fn disposition(mut self, disposition: Disposition) -> Self {
+        self.dispositions
+            .get_or_insert_with(Vec::new)
+            .push(disposition);
+        self
+    }
+
+    /// Clear all disposition flags inherited from the input (renders `0`).
+    pub fn clear_disposition(mut self) -> Self {
+        self.dispositions = Some(Vec::new());
+        self
+    }
+
+    /// Set a metadata tag on the stream.
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.push((key.into(), value.into()));
+        self
+    }
+
+    /// Set the ISO 639-2 `language` tag (e.g. `eng`).
+    pub fn language(self, language: impl Into<String>) -> Self {
+        self.metadata("language", language)
+    }
+
+    /// Set the stream `title` tag.
+    pub fn title(self, title: impl Into<String>) -> Self {
+        self.metadata("title", title)
+    }
+
+    /// Stream type this configuration applies to.
+    pub fn kind(&self) -> &StreamType {
+        &self.kind
+    }
+
+    /// Output stream index, `None` for all streams of the type.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// Configured encoder.
+    pub fn codec_ref(&self) -> Option<&str> {
+        self.codec.as_deref()
+    }
+
+    /// Stream specifier such as `a:1` (or `a` for every audio stream).
+    pub fn specifier(&self) -> String {
+        let kind = stream_specifier(&self.kind);
+        match self.index {
+            Some(index) => format!("{kind}:{index}"),
+            None => kind.to_string(),
+        }
+    }
+
+    /// Render the per-stream flags.
+    pub fn to_args(&self) -> Vec<OsString> {
+        let spec = self.specifier();
+        let mut args: Vec<OsString> = Vec::new();
+        let mut push = |flag: &str, value: String| {
+            args.push(format!("-{flag}:{spec}").into());
+            args.push(value.into());
+        };
+        if let Some(codec) = &self.codec {
+            push("c", codec.clone());
+        }
+        if let Some(kbps) = self.bitrate {
+            push("b", format!("{kbps}k"));
+        }
+        if let Some(quality) = self.quality {
+            push("q", quality.to_string());
+        }
+        if let Some(channels) = self.channels {
+            push("ac", channels.to_string());
+        }
+        if let Some(layout) = &self.channel_layout {
+            push("ch_layout", layout.clone());
+        }
+        if let Some(hz) = self.sample_rate {
+            push("ar", hz.to_string());
+        }
+        if let Some(dispositions) = &self.dispositions {
+            let value = if dispositions.is_empty() {
+                "0".to_string()
+            } else {
+                dispositions
+                    .iter()
+                    .map(|disposition| disposition.as_str())
+                    .collect::<Vec<_>>()
+                    .join("+")
+            };
+            push("disposition", value);
+        }
+        for (key, value) in &self.metadata {
+            args.push(format!("-metadata:s:{spec}").into());
+            args.push(format!("{key}={value}").into());
+        }
+        args
+    }
+}
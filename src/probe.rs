@@ -1,16 +1,16 @@
 //! Media probing utilities built on top of `ffprobe`.
 
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
 use serde::Deserialize;
 
-#[cfg(feature = "tokio")]
-use crate::command::ffprobe_json_async;
-use crate::command::{ffprobe_json, FfmpegBinaryPaths};
-use crate::config::FfmpegLocator;
-use crate::error::Result;
+use crate::command::{FfmpegBinaryPaths, FfprobeCommand};
+use crate::config::{FfmpegLocator, Tools};
+use crate::error::{Error, Result};
+use crate::runner::{default_runner, CommandRunner};
 use crate::types::{
     AudioStreamInfo, CodecType, DataStreamInfo, FormatInfo, ProbeResult, StreamInfo,
     SubtitleStreamInfo, VideoStreamInfo,
@@ -31,7 +31,7 @@ pub async fn probe_async(path: impl AsRef<Path>) -> Result<ProbeResult> {
 
 /// Probe a file with a pre-configured locator (useful for custom binary paths).
 pub fn probe_with_locator(locator: &FfmpegLocator, path: impl AsRef<Path>) -> Result<ProbeResult> {
-    probe_with_binaries(locator.binaries(), path)
+    probe_with_runner(locator.binaries(), locator.runner(), path.as_ref(), None)
 }
 
 /// Async variant of [`probe_with_locator`] (requires the `tokio` feature).
@@ -40,7 +40,7 @@ pub async fn probe_with_locator_async(
     locator: &FfmpegLocator,
     path: impl AsRef<Path>,
 ) -> Result<ProbeResult> {
-    probe_with_binaries_async(locator.binaries(), path).await
+    probe_with_runner_async(locator.binaries(), locator.runner(), path.as_ref(), None).await
 }
 
 /// Probe a file using already-resolved binaries.
@@ -48,8 +48,7 @@ pub fn probe_with_binaries(
     paths: &FfmpegBinaryPaths,
     path: impl AsRef<Path>,
 ) -> Result<ProbeResult> {
-    let json = ffprobe_json(paths, path.as_ref())?;
-    parse_probe_output(&json)
+    probe_with_runner(paths, &default_runner(), path.as_ref(), None)
 }
 
 /// Async variant of [`probe_with_binaries`] (requires the `tokio` feature).
@@ -58,11 +57,226 @@ pub async fn probe_with_binaries_async(
     paths: &FfmpegBinaryPaths,
     path: impl AsRef<Path>,
 ) -> Result<ProbeResult> {
-    let json = ffprobe_json_async(paths, path.as_ref()).await?;
+    probe_with_runner_async(paths, &default_runner(), path.as_ref(), None).await
+}
+
+/// Probe with a timeout, custom binaries or a custom runner.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// use ffmpeg_light::probe::ProbeBuilder;
+///
+/// let info = ProbeBuilder::new("input.mp4")
+///     .timeout(Duration::from_secs(10))
+///     .run()?;
+/// # Ok::<(), ffmpeg_light::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct ProbeBuilder {
+    tools: Tools,
+    path: PathBuf,
+}
+
+impl ProbeBuilder {
+    /// Create a builder probing `path` with binaries discovered on PATH.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            tools: Tools::default(),
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Use pre-discovered binaries instead of searching PATH.
+    pub fn with_binaries(mut self, binaries: &FfmpegBinaryPaths) -> Self {
+        self.tools.binaries = Some(binaries.clone());
+        self
+    }
+
+    /// Pin the builder to a specific locator.
+    pub fn with_locator(mut self, locator: &FfmpegLocator) -> Self {
+        self.tools.locate(locator);
+        self
+    }
+
+    /// Execute ffprobe through a custom [`CommandRunner`].
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.tools.runner = Some(runner);
+        self
+    }
+
+    /// Fail with [`Error::TimeoutError`] if ffprobe runs longer than `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.tools.timeout = Some(timeout);
+        self
+    }
+
+    /// Accessor for the configured timeout.
+    pub fn timeout_value(&self) -> Option<Duration> {
+        self.tools.timeout
+    }
+
+    /// Run ffprobe and parse its output.
+    pub fn run(self) -> Result<ProbeResult> {
+        let (binaries, runner) = self.tools.resolve()?;
+        probe_with_runner(&binaries, &runner, &self.path, self.tools.timeout)
+    }
+
+    /// Async variant of [`run`](Self::run) (requires `tokio`).
+    ///
+    /// Dropping the future kills ffprobe.
+    #[cfg(feature = "tokio")]
+    pub async fn run_async(self) -> Result<ProbeResult> {
+        let (binaries, runner) = self.tools.resolve()?;
+        probe_with_runner_async(&binaries, &runner, &self.path, self.tools.timeout).await
+    }
+}
+
+/// Render the ffprobe command [`probe`] would run, without running it.
+///
+/// The returned string is POSIX shell-quoted and safe to paste into a terminal.
+pub fn dry_run(path: impl AsRef<Path>) -> Result<String> {
+    let locator = FfmpegLocator::system()?;
+    Ok(dry_run_with_binaries(locator.binaries(), path))
+}
+
+/// Same as [`dry_run`] but reuses already-discovered binaries.
+pub fn dry_run_with_binaries(paths: &FfmpegBinaryPaths, path: impl AsRef<Path>) -> String {
+    FfprobeCommand::new(paths.ffprobe(), path.as_ref()).to_shell_string()
+}
+
+/// Presentation timestamps of the keyframes in the first video stream.
+///
+/// Keyframes are read from the packet flags without decoding anything. Times
+/// count from the start of the file, the same origin as `-ss`, so they do not
+/// shift for inputs such as MPEG-TS whose timestamps start above zero. Compare renditions with
+/// [`misaligned_keyframes`](crate::keyframe::misaligned_keyframes).
+pub fn keyframe_timestamps(path: impl AsRef<Path>) -> Result<Vec<Duration>> {
+    let locator = FfmpegLocator::system()?;
+    keyframe_timestamps_with_locator(&locator, path)
+}
+
+/// Same as [`keyframe_timestamps`] with a pre-configured locator.
+pub fn keyframe_timestamps_with_locator(
+    locator: &FfmpegLocator,
+    path: impl AsRef<Path>,
+) -> Result<Vec<Duration>> {
+    let output =
+        keyframe_command(locator.binaries(), locator.runner(), path.as_ref(), None).run()?;
+    Ok(KeyframeIndex::parse(&output.stdout)?.keyframes)
+}
+
+/// Async variant of [`keyframe_timestamps_with_locator`] (requires the `tokio` feature).
+#[cfg(feature = "tokio")]
+pub async fn keyframe_timestamps_with_locator_async(
+    locator: &FfmpegLocator,
+    path: impl AsRef<Path>,
+) -> Result<Vec<Duration>> {
+    let output = keyframe_command(locator.binaries(), locator.runner(), path.as_ref(), None)
+        .run_async()
+        .await?;
+    Ok(KeyframeIndex::parse(&output.stdout)?.keyframes)
+}
+
+/// ffprobe listing the packets of the first video stream.
+pub(crate) fn keyframe_command(
+    paths: &FfmpegBinaryPaths,
+    runner: &Arc<dyn CommandRunner>,
+    path: &Path,
+    timeout: Option<Duration>,
+) -> FfprobeCommand {
+    let mut cmd = ffprobe_command(paths, runner, path, timeout);
+    cmd.arg("-select_streams")
+        .arg("v:0")
+        .arg("-show_entries")
+        .arg("packet=pts_time,dts_time,duration_time,flags");
+    cmd
+}
+
+/// Keyframes and end of the first video stream.
+#[derive(Debug, PartialEq)]
+pub(crate) struct KeyframeIndex {
+    /// Sorted timestamps of the packets flagged as keyframes (`K`).
+    pub(crate) keyframes: Vec<Duration>,
+    /// End of the last packet.
+    pub(crate) end: Duration,
+}
+
+impl KeyframeIndex {
+    /// Read the index, counting every time from the stream's `start_time`, as
+    /// input `-ss` does, rather than from raw packet timestamps.
+    pub(crate) fn parse(json: &[u8]) -> Result<Self> {
+        let data: FfprobePackets = serde_json::from_slice(json)?;
+        let (start, length) = data.format.map_or((None, None), |format| {
+            (
+                parse_signed_seconds(format.start_time),
+                parse_seconds(format.duration),
+            )
+        });
+        let start = start.unwrap_or_default();
+        // Leading packets can sit slightly before the start after edit lists.
+        let offset = |seconds: f64| Duration::from_secs_f64((seconds - start).max(0.0));
+        let mut keyframes = Vec::new();
+        let mut end = Duration::ZERO;
+        for packet in data.packets {
+            let Some(pts) = parse_signed_seconds(packet.pts_time.or(packet.dts_time)) else {
+                continue;
+            };
+            let duration = parse_signed_seconds(packet.duration_time).unwrap_or_default();
+            end = end.max(offset(pts + duration));
+            if packet.flags.is_some_and(|flags| flags.contains('K')) {
+                keyframes.push(offset(pts));
+            }
+        }
+        keyframes.sort();
+        keyframes.dedup();
+        Ok(Self {
+            keyframes,
+            end: end.max(length.unwrap_or_default()),
+        })
+    }
+}
+
+fn ffprobe_command(
+    paths: &FfmpegBinaryPaths,
+    runner: &Arc<dyn CommandRunner>,
+    path: &Path,
+    timeout: Option<Duration>,
+) -> FfprobeCommand {
+    let mut cmd = FfprobeCommand::new(paths.ffprobe(), path);
+    cmd.runner(Arc::clone(runner));
+    if let Some(timeout) = timeout {
+        cmd.timeout(timeout);
+    }
+    cmd
+}
+
+pub(crate) fn probe_with_runner(
+    paths: &FfmpegBinaryPaths,
+    runner: &Arc<dyn CommandRunner>,
+    path: &Path,
+    timeout: Option<Duration>,
+) -> Result<ProbeResult> {
+    let output = ffprobe_command(paths, runner, path, timeout).run()?;
+    let json = String::from_utf8(output.stdout).map_err(|err| Error::Parse(err.to_string()))?;
+    parse_probe_output(&json)
+}
+
+#[cfg(feature = "tokio")]
+pub(crate) async fn probe_with_runner_async(
+    paths: &FfmpegBinaryPaths,
+    runner: &Arc<dyn CommandRunner>,
+    path: &Path,
+    timeout: Option<Duration>,
+) -> Result<ProbeResult> {
+    let output = ffprobe_command(paths, runner, path, timeout)
+        .run_async()
+        .await?;
+    let json = String::from_utf8(output.stdout).map_err(|err| Error::Parse(err.to_string()))?;
     parse_probe_output(&json)
 }
 
-fn parse_probe_output(json: &str) -> Result<ProbeResult> {
+pub(crate) fn parse_probe_output(json: &str) -> Result<ProbeResult> {
     let data: FfprobeOutput = serde_json::from_str(json)?;
     let format = data
         .format
@@ -83,6 +297,27 @@ struct FfprobeOutput {
     streams: Vec<FfprobeStream>,
 }
 
+#[derive(Debug, Deserialize)]
+struct FfprobePackets {
+    #[serde(default)]
+    packets: Vec<FfprobePacket>,
+    format: Option<FfprobePacketsFormat>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FfprobePacket {
+    pts_time: Option<String>,
+    dts_time: Option<String>,
+    duration_time: Option<String>,
+    flags: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FfprobePacketsFormat {
+    start_time: Option<String>,
+    duration: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct FfprobeFormat {
     format_name: Option<String>,
@@ -100,8 +335,15 @@ struct FfprobeStream {
     height: Option<u32>,
     bit_rate: Option<String>,
     avg_frame_rate: Option<String>,
+    pix_fmt: Option<String>,
+    profile: Option<String>,
+    level: Option<i32>,
+    sample_aspect_ratio: Option<String>,
+    time_base: Option<String>,
     channels: Option<u32>,
+    channel_layout: Option<String>,
     sample_rate: Option<String>,
+    sample_fmt: Option<String>,
     tags: Option<HashMap<String, String>>,
 }
 
@@ -128,12 +370,22 @@ fn stream_info_from_ffprobe(stream: FfprobeStream) -> Option<StreamInfo> {
             height: stream.height,
             bit_rate: parse_u64(stream.bit_rate.as_deref()),
             frame_rate: parse_ratio(stream.avg_frame_rate.as_deref()),
+            pix_fmt: stream.pix_fmt,
+            profile: stream.profile,
+            // ffprobe reports -99 for an unknown level.
+            level: stream.level.filter(|level| *level > 0),
+            sample_aspect_ratio: stream.sample_aspect_ratio.filter(|sar| {
+                parse_ratio(Some(&sar.replace(':', "/"))).is_some_and(|ratio| ratio > 0.0)
+            }),
+            time_base: stream.time_base,
         })),
         Some("audio") => Some(StreamInfo::Audio(AudioStreamInfo {
             codec,
             channels: stream.channels,
             sample_rate: parse_u32(stream.sample_rate.as_deref()),
             bit_rate: parse_u64(stream.bit_rate.as_deref()),
+            channel_layout: stream.channel_layout,
+            sample_fmt: stream.sample_fmt,
         })),
         Some("subtitle") => {
             let language = stream
@@ -158,6 +410,15 @@ fn parse_duration(raw: Option<&str>) -> Option<Duration> {
         .map(Duration::from_secs_f64)
 }
 
+fn parse_seconds(raw: Option<String>) -> Option<Duration> {
+    parse_signed_seconds(raw).map(|seconds| Duration::from_secs_f64(seconds.max(0.0)))
+}
+
+fn parse_signed_seconds(raw: Option<String>) -> Option<f64> {
+    let seconds: f64 = raw?.parse().ok()?;
+    seconds.is_finite().then_some(seconds)
+}
+
 fn parse_u64(raw: Option<&str>) -> Option<u64> {
     raw.and_then(|value| value.parse().ok())
 }
@@ -0,0 +1,226 @@
+//! Progress reporting built on ffmpeg's `-progress` key/value output.
+
+use std::time::Duration;
+
+use crate::types::Time;
+
+/// Snapshot of an ffmpeg job's progress.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Progress {
+    /// Number of frames written so far.
+    pub frame: Option<u64>,
+    /// Current encoding speed in frames per second.
+    pub fps: Option<f64>,
+    /// Timestamp of the last written output packet.
+    pub out_time: Option<Time>,
+    /// Current output bitrate in kbit/s.
+    pub bitrate_kbps: Option<f64>,
+    /// Bytes written to the output so far.
+    pub total_size: Option<u64>,
+    /// Processing speed relative to realtime (e.g. `2.0` for 2x).
+    pub speed: Option<f64>,
+    /// Frames duplicated to keep the output frame rate.
+    pub dup_frames: Option<u64>,
+    /// Frames dropped to keep the output frame rate.
+    pub drop_frames: Option<u64>,
+    /// Completion percentage (0-100) when the input duration is known.
+    pub percent: Option<f64>,
+    /// Estimated remaining wall-clock time when the input duration is known.
+    pub eta: Option<Duration>,
+    /// True for the final report emitted when ffmpeg finishes.
+    pub finished: bool,
+    /// Current pass (1-based) of a multi-pass encode.
+    pub pass: Option<usize>,
+}
+
+impl Progress {
+    /// Rescale a report of pass `pass` onto the whole `passes`-pass job.
+    ///
+    /// Percent covers all passes; the ETA assumes the remaining passes run at
+    /// the current speed.
+    pub(crate) fn for_pass(mut self, pass: usize, passes: usize, total: Option<Duration>) -> Self {
+        if passes <= 1 {
+            return self;
+        }
+        let remaining_passes = passes.saturating_sub(pass) as f64;
+        self.pass = Some(pass);
+        self.percent = self
+            .percent
+            .map(|percent| ((pass - 1) as f64 * 100.0 + percent) / passes as f64);
+        self.eta = match (self.eta, total, self.speed) {
+            (Some(eta), Some(total), Some(speed)) if speed > 0.0 => {
+                Some(eta + Duration::from_secs_f64(total.as_secs_f64() / speed * remaining_passes))
+            }
+            _ if remaining_passes > 0.0 => None,
+            (eta, ..) => eta,
+        };
+        self.finished = self.finished && pass == passes;
+        self
+    }
+
+    fn update_estimates(&mut self, total: Option<Duration>) {
+        if self.finished {
+            self.percent = Some(100.0);
+            self.eta = Some(Duration::ZERO);
+            return;
+        }
+        let (Some(total), Some(out_time)) = (total, self.out_time) else {
+            return;
+        };
+        let total = total.as_secs_f64();
+        if total <= 0.0 {
+            return;
+        }
+        let done = out_time.as_duration().as_secs_f64().min(total);
+        self.percent = Some(done / total * 100.0);
+        self.eta = match self.speed {
+            Some(speed) if speed > 0.0 => Some(Duration::from_secs_f64((total - done) / speed)),
+            _ => None,
+        };
+    }
+}
+
+/// Incremental parser for the output of `ffmpeg -progress`.
+///
+/// ffmpeg emits blocks of `key=value` lines terminated by `progress=continue`
+/// or `progress=end`; one [`Progress`] is produced per block.
+#[derive(Debug, Default)]
+pub struct ProgressParser {
+    current: Progress,
+    total: Option<Duration>,
+}
+
+impl ProgressParser {
+    /// Create a parser; `total` enables percent/ETA calculations.
+    pub fn new(total: Option<Duration>) -> Self {
+        Self {
+            current: Progress::default(),
+            total,
+        }
+    }
+
+    /// Feed one line of output, returning a snapshot when a block completes.
+    pub fn push_line(&mut self, line: &str) -> Option<Progress> {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+        match key {
+            "frame" => self.current.frame = value.parse().ok(),
+            "fps" => self.current.fps = value.parse().ok(),
+            "bitrate" => {
+                self.current.bitrate_kbps = value.trim_end_matches("kbits/s").parse().ok();
+            }
+            "total_size" => self.current.total_size = value.parse().ok(),
+            // `out_time_ms` is historically in microseconds as well.
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(micros) = value.parse::<u64>() {
+                    self.current.out_time =
+                        Some(Time::from_duration(Duration::from_micros(micros)));
+                }
+            }
+            "out_time" => {
+                if let Some(time) = parse_timestamp(value) {
+                    self.current.out_time = Some(time);
+                }
+            }
+            "dup_frames" => self.current.dup_frames = value.parse().ok(),
+            "drop_frames" => self.current.drop_frames = value.parse().ok(),
+            "speed" => self.current.speed = value.trim_end_matches('x').parse().ok(),
+            "progress" => {
+                let mut snapshot = std::mem::take(&mut self.current);
+                snapshot.finished = value == "end";
+                snapshot.update_estimates(self.total);
+                return Some(snapshot);
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Parse `HH:MM:SS.micros` timestamps as printed by ffmpeg.
+fn parse_timestamp(raw: &str) -> Option<Time> {
+    let negative = raw.starts_with('-');
+    let mut parts = raw.trim_start_matches('-').splitn(3, ':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    if negative {
+        return Some(Time::zero());
+    }
+    Some(Time::from_seconds_f64(
+        hours * 3600.0 + minutes * 60.0 + seconds,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: &str = "frame=120\n\
+        fps=59.94\n\
+        stream_0_0_q=28.0\n\
+        bitrate=1536.2kbits/s\n\
+        total_size=786432\n\
+        out_time_us=5000000\n\
+        out_time_ms=5000000\n\
+        out_time=00:00:05.000000\n\
+        dup_frames=1\n\
+        drop_frames=2\n\
+        speed=2.5x\n\
+        progress=continue\n";
+
+    #[test]
+    fn parses_progress_block() {
+        let mut parser = ProgressParser::new(Some(Duration::from_secs(20)));
+        let snapshots: Vec<_> = BLOCK.lines().filter_map(|l| parser.push_line(l)).collect();
+        assert_eq!(snapshots.len(), 1);
+        let progress = &snapshots[0];
+        assert_eq!(progress.frame, Some(120));
+        assert_eq!(progress.fps, Some(59.94));
+        assert_eq!(progress.bitrate_kbps, Some(1536.2));
+        assert_eq!(progress.total_size, Some(786_432));
+        assert_eq!(progress.out_time, Some(Time::from_seconds(5)));
+        assert_eq!(progress.dup_frames, Some(1));
+        assert_eq!(progress.drop_frames, Some(2));
+        assert_eq!(progress.speed, Some(2.5));
+        assert_eq!(progress.percent, Some(25.0));
+        assert_eq!(progress.eta, Some(Duration::from_secs(6)));
+        assert!(!progress.finished);
+    }
+
+    #[test]
+    fn handles_unavailable_values_and_end_marker() {
+        let mut parser = ProgressParser::new(None);
+        for line in ["bitrate=N/A", "speed=N/A", "out_time=-577014:32:22.775808"] {
+            assert!(parser.push_line(line).is_none());
+        }
+        let progress = parser.push_line("progress=end").unwrap();
+        assert!(progress.finished);
+        assert_eq!(progress.bitrate_kbps, None);
+        assert_eq!(progress.speed, None);
+        assert_eq!(progress.out_time, Some(Time::zero()));
+        assert_eq!(progress.percent, Some(100.0));
+    }
+
+    #[test]
+    fn rescales_progress_across_passes() {
+        let mut parser = ProgressParser::new(Some(Duration::from_secs(20)));
+        let first = BLOCK.lines().find_map(|l| parser.push_line(l)).unwrap();
+        let total = Some(Duration::from_secs(20));
+
+        let pass1 = first.clone().for_pass(1, 2, total);
+        assert_eq!(pass1.pass, Some(1));
+        assert_eq!(pass1.percent, Some(12.5));
+        assert_eq!(pass1.eta, Some(Duration::from_secs(14)));
+
+        let pass2 = first.for_pass(2, 2, total);
+        assert_eq!(pass2.percent, Some(62.5));
+        assert_eq!(pass2.eta, Some(Duration::from_secs(6)));
+
+        let end = parser
+            .push_line("progress=end")
+            .unwrap()
+            .for_pass(1, 2, total);
+        assert!(!end.finished);
+    }
+}
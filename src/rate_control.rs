@@ -0,0 +1,362 @@
+//! Rate-control modes rendered for the selected encoder.
+//!
+//! The same intent maps to different flags per encoder: CRF is `-crf` for
+//! x264/x265 but needs `-b:v 0` on libvpx and libaom, NVENC uses `-cq`, and
+//! audio encoders each have their own VBR scale.
+
+use std::ffi::OsString;
+
+use crate::error::{Error, Result};
+
+/// How an encoder distributes bits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RateControl {
+    /// Constant quality (constant rate factor); lower is better.
+    Crf(f32),
+    /// Constant quality capped by a maximum rate (constrained VBR for streaming).
+    CappedCrf {
+        /// Quality target.
+        crf: f32,
+        /// Peak bitrate in kbps.
+        max_kbps: u32,
+        /// VBV/HRD buffer size in kbps.
+        buffer_kbps: u32,
+    },
+    /// Average bitrate in kbps; Opus uses constrained VBR (`-vbr constrained`) to hold it.
+    Abr(u32),
+    /// Constant bitrate with HRD signalling where the encoder supports it.
+    Cbr {
+        /// Bitrate in kbps.
+        kbps: u32,
+        /// VBV/HRD buffer size in kbps.
+        buffer_kbps: u32,
+    },
+    /// Constant quantizer, mostly useful for testing and intermediates.
+    ConstantQp(u32),
+    /// Encoder-native audio VBR quality (`-q:a` for aac/mp3/vorbis, `-vbr` for libfdk_aac).
+    ///
+    /// libopus has no quality scale: it runs unconstrained VBR (`-vbr on`) around a bitrate
+    /// taken from the libfdk_aac scale, 32 kbps per step from 1 (32k) to 5 (160k).
+    Vbr(f32),
+}
+
+/// Encoder families with distinct rate-control flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Family {
+    X264,
+    X265,
+    Vpx,
+    Aom,
+    SvtAv1,
+    Nvenc,
+    Aac,
+    FdkAac,
+    Opus,
+    Mp3,
+    Vorbis,
+    Other,
+}
+
+impl Family {
+    fn of(encoder: &str) -> Self {
+        match encoder {
+            "libx264" | "libx264rgb" => Family::X264,
+            "libx265" => Family::X265,
+            "libvpx" | "libvpx-vp9" => Family::Vpx,
+            "libaom-av1" => Family::Aom,
+            "libsvtav1" => Family::SvtAv1,
+            "aac" => Family::Aac,
+            "libfdk_aac" => Family::FdkAac,
+            "libopus" | "opus" => Family::Opus,
+            "libmp3lame" => Family::Mp3,
+            "libvorbis" | "vorbis" => Family::Vorbis,
+            _ if encoder.ends_with("_nvenc") => Family::Nvenc,
+            _ => Family::Other,
+        }
+    }
+}
+
+impl RateControl {
+    /// Render the flags for `encoder`, applied to streams matching `spec` (e.g. `v` or `a:1`).
+    ///
+    /// Fails with [`Error::Unsupported`] for combinations the encoder has no
+    /// equivalent for, such as `Crf` on an audio encoder.
+    pub fn to_args(&self, encoder: &str, spec: &str) -> Result<Vec<OsString>> {
+        let mut args = self.flag_args(encoder, spec)?;
+        let params = self.encoder_params(encoder);
+        if let Some(flag) = params_flag(encoder).filter(|_| !params.is_empty()) {
+            let joined = params
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect::<Vec<_>>()
+                .join(":");
+            args.push(format!("-{flag}:{spec}").into());
+            args.push(joined.into());
+        }
+        Ok(args)
+    }
+
+    /// Entries this mode adds to `-x264-params`/`-x265-params` (HRD signalling for CBR).
+    ///
+    /// Kept apart from [`flag_args`](Self::flag_args) so they can be merged
+    /// with other encoder parameters into a single option.
+    pub(crate) fn encoder_params(&self, encoder: &str) -> Vec<(String, String)> {
+        let entries: &[(&str, &str)] = match (self, Family::of(encoder)) {
+            (RateControl::Cbr { .. }, Family::X264) => &[("nal-hrd", "cbr")],
+            (RateControl::Cbr { .. }, Family::X265) => &[("hrd", "1"), ("strict-cbr", "1")],
+            _ => &[],
+        };
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// The flags of [`to_args`](Self::to_args) without the encoder parameters.
+    pub(crate) fn flag_args(&self, encoder: &str, spec: &str) -> Result<Vec<OsString>> {
+        let family = Family::of(encoder);
+        // Unknown encoders fall into `Family::Other`, so the spec decides too.
+        let audio = spec.starts_with('a')
+            || matches!(
+                family,
+                Family::Aac | Family::FdkAac | Family::Opus | Family::Mp3 | Family::Vorbis
+            );
+        let mut args = StreamArgs::new(spec);
+        match (*self, family) {
+            (RateControl::Crf(_) | RateControl::CappedCrf { .. }, _) if audio => {
+                return Err(unsupported(self, encoder))
+            }
+            (RateControl::Crf(crf), Family::Vpx | Family::Aom) => {
+                args.push("crf", crf).push("b", 0);
+            }
+            (RateControl::Crf(crf), Family::Nvenc) => {
+                args.push("rc", "vbr").push("cq", crf).push("b", 0);
+            }
+            (RateControl::Crf(crf), _) => {
+                args.push("crf", crf);
+            }
+            (
+                RateControl::CappedCrf {
+                    crf,
+                    max_kbps,
+                    buffer_kbps,
+                },
+                family,
+            ) => {
+                match family {
+                    Family::Nvenc => args.push("rc", "vbr").push("cq", crf),
+                    _ => args.push("crf", crf),
+                };
+                // libvpx/libaom treat `-b:v` as the ceiling in constrained-quality mode.
+                if matches!(family, Family::Vpx | Family::Aom) {
+                    args.push("b", kbps(max_kbps));
+                }
+                args.push("maxrate", kbps(max_kbps))
+                    .push("bufsize", kbps(buffer_kbps));
+            }
+            (RateControl::Abr(rate), Family::Opus) => {
+                args.push("b", kbps(rate)).push("vbr", "constrained");
+            }
+            (RateControl::Abr(rate), _) => {
+                args.push("b", kbps(rate));
+            }
+            (RateControl::Cbr { kbps: rate, .. }, Family::Opus) => {
+                args.push("b", kbps(rate)).push("vbr", "off");
+            }
+            (
+                RateControl::Cbr { kbps: rate, .. },
+                Family::Aac | Family::FdkAac | Family::Mp3 | Family::Vorbis,
+            ) => {
+                args.push("b", kbps(rate));
+            }
+            (
+                RateControl::Cbr {
+                    kbps: rate,
+                    buffer_kbps,
+                },
+                family,
+            ) => {
+                if family == Family::Nvenc {
+                    args.push("rc", "cbr");
+                }
+                args.push("b", kbps(rate))
+                    .push("minrate", kbps(rate))
+                    .push("maxrate", kbps(rate))
+                    .push("bufsize", kbps(buffer_kbps));
+            }
+            (RateControl::ConstantQp(qp), Family::X264 | Family::X265 | Family::SvtAv1) => {
+                args.push("qp", qp);
+            }
+            (RateControl::ConstantQp(qp), Family::Nvenc) => {
+                args.push("rc", "constqp").push("qp", qp);
+            }
+            (RateControl::ConstantQp(_), _) => return Err(unsupported(self, encoder)),
+            (RateControl::Vbr(quality), Family::FdkAac) => {
+                args.push("vbr", quality.round().clamp(1.0, 5.0));
+            }
+            (RateControl::Vbr(quality), Family::Aac | Family::Mp3 | Family::Vorbis) => {
+                args.push("q", quality);
+            }
+            (RateControl::Vbr(quality), Family::Opus) => {
+                let step = quality.round().clamp(1.0, 5.0) as u32;
+                args.push("b", kbps(step * 32)).push("vbr", "on");
+            }
+            (RateControl::Vbr(_), _) => return Err(unsupported(self, encoder)),
+        }
+        Ok(args.into_inner())
+    }
+}
+
+/// Option carrying the native parameters of `encoder`, if it has one.
+fn params_flag(encoder: &str) -> Option<&'static str> {
+    match Family::of(encoder) {
+        Family::X264 => Some("x264-params"),
+        Family::X265 => Some("x265-params"),
+        _ => None,
+    }
+}
+
+fn kbps(rate: u32) -> String {
+    format!("{rate}k")
+}
+
+fn unsupported(rate: &RateControl, encoder: &str) -> Error {
+    Error::Unsupported(format!(
+        "{rate:?} rate control is not available for {encoder}"
+    ))
+}
+
+/// Accumulates `-flag:spec value` pairs.
+pub(crate) struct StreamArgs {
+    spec: String,
+    args: Vec<OsString>,
+}
+
+impl StreamArgs {
+    pub(crate) fn new(spec: &str) -> Self {
+        Self {
+            spec: spec.to_string(),
+            args: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, flag: &str, value: impl ToString) -> &mut Self {
+        self.args.push(format!("-{flag}:{}", self.spec).into());
+        self.args.push(value.to_string().into());
+        self
+    }
+
+    pub(crate) fn into_inner(self) -> Vec<OsString> {
+        self.args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(rate: RateControl, encoder: &str, spec: &str) -> Vec<String> {
+        rate.to_args(encoder, spec)
+            .unwrap()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn crf_per_encoder_family() {
+        assert_eq!(
+            render(RateControl::Crf(20.0), "libx264", "v"),
+            ["-crf:v", "20"]
+        );
+        assert_eq!(
+            render(RateControl::Crf(31.0), "libvpx-vp9", "v"),
+            ["-crf:v", "31", "-b:v", "0"]
+        );
+        assert_eq!(
+            render(RateControl::Crf(35.0), "libsvtav1", "v"),
+            ["-crf:v", "35"]
+        );
+        assert_eq!(
+            render(RateControl::Crf(23.0), "h264_nvenc", "v"),
+            ["-rc:v", "vbr", "-cq:v", "23", "-b:v", "0"]
+        );
+        assert!(RateControl::Crf(20.0).to_args("aac", "a").is_err());
+        assert!(RateControl::Crf(20.0).to_args("pcm_s16le", "a:1").is_err());
+        let capped = RateControl::CappedCrf {
+            crf: 23.0,
+            max_kbps: 128,
+            buffer_kbps: 256,
+        };
+        assert!(capped.to_args("flac", "a").is_err());
+    }
+
+    #[test]
+    fn capped_crf_and_cbr() {
+        let capped = RateControl::CappedCrf {
+            crf: 23.0,
+            max_kbps: 4500,
+            buffer_kbps: 9000,
+        };
+        assert_eq!(
+            render(capped, "libx265", "v"),
+            ["-crf:v", "23", "-maxrate:v", "4500k", "-bufsize:v", "9000k"]
+        );
+        assert_eq!(
+            render(capped, "libaom-av1", "v"),
+            [
+                "-crf:v",
+                "23",
+                "-b:v",
+                "4500k",
+                "-maxrate:v",
+                "4500k",
+                "-bufsize:v",
+                "9000k"
+            ]
+        );
+        let cbr = RateControl::Cbr {
+            kbps: 3000,
+            buffer_kbps: 3000,
+        };
+        assert_eq!(
+            render(cbr, "libx264", "v"),
+            [
+                "-b:v",
+                "3000k",
+                "-minrate:v",
+                "3000k",
+                "-maxrate:v",
+                "3000k",
+                "-bufsize:v",
+                "3000k",
+                "-x264-params:v",
+                "nal-hrd=cbr"
+            ]
+        );
+    }
+
+    #[test]
+    fn audio_vbr_modes() {
+        assert_eq!(
+            render(RateControl::Vbr(2.0), "libmp3lame", "a"),
+            ["-q:a", "2"]
+        );
+        assert_eq!(
+            render(RateControl::Vbr(4.4), "libfdk_aac", "a:1"),
+            ["-vbr:a:1", "4"]
+        );
+        assert_eq!(
+            render(RateControl::Abr(96), "libopus", "a"),
+            ["-b:a", "96k", "-vbr:a", "constrained"]
+        );
+        assert_eq!(
+            render(RateControl::Vbr(2.6), "libopus", "a"),
+            ["-b:a", "96k", "-vbr:a", "on"]
+        );
+        assert!(RateControl::Vbr(5.0).to_args("libx264", "v").is_err());
+        assert!(RateControl::ConstantQp(20)
+            .to_args("libvpx-vp9", "v")
+            .is_err());
+    }
+}
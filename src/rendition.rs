@@ -0,0 +1,247 @@
+//! Renditions of an adaptive-bitrate stream, shared by the HLS and DASH packagers.
+
+use std::ffi::OsString;
+
+use crate::error::{Error, Result};
+use crate::filter::VideoFilter;
+use crate::rate_control::StreamArgs;
+
+/// Audio bitrate of a rendition unless configured otherwise.
+const DEFAULT_AUDIO_KBPS: u32 = 128;
+
+/// One quality level of an adaptive-bitrate ladder.
+///
+/// ```rust
+/// use ffmpeg_light::rendition::Rendition;
+///
+/// let rendition = Rendition::new(1280, 720, 3000).max_bitrate(3300).audio_bitrate(96);
+/// assert_eq!(rendition.name_ref(), "720p");
+/// assert_eq!(rendition.bandwidth(), 3_396_000);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rendition {
+    name: String,
+    width: u32,
+    height: u32,
+    video_bitrate: u32,
+    max_bitrate: Option<u32>,
+    video_codec: String,
+    frame_rate: Option<f64>,
+    audio_codec: String,
+    audio_bitrate: Option<u32>,
+}
+
+impl Rendition {
+    /// A `width`x`height` H.264 rendition at `video_kbps`, with 128 kbps AAC audio.
+    pub fn new(width: u32, height: u32, video_kbps: u32) -> Self {
+        Self {
+            name: format!("{height}p"),
+            width,
+            height,
+            video_bitrate: video_kbps,
+            max_bitrate: None,
+            video_codec: "libx264".into(),
+            frame_rate: None,
+            audio_codec: "aac".into(),
+            audio_bitrate: Some(DEFAULT_AUDIO_KBPS),
+        }
+    }
+
+    /// Name used in output paths and playlists (defaults to the height, e.g. `720p`).
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Peak video bitrate in kbps (maps to `-maxrate`, with a buffer of twice that).
+    pub fn max_bitrate(mut self, kbps: u32) -> Self {
+        self.max_bitrate = Some(kbps);
+        self
+    }
+
+    /// Video encoder (default `libx264`).
+    pub fn video_codec(mut self, codec: impl Into<String>) -> Self {
+        self.video_codec = codec.into();
+        self
+    }
+
+    /// Output frame rate; the source rate is kept when unset.
+    pub fn frame_rate(mut self, fps: f64) -> Self {
+        self.frame_rate = Some(fps);
+        self
+    }
+
+    /// Audio encoder (default `aac`).
+    pub fn audio_codec(mut self, codec: impl Into<String>) -> Self {
+        self.audio_codec = codec.into();
+        self
+    }
+
+    /// Audio bitrate in kbps (default 128).
+    pub fn audio_bitrate(mut self, kbps: u32) -> Self {
+        self.audio_bitrate = Some(kbps);
+        self
+    }
+
+    /// Leave audio out of this rendition.
+    pub fn no_audio(mut self) -> Self {
+        self.audio_bitrate = None;
+        self
+    }
+
+    /// Name used in output paths and playlists.
+    pub fn name_ref(&self) -> &str {
+        &self.name
+    }
+
+    /// Output width in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Output height in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Average video bitrate in kbps.
+    pub fn video_bitrate_value(&self) -> u32 {
+        self.video_bitrate
+    }
+
+    /// Peak video bitrate in kbps, if capped.
+    pub fn max_bitrate_value(&self) -> Option<u32> {
+        self.max_bitrate
+    }
+
+    /// Configured video encoder.
+    pub fn video_codec_ref(&self) -> &str {
+        &self.video_codec
+    }
+
+    /// Configured output frame rate.
+    pub fn frame_rate_value(&self) -> Option<f64> {
+        self.frame_rate
+    }
+
+    /// Configured audio encoder.
+    pub fn audio_codec_ref(&self) -> &str {
+        &self.audio_codec
+    }
+
+    /// Audio bitrate in kbps, `None` without audio.
+    pub fn audio_bitrate_value(&self) -> Option<u32> {
+        self.audio_bitrate
+    }
+
+    /// Whether the rendition carries audio.
+    pub fn has_audio(&self) -> bool {
+        self.audio_bitrate.is_some()
+    }
+
+    /// Peak bandwidth in bits per second, as advertised in manifests.
+    pub fn bandwidth(&self) -> u64 {
+        let video = self.max_bitrate.unwrap_or(self.video_bitrate);
+        (u64::from(video) + u64::from(self.audio_bitrate.unwrap_or(0))) * 1000
+    }
+
+    /// Check dimensions, bitrates and the name.
+    pub(crate) fn validate(&self) -> Result<()> {
+        let name = &self.name;
+        let invalid =
+            |reason: String| Err(Error::InvalidInput(format!("rendition {name}: {reason}")));
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return invalid("names may only contain ASCII letters, digits, '-' and '_'".into());
+        }
+        if self.width == 0 || self.height == 0 || self.width % 2 != 0 || self.height % 2 != 0 {
+            return invalid(format!(
+                "{}x{} is not a valid size; both dimensions must be even and non-zero",
+                self.width, self.height
+            ));
+        }
+        if self.video_bitrate == 0 || self.audio_bitrate == Some(0) {
+            return invalid("bitrates must be positive".into());
+        }
+        if matches!(self.max_bitrate, Some(max) if max < self.video_bitrate) {
+            return invalid("max_bitrate is below the average bitrate".into());
+        }
+        Ok(())
+    }
+
+    /// Encoder flags for the `index`-th video output stream.
+    pub(crate) fn video_args(&self, index: usize) -> Vec<OsString> {
+        let mut args = StreamArgs::new(&format!("v:{index}"));
+        args.push("c", &self.video_codec)
+            .push("b", format!("{}k", self.video_bitrate));
+        if let Some(max) = self.max_bitrate {
+            args.push("maxrate", format!("{max}k"))
+                .push("bufsize", format!("{}k", max * 2));
+        }
+        if let Some(fps) = self.frame_rate {
+            args.push("r", fps);
+        }
+        let scale = VideoFilter::Scale {
+            width: self.width,
+            height: self.height,
+        };
+        args.push("filter", scale.to_filter_string());
+        args.into_inner()
+    }
+
+    /// Encoder flags for the `index`-th audio output stream.
+    pub(crate) fn audio_args(&self, index: usize) -> Vec<OsString> {
+        let mut args = StreamArgs::new(&format!("a:{index}"));
+        args.push("c", &self.audio_codec);
+        if let Some(kbps) = self.audio_bitrate {
+            args.push("b", format!("{kbps}k"));
+        }
+        args.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_indexed_stream_flags() {
+        let rendition = Rendition::new(640, 360, 800)
+            .max_bitrate(880)
+            .frame_rate(25.0);
+        let render = |args: Vec<OsString>| -> Vec<String> {
+            args.iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(
+            render(rendition.video_args(2)),
+            [
+                "-c:v:2",
+                "libx264",
+                "-b:v:2",
+                "800k",
+                "-maxrate:v:2",
+                "880k",
+                "-bufsize:v:2",
+                "1760k",
+                "-r:v:2",
+                "25",
+                "-filter:v:2",
+                "scale=640:360"
+            ]
+        );
+        assert_eq!(
+            render(rendition.audio_args(1)),
+            ["-c:a:1", "aac", "-b:a:1", "128k"]
+        );
+        assert!(Rendition::new(641, 360, 800).validate().is_err());
+        assert!(Rendition::new(640, 360, 800)
+            .name("360p mobile")
+            .validate()
+            .is_err());
+    }
+}
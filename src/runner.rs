@@ -0,0 +1,306 @@
+//! Pluggable execution of ffmpeg/ffprobe invocations.
+//!
+//! Every [`FfmpegCommand`](crate::command::FfmpegCommand) and
+//! [`FfprobeCommand`](crate::command::FfprobeCommand) hands its arguments to a
+//! [`CommandRunner`](crate::runner::CommandRunner). The default
+//! [`ProcessRunner`](crate::runner::ProcessRunner) spawns real processes; tests
+//! can swap in a recording mock (see the `testing` feature).
+
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "tokio")]
+use std::future::Future;
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+#[cfg(feature = "tokio")]
+use std::process::Stdio;
+#[cfg(feature = "tokio")]
+use std::sync::Mutex;
+
+use crate::command::RunningJob;
+#[cfg(feature = "tokio")]
+use crate::error::Error;
+use crate::error::Result;
+#[cfg(feature = "tokio")]
+use crate::logging;
+use crate::logging::InvocationSpan;
+
+/// How a child process' stdout is handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StdoutMode {
+    /// Forward stdout to the parent's stdout.
+    Inherit,
+    /// Capture stdout into [`Output::stdout`].
+    Capture,
+    /// Deliver stdout line by line to the runner's callback.
+    Lines,
+}
+
+/// A fully-resolved process invocation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Invocation {
+    /// Binary to execute.
+    pub binary: PathBuf,
+    /// Arguments passed to the binary, in order.
+    pub args: Vec<OsString>,
+    /// Deadline after which the process is cancelled.
+    pub timeout: Option<Duration>,
+    /// Treatment of the process' stdout.
+    pub stdout: StdoutMode,
+}
+
+impl Invocation {
+    /// Arguments converted to (lossy) UTF-8, handy for assertions and logging.
+    pub fn args_lossy(&self) -> Vec<String> {
+        self.args
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Render the invocation as a single POSIX shell command line.
+    ///
+    /// Every argument is quoted where needed so the result can be pasted into
+    /// `sh`/`bash` verbatim. Non-UTF-8 bytes are replaced lossily.
+    pub fn to_shell_string(&self) -> String {
+        std::iter::once(self.binary.as_os_str())
+            .chain(self.args.iter().map(OsString::as_os_str))
+            .map(shell_quote)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Quote a single argument for a POSIX shell.
+///
+/// Arguments made only of characters with no special meaning are returned
+/// unchanged; everything else is wrapped in single quotes, with embedded
+/// single quotes written as `'\''`.
+pub fn shell_quote(arg: &OsStr) -> String {
+    let arg = arg.to_string_lossy();
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-+=:,./@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return arg.into_owned();
+    }
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Boxed future returned by [`CommandRunner::run_async`].
+#[cfg(feature = "tokio")]
+pub type RunFuture<'a> = Pin<Box<dyn Future<Output = Result<Output>> + Send + 'a>>;
+
+/// Executes [`Invocation`]s on behalf of the crate's commands.
+///
+/// Implementations return the raw [`Output`]; callers are responsible for
+/// turning a non-zero exit status into an error.
+pub trait CommandRunner: fmt::Debug + Send + Sync {
+    /// Run the invocation to completion.
+    ///
+    /// In [`StdoutMode::Lines`] every stdout line is passed to `on_line` as it
+    /// arrives instead of being captured.
+    fn run(&self, invocation: &Invocation, on_line: &mut dyn FnMut(&str)) -> Result<Output>;
+
+    /// Async variant of [`run`](Self::run) (requires the `tokio` feature).
+    ///
+    /// Dropping the returned future must stop the process. The default
+    /// implementation calls the blocking [`run`](Self::run), which is fine for
+    /// in-memory runners.
+    #[cfg(feature = "tokio")]
+    fn run_async<'a>(
+        &'a self,
+        invocation: &'a Invocation,
+        on_line: &'a mut (dyn FnMut(&str) + Send),
+    ) -> RunFuture<'a> {
+        Box::pin(async move { self.run(invocation, on_line) })
+    }
+
+    /// Whether invocations run as real processes of their binary.
+    ///
+    /// [`FfmpegCommand::spawn`](crate::command::FfmpegCommand::spawn) starts
+    /// the process itself, so it refuses runners that return `false`.
+    fn spawns_processes(&self) -> bool {
+        false
+    }
+}
+
+/// Runner that spawns real operating-system processes.
+///
+/// stderr is read line by line while the process runs; on a timeout the lines
+/// read so far come back attached to the error as [`Error::WithLog`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProcessRunner;
+
+impl CommandRunner for ProcessRunner {
+    fn run(&self, invocation: &Invocation, on_line: &mut dyn FnMut(&str)) -> Result<Output> {
+        let span = InvocationSpan::new(invocation);
+        let result = span.in_scope(|| {
+            let mut cmd = Command::new(&invocation.binary);
+            cmd.args(&invocation.args);
+            let job = RunningJob::spawn(cmd, &invocation.binary, invocation.stdout)?;
+            let deadline = invocation.timeout.map(|timeout| Instant::now() + timeout);
+            job.finish(deadline, on_line)
+        });
+        span.finish(&result);
+        result
+    }
+
+    fn spawns_processes(&self) -> bool {
+        true
+    }
+
+    #[cfg(feature = "tokio")]
+    fn run_async<'a>(
+        &'a self,
+        invocation: &'a Invocation,
+        on_line: &'a mut (dyn FnMut(&str) + Send),
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            let mut cmd = tokio::process::Command::new(&invocation.binary);
+            cmd.args(&invocation.args)
+                .stdin(Stdio::null())
+                .stdout(match invocation.stdout {
+                    StdoutMode::Inherit => Stdio::inherit(),
+                    StdoutMode::Capture | StdoutMode::Lines => Stdio::piped(),
+                })
+                .stderr(Stdio::piped())
+                .kill_on_drop(true);
+            let span = InvocationSpan::new(invocation);
+            let log = Arc::new(Mutex::new(Vec::new()));
+            let work = output_async(cmd, &span, invocation.stdout, on_line, &log);
+            let result =
+                crate::command::with_timeout_async(&invocation.binary, invocation.timeout, work)
+                    .await
+                    .map_err(|err| match err {
+                        Error::TimeoutError(_) => {
+                            logging::with_partial_log(err, &logging::take_log(&log))
+                        }
+                        other => other,
+                    });
+            span.finish(&result);
+            result
+        })
+    }
+}
+
+/// Spawn `cmd` and wait for it, streaming stderr line by line into `log`.
+///
+/// In [`StdoutMode::Lines`] stdout lines go to `on_line` instead of being captured.
+#[cfg(feature = "tokio")]
+async fn output_async(
+    mut cmd: tokio::process::Command,
+    span: &InvocationSpan,
+    stdout: StdoutMode,
+    on_line: &mut (dyn FnMut(&str) + Send),
+    log: &Arc<Mutex<Vec<u8>>>,
+) -> std::io::Result<Output> {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+
+    let mut child = cmd.spawn()?;
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let stderr_reader = span.in_scope(|| logging::spawn_stderr_reader(stderr, Arc::clone(log)));
+    let mut captured = Vec::new();
+    if let Some(pipe) = child.stdout.take() {
+        match stdout {
+            StdoutMode::Lines => {
+                let mut lines = BufReader::new(pipe).lines();
+                while let Some(line) = lines.next_line().await? {
+                    on_line(&line);
+                }
+            }
+            StdoutMode::Capture | StdoutMode::Inherit => {
+                BufReader::new(pipe).read_to_end(&mut captured).await?;
+            }
+        }
+    }
+    let status = child.wait().await?;
+    stderr_reader.await??;
+    Ok(Output {
+        status,
+        stdout: captured,
+        stderr: logging::take_log(log),
+    })
+}
+
+/// Shared handle to the runner used when none is configured explicitly.
+pub fn default_runner() -> Arc<dyn CommandRunner> {
+    Arc::new(ProcessRunner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_quote_leaves_plain_arguments_alone() {
+        assert_eq!(shell_quote(OsStr::new("-c:v")), "-c:v");
+        assert_eq!(
+            shell_quote(OsStr::new("scale=1280:720,fps=30")),
+            "scale=1280:720,fps=30"
+        );
+        assert_eq!(shell_quote(OsStr::new("/tmp/out.mp4")), "/tmp/out.mp4");
+    }
+
+    #[test]
+    fn shell_quote_escapes_special_characters() {
+        assert_eq!(shell_quote(OsStr::new("")), "''");
+        assert_eq!(shell_quote(OsStr::new("my video.mp4")), "'my video.mp4'");
+        assert_eq!(
+            shell_quote(OsStr::new("[0:v][1:v]overlay")),
+            "'[0:v][1:v]overlay'"
+        );
+        assert_eq!(shell_quote(OsStr::new("it's $HOME")), r"'it'\''s $HOME'");
+    }
+
+    #[test]
+    fn invocation_renders_shell_string() {
+        let invocation = Invocation {
+            binary: PathBuf::from("/usr/bin/ffmpeg"),
+            args: ["-i", "in put.mov", "-vf", "drawtext=text='hi'", "out.mp4"]
+                .iter()
+                .map(OsString::from)
+                .collect(),
+            timeout: None,
+            stdout: StdoutMode::Inherit,
+        };
+        assert_eq!(
+            invocation.to_shell_string(),
+            r"/usr/bin/ffmpeg -i 'in put.mov' -vf 'drawtext=text='\''hi'\''' out.mp4"
+        );
+    }
+
+    #[cfg(all(unix, feature = "tokio"))]
+    #[test]
+    fn async_lines_are_streamed_until_timeout() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let invocation = Invocation {
+            binary: PathBuf::from("sh"),
+            args: [
+                "-c",
+                "echo progress=continue; echo '[error] stuck' >&2; sleep 5",
+            ]
+            .iter()
+            .map(OsString::from)
+            .collect(),
+            timeout: Some(Duration::from_millis(200)),
+            stdout: StdoutMode::Lines,
+        };
+        let mut lines = Vec::new();
+        let mut on_line = |line: &str| lines.push(line.to_string());
+        let started = Instant::now();
+        let result = runtime.block_on(ProcessRunner.run_async(&invocation, &mut on_line));
+        let err = result.unwrap_err();
+        assert!(matches!(err.without_log(), Error::TimeoutError(_)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(lines, ["progress=continue"]);
+        // stderr is read as it arrives, so the lines before the timeout are kept.
+        assert_eq!(err.log(), Some(&["[error] stuck".to_string()][..]));
+    }
+}
@@ -0,0 +1,188 @@
+//! Test doubles for code that drives ffmpeg through this crate.
+//!
+//! [`MockRunner`](crate::testing::MockRunner) records every
+//! [`Invocation`](crate::runner::Invocation) and answers with canned
+//! [`MockResponse`](crate::testing::MockResponse)s, so builders can be
+//! exercised without ffmpeg installed:
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use ffmpeg_light::command::FfmpegBinaryPaths;
+//! use ffmpeg_light::config::FfmpegLocator;
+//! use ffmpeg_light::testing::{MockResponse, MockRunner};
+//! use ffmpeg_light::TranscodeBuilder;
+//!
+//! let mock = MockRunner::new();
+//! mock.push_response(MockResponse::success());
+//! let locator = FfmpegLocator::from_paths(FfmpegBinaryPaths::with_paths("ffmpeg", "ffprobe"))
+//!     .with_runner(Arc::new(mock.clone()));
+//!
+//! TranscodeBuilder::new()
+//!     .with_locator(&locator)
+//!     .input("in.mov")
+//!     .output("out.mp4")
+//!     .run()?;
+//!
+//! assert_eq!(mock.invocations().len(), 1);
+//! # Ok::<(), ffmpeg_light::Error>(())
+//! ```
+
+use std::collections::VecDeque;
+use std::process::{ExitStatus, Output};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::error::{Error, Result};
+use crate::runner::{CommandRunner, Invocation, StdoutMode};
+
+/// Canned result returned by [`MockRunner`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MockResponse {
+    exit_code: i32,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    timed_out: bool,
+}
+
+impl MockResponse {
+    /// Successful exit with no output.
+    pub fn success() -> Self {
+        Self::default()
+    }
+
+    /// Failed exit with the given code and stderr.
+    pub fn failure(exit_code: i32, stderr: impl Into<Vec<u8>>) -> Self {
+        Self {
+            exit_code,
+            stderr: stderr.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Simulate the invocation exceeding its deadline.
+    pub fn timeout() -> Self {
+        Self {
+            timed_out: true,
+            ..Self::default()
+        }
+    }
+
+    /// Set the captured stdout (delivered line by line in [`StdoutMode::Lines`]).
+    pub fn stdout(mut self, stdout: impl Into<Vec<u8>>) -> Self {
+        self.stdout = stdout.into();
+        self
+    }
+
+    /// Set the captured stderr.
+    pub fn stderr(mut self, stderr: impl Into<Vec<u8>>) -> Self {
+        self.stderr = stderr.into();
+        self
+    }
+
+    /// Set the process exit code.
+    pub fn exit_code(mut self, exit_code: i32) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    responses: VecDeque<MockResponse>,
+    fallback: MockResponse,
+    invocations: Vec<Invocation>,
+}
+
+/// [`CommandRunner`] that records invocations instead of spawning processes.
+///
+/// Responses are consumed in FIFO order; once the queue is empty the fallback
+/// (a successful, silent exit unless changed) is returned. Clones share state,
+/// so keep one clone for assertions and hand another to the code under test.
+#[derive(Clone, Debug, Default)]
+pub struct MockRunner {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockRunner {
+    /// Create a mock that succeeds for every invocation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a response for the next unanswered invocation.
+    pub fn push_response(&self, response: MockResponse) -> &Self {
+        self.state().responses.push_back(response);
+        self
+    }
+
+    /// Response used once the queue is exhausted.
+    pub fn set_fallback(&self, response: MockResponse) -> &Self {
+        self.state().fallback = response;
+        self
+    }
+
+    /// All invocations recorded so far, oldest first.
+    pub fn invocations(&self) -> Vec<Invocation> {
+        self.state().invocations.clone()
+    }
+
+    /// Most recent invocation, if any.
+    pub fn last_invocation(&self) -> Option<Invocation> {
+        self.state().invocations.last().cloned()
+    }
+
+    /// Forget recorded invocations (queued responses are kept).
+    pub fn clear(&self) {
+        self.state().invocations.clear();
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        // A panicking test must not poison assertions made by other tests.
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl CommandRunner for MockRunner {
+    fn run(&self, invocation: &Invocation, on_line: &mut dyn FnMut(&str)) -> Result<Output> {
+        let response = {
+            let mut state = self.state();
+            state.invocations.push(invocation.clone());
+            let fallback = state.fallback.clone();
+            state.responses.pop_front().unwrap_or(fallback)
+        };
+
+        if response.timed_out {
+            return Err(Error::TimeoutError(format!(
+                "{} did not finish before the deadline",
+                invocation.binary.display()
+            )));
+        }
+
+        let stdout = match invocation.stdout {
+            StdoutMode::Capture => response.stdout,
+            StdoutMode::Lines => {
+                for line in String::from_utf8_lossy(&response.stdout).lines() {
+                    on_line(line);
+                }
+                Vec::new()
+            }
+            StdoutMode::Inherit => Vec::new(),
+        };
+        Ok(Output {
+            status: exit_status(response.exit_code),
+            stdout,
+            stderr: response.stderr,
+        })
+    }
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw((code & 0xff) << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
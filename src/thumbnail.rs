@@ -1,10 +1,13 @@
 //! Thumbnail generation helpers.
 
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::command::{FfmpegBinaryPaths, FfmpegCommand};
 use crate::config::FfmpegLocator;
 use crate::error::Result;
+use crate::runner::{default_runner, CommandRunner};
 use crate::types::Time;
 
 /// Supported output formats.
@@ -39,6 +42,7 @@ pub struct ThumbnailOptions {
     width: Option<u32>,
     height: Option<u32>,
     format: ThumbnailFormat,
+    timeout: Option<Duration>,
 }
 
 impl ThumbnailOptions {
@@ -49,6 +53,7 @@ impl ThumbnailOptions {
             width: None,
             height: None,
             format: ThumbnailFormat::Png,
+            timeout: None,
         }
     }
 
@@ -82,6 +87,17 @@ impl ThumbnailOptions {
     pub fn output_format(&self) -> &ThumbnailFormat {
         &self.format
     }
+
+    /// Fail with [`Error::TimeoutError`](crate::Error::TimeoutError) if ffmpeg runs longer than `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Configured timeout, if any.
+    pub fn timeout_value(&self) -> Option<Duration> {
+        self.timeout
+    }
 }
 
 /// Generate a thumbnail file.
@@ -91,7 +107,23 @@ pub fn generate(
     options: &ThumbnailOptions,
 ) -> Result<()> {
     let locator = FfmpegLocator::system()?;
-    generate_with_binaries(locator.binaries(), input, output, options)
+    generate_with_locator(&locator, input, output, options)
+}
+
+/// Same as [`generate`] but uses the locator's binaries and runner.
+pub fn generate_with_locator(
+    locator: &FfmpegLocator,
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &ThumbnailOptions,
+) -> Result<()> {
+    generate_with_runner(
+        locator.binaries(),
+        locator.runner(),
+        input.as_ref(),
+        output.as_ref(),
+        options,
+    )
 }
 
 /// Same as [`generate`] but reuses already-discovered binaries.
@@ -101,11 +133,102 @@ pub fn generate_with_binaries(
     output: impl AsRef<Path>,
     options: &ThumbnailOptions,
 ) -> Result<()> {
-    if let Some(parent) = output.as_ref().parent() {
-        std::fs::create_dir_all(parent)?;
-    }
+    generate_with_runner(
+        binaries,
+        &default_runner(),
+        input.as_ref(),
+        output.as_ref(),
+        options,
+    )
+}
+
+/// Async variant of [`generate`] (requires `tokio`).
+///
+/// Dropping the future kills the ffmpeg process.
+#[cfg(feature = "tokio")]
+pub async fn generate_async(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &ThumbnailOptions,
+) -> Result<()> {
+    let locator = FfmpegLocator::system()?;
+    generate_with_locator_async(&locator, input, output, options).await
+}
+
+/// Async variant of [`generate_with_locator`] (requires `tokio`).
+#[cfg(feature = "tokio")]
+pub async fn generate_with_locator_async(
+    locator: &FfmpegLocator,
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &ThumbnailOptions,
+) -> Result<()> {
+    let cmd = prepare(
+        locator.binaries(),
+        locator.runner(),
+        input.as_ref(),
+        output.as_ref(),
+        options,
+    )?;
+    cmd.run_async().await
+}
 
+/// Async variant of [`generate_with_binaries`] (requires `tokio`).
+#[cfg(feature = "tokio")]
+pub async fn generate_with_binaries_async(
+    binaries: &FfmpegBinaryPaths,
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &ThumbnailOptions,
+) -> Result<()> {
+    let cmd = prepare(
+        binaries,
+        &default_runner(),
+        input.as_ref(),
+        output.as_ref(),
+        options,
+    )?;
+    cmd.run_async().await
+}
+
+/// Render the ffmpeg command [`generate`] would run, without running it.
+///
+/// The returned string is POSIX shell-quoted and safe to paste into a terminal.
+pub fn dry_run(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &ThumbnailOptions,
+) -> Result<String> {
+    let locator = FfmpegLocator::system()?;
+    Ok(dry_run_with_binaries(
+        locator.binaries(),
+        input,
+        output,
+        options,
+    ))
+}
+
+/// Same as [`dry_run`] but reuses already-discovered binaries.
+pub fn dry_run_with_binaries(
+    binaries: &FfmpegBinaryPaths,
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &ThumbnailOptions,
+) -> String {
+    build_command(binaries, input, output, options).to_shell_string()
+}
+
+/// Build the ffmpeg command used to capture a thumbnail.
+pub fn build_command(
+    binaries: &FfmpegBinaryPaths,
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &ThumbnailOptions,
+) -> FfmpegCommand {
     let mut cmd = FfmpegCommand::new(binaries.ffmpeg());
+    if let Some(timeout) = options.timeout {
+        cmd.timeout(timeout);
+    }
     cmd.arg("-y");
     cmd.arg("-ss").arg(options.time.to_ffmpeg_timestamp());
     cmd.arg("-i").arg(input.as_ref());
@@ -124,5 +247,32 @@ pub fn generate_with_binaries(
         output_path.set_extension(options.format.extension());
     }
     cmd.arg(output_path);
-    cmd.run()
+    cmd
+}
+
+fn generate_with_runner(
+    binaries: &FfmpegBinaryPaths,
+    runner: &Arc<dyn CommandRunner>,
+    input: &Path,
+    output: &Path,
+    options: &ThumbnailOptions,
+) -> Result<()> {
+    prepare(binaries, runner, input, output, options)?.run()
+}
+
+/// Create the output directory and build the command bound to `runner`.
+fn prepare(
+    binaries: &FfmpegBinaryPaths,
+    runner: &Arc<dyn CommandRunner>,
+    input: &Path,
+    output: &Path,
+    options: &ThumbnailOptions,
+) -> Result<FfmpegCommand> {
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut cmd = build_command(binaries, input, output, options);
+    cmd.runner(Arc::clone(runner));
+    Ok(cmd)
 }
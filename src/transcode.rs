@@ -1,29 +1,67 @@
 //! Transcoding helpers built on top of the CLI `ffmpeg` binary.
 
 use std::ffi::OsString;
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
+use crate::capabilities::FfmpegCapabilities;
+#[cfg(feature = "tokio")]
+use crate::command::ProgressStream;
 use crate::command::{FfmpegBinaryPaths, FfmpegCommand};
-use crate::config::FfmpegLocator;
+use crate::config::{FfmpegLocator, Tools};
+use crate::encoder::{EncoderOptions, X264Options, X265Options};
 use crate::error::{Error, Result};
-use crate::filter::{AudioFilter, VideoFilter};
+use crate::filter::{AudioFilter, FilterGraph, VideoFilter};
+use crate::input::{Input, StreamMap};
+use crate::keyframe::{ForceKeyframes, GopInterval, GopSettings};
+use crate::logging::LogCapture;
+use crate::output::OutputStream;
+use crate::probe::probe_with_runner;
+#[cfg(feature = "tokio")]
+use crate::probe::probe_with_runner_async;
+use crate::progress::Progress;
+use crate::rate_control::RateControl;
+use crate::rendition::Rendition;
+use crate::runner::CommandRunner;
+use crate::tempdir::TempDir;
+use crate::types::{ProbeResult, StreamType, Time};
+use crate::validation::{
+    check_codec_container, check_filter_chain, infer_container, IssueKind, Severity,
+    ValidationReport,
+};
 
 /// Builder-style API for spinning up simple ffmpeg jobs.
 #[derive(Debug, Default)]
 pub struct TranscodeBuilder {
-    binaries: Option<FfmpegBinaryPaths>,
-    input: Option<PathBuf>,
+    tools: Tools,
+    inputs: Vec<Input>,
+    has_primary: bool,
+    maps: Vec<StreamMap>,
     output: Option<PathBuf>,
+    output_streams: Vec<OutputStream>,
     video_codec: Option<String>,
     audio_codec: Option<String>,
     video_bitrate: Option<u32>,
     audio_bitrate: Option<u32>,
+    video_rate_control: Option<RateControl>,
+    audio_rate_control: Option<RateControl>,
+    video_encoder_options: Option<EncoderOptions>,
+    audio_encoder_options: Option<EncoderOptions>,
+    gop: GopSettings,
     frame_rate: Option<f64>,
     preset: Option<String>,
     video_filters: Vec<VideoFilter>,
     audio_filters: Vec<AudioFilter>,
+    filter_graph: Option<FilterGraph>,
+    clip: Option<(Time, Time)>,
+    format: Option<String>,
     extra_args: Vec<OsString>,
     overwrite: bool,
+    two_pass: bool,
+    target_size: Option<u64>,
+    target_size_attempts: Option<u32>,
 }
 
 impl TranscodeBuilder {
@@ -37,19 +75,61 @@ impl TranscodeBuilder {
 
     /// Use pre-discovered binaries instead of searching PATH every call.
     pub fn with_binaries(mut self, binaries: &FfmpegBinaryPaths) -> Self {
-        self.binaries = Some(binaries.clone());
+        self.tools.binaries = Some(binaries.clone());
         self
     }
 
     /// Pin the builder to a specific locator.
     pub fn with_locator(mut self, locator: &FfmpegLocator) -> Self {
-        self.binaries = Some(locator.binaries().clone());
+        self.tools.locate(locator);
         self
     }
 
-    /// Input media path.
+    /// Execute ffmpeg/ffprobe through a custom [`CommandRunner`].
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.tools.runner = Some(runner);
+        self
+    }
+
+    /// Primary input media path, numbered 0.
+    ///
+    /// Calling it again replaces the primary input; inputs added with
+    /// [`add_input`](Self::add_input) keep their order after it.
     pub fn input<P: AsRef<Path>>(mut self, path: P) -> Self {
-        self.input = Some(path.as_ref().to_path_buf());
+        let input = Input::new(path);
+        if self.has_primary {
+            self.inputs[0] = input;
+        } else {
+            self.inputs.insert(0, input);
+            self.has_primary = true;
+        }
+        self
+    }
+
+    /// Append another input, e.g. an external audio track to mux in.
+    ///
+    /// Inputs are numbered in the order they are added, after the one set
+    /// through [`input`](Self::input); without it the first added input is 0.
+    pub fn add_input(mut self, input: impl Into<Input>) -> Self {
+        self.inputs.push(input.into());
+        self
+    }
+
+    /// Configure one output stream, e.g. a second audio track in AC-3 5.1.
+    ///
+    /// Per-stream options override the builder-wide codec and bitrate for the
+    /// streams they address.
+    pub fn output_stream(mut self, stream: OutputStream) -> Self {
+        self.output_streams.push(stream);
+        self
+    }
+
+    /// Select streams explicitly instead of letting ffmpeg pick (maps to `-map`).
+    ///
+    /// Maps are applied in order, so negative maps remove streams selected by
+    /// earlier ones.
+    pub fn map(mut self, map: StreamMap) -> Self {
+        self.maps.push(map);
         self
     }
 
@@ -71,6 +151,19 @@ impl TranscodeBuilder {
         self
     }
 
+    /// Typed options for the video or audio encoder, e.g. [`X264Options`].
+    ///
+    /// Sets the matching codec when none is configured; a different explicit
+    /// codec, or an out-of-range value, is rejected when the job is built.
+    pub fn encoder_options(mut self, options: impl Into<EncoderOptions>) -> Self {
+        let options = options.into();
+        match options.stream_type() {
+            StreamType::Audio => self.audio_encoder_options = Some(options),
+            _ => self.video_encoder_options = Some(options),
+        }
+        self
+    }
+
     /// Target video bitrate in kbps.
     pub fn video_bitrate(mut self, kbps: u32) -> Self {
         self.video_bitrate = Some(kbps);
@@ -83,6 +176,59 @@ impl TranscodeBuilder {
         self
     }
 
+    /// Video rate-control mode, rendered for the configured video codec.
+    ///
+    /// Requires [`video_codec`](Self::video_codec) and replaces
+    /// [`video_bitrate`](Self::video_bitrate); setting both is an error.
+    pub fn video_rate_control(mut self, rate: RateControl) -> Self {
+        self.video_rate_control = Some(rate);
+        self
+    }
+
+    /// Audio rate-control mode, rendered for the configured audio codec.
+    ///
+    /// Requires [`audio_codec`](Self::audio_codec) and replaces
+    /// [`audio_bitrate`](Self::audio_bitrate); setting both is an error.
+    pub fn audio_rate_control(mut self, rate: RateControl) -> Self {
+        self.audio_rate_control = Some(rate);
+        self
+    }
+
+    /// Fixed GOP of `frames` frames (sets both maximum and minimum keyframe distance).
+    ///
+    /// Conflicts with a `keyint`/`min_keyint` set in [`encoder_options`](Self::encoder_options).
+    pub fn gop_frames(mut self, frames: u32) -> Self {
+        self.gop.interval = Some(GopInterval::Frames(frames));
+        self
+    }
+
+    /// Fixed GOP of `seconds`, converted to frames using [`frame_rate`](Self::frame_rate).
+    ///
+    /// Without a frame rate, keyframes are forced at every multiple of
+    /// `seconds` via `-force_key_frames` instead.
+    pub fn gop_seconds(mut self, seconds: f64) -> Self {
+        self.gop.interval = Some(GopInterval::Seconds(seconds));
+        self
+    }
+
+    /// Close every GOP so segments can be decoded independently.
+    pub fn closed_gop(mut self, closed: bool) -> Self {
+        self.gop.closed = closed;
+        self
+    }
+
+    /// Allow (default) or suppress extra keyframes on scene changes.
+    pub fn scenecut(mut self, enabled: bool) -> Self {
+        self.gop.no_scenecut = !enabled;
+        self
+    }
+
+    /// Force keyframes at fixed intervals or timestamps (maps to `-force_key_frames`).
+    pub fn force_key_frames(mut self, placement: ForceKeyframes) -> Self {
+        self.gop.force = Some(placement);
+        self
+    }
+
     /// Target frame rate.
     pub fn frame_rate(mut self, fps: f64) -> Self {
         self.frame_rate = Some(fps);
@@ -100,6 +246,33 @@ impl TranscodeBuilder {
         self.add_video_filter(VideoFilter::Scale { width, height })
     }
 
+    /// Encode one [`Rendition`], e.g. a rung of a [`ladder`](crate::ladder).
+    ///
+    /// Sets the size, codecs, bitrates and frame rate; a peak bitrate becomes
+    /// `-maxrate`/`-bufsize` and a rendition without audio drops it (`-an`).
+    pub fn rendition(self, rendition: &Rendition) -> Self {
+        let mut builder = self
+            .size(rendition.width(), rendition.height())
+            .video_codec(rendition.video_codec_ref())
+            .video_bitrate(rendition.video_bitrate_value());
+        if let Some(fps) = rendition.frame_rate_value() {
+            builder = builder.frame_rate(fps);
+        }
+        if let Some(max) = rendition.max_bitrate_value() {
+            builder = builder
+                .extra_arg("-maxrate:v")
+                .extra_arg(format!("{max}k"))
+                .extra_arg("-bufsize:v")
+                .extra_arg(format!("{}k", max * 2));
+        }
+        match rendition.audio_bitrate_value() {
+            Some(kbps) => builder
+                .audio_codec(rendition.audio_codec_ref())
+                .audio_bitrate(kbps),
+            None => builder.extra_arg("-an"),
+        }
+    }
+
     /// Add a video filter to the processing chain.
     pub fn add_video_filter(mut self, filter: VideoFilter) -> Self {
         self.video_filters.push(filter);
@@ -112,12 +285,41 @@ impl TranscodeBuilder {
         self
     }
 
+    /// Route streams through a `-filter_complex` graph and map its outputs.
+    ///
+    /// Replaces the `-vf`/`-af` chains, which cannot be combined with it, and
+    /// is validated before the command is built. Other maps added with
+    /// [`map`](Self::map), e.g. subtitles, follow the graph outputs.
+    pub fn filter_graph(mut self, graph: FilterGraph) -> Self {
+        self.filter_graph = Some(graph);
+        self
+    }
+
+    /// Keep only `start..end` of the primary input, in both video and audio.
+    ///
+    /// The input is seeked to `start` (`-ss` before `-i`), then `trim`/`atrim`
+    /// cut the exact length and `setpts`/`asetpts` make the output start at
+    /// zero, ahead of any other filters. The streams are re-encoded, so codecs
+    /// cannot be `copy` (see [`cut`](crate::cut) for lossless cuts). After a
+    /// run the output is probed and a length off by more than 100 ms fails
+    /// with [`Error::DurationMismatch`].
+    pub fn clip(mut self, start: Time, end: Time) -> Self {
+        self.clip = Some((start, end));
+        self
+    }
+
     /// Backward compatibility: alias for `add_video_filter`.
     #[deprecated(since = "0.2.0", note = "use add_video_filter() instead")]
     pub fn add_filter(self, filter: VideoFilter) -> Self {
         self.add_video_filter(filter)
     }
 
+    /// Force the output container (maps to `-f`) instead of inferring it from the extension.
+    pub fn format(mut self, format: impl Into<String>) -> Self {
+        self.format = Some(format.into());
+        self
+    }
+
     /// Pass a raw argument for advanced cases.
     pub fn extra_arg(mut self, arg: impl Into<OsString>) -> Self {
         self.extra_args.push(arg.into());
@@ -130,9 +332,73 @@ impl TranscodeBuilder {
         self
     }
 
+    /// Encode video in two passes for more accurate bitrate targeting.
+    ///
+    /// Pass 1 analyses the video (audio disabled, output discarded) and writes
+    /// its statistics to a temporary directory that is removed afterwards;
+    /// pass 2 produces the output. Progress covers both passes, timeouts apply
+    /// to each pass, and failures are reported as [`Error::PassFailed`].
+    /// Requires an explicit [`video_codec`](Self::video_codec).
+    pub fn two_pass(mut self, enabled: bool) -> Self {
+        self.two_pass = enabled;
+        self
+    }
+
+    /// Aim for an output of at most `bytes` (e.g. an attachment limit).
+    ///
+    /// The input is probed for its duration, and the audio bitrate (the probed
+    /// one for copied audio, none without audio, otherwise 128 kbps unless set
+    /// via [`audio_bitrate`](Self::audio_bitrate) or an ABR/CBR
+    /// [`audio_rate_control`](Self::audio_rate_control)) and container overhead
+    /// are subtracted to get the video bitrate for a two-pass encode. If the
+    /// output still ends up too large, it is re-encoded at a lower bitrate, up
+    /// to [`target_size_attempts`](Self::target_size_attempts) times, before
+    /// failing with [`Error::TargetSizeExceeded`]. Replaces
+    /// [`video_bitrate`](Self::video_bitrate) and
+    /// [`video_rate_control`](Self::video_rate_control).
+    pub fn target_size(mut self, bytes: u64) -> Self {
+        self.target_size = Some(bytes);
+        self
+    }
+
+    /// Maximum number of encodes for [`target_size`](Self::target_size) (default 3).
+    pub fn target_size_attempts(mut self, attempts: u32) -> Self {
+        self.target_size_attempts = Some(attempts);
+        self
+    }
+
+    /// Abort the job with [`Error::TimeoutError`] if it runs longer than `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.tools.timeout = Some(timeout);
+        self
+    }
+
+    /// Keep ffmpeg's complete log in a file or attach it to the returned error.
+    ///
+    /// Two-pass jobs write one file per pass, e.g. `job-pass1.log` and `job-pass2.log`.
+    pub fn log_capture(mut self, capture: LogCapture) -> Self {
+        self.tools.log = capture;
+        self
+    }
+
     /// Accessor for the configured input path.
     pub fn input_path(&self) -> Option<&Path> {
-        self.input.as_deref()
+        self.inputs.first().map(Input::path)
+    }
+
+    /// Accessor for all configured inputs.
+    pub fn inputs(&self) -> &[Input] {
+        &self.inputs
+    }
+
+    /// Accessor for the configured stream maps.
+    pub fn maps(&self) -> &[StreamMap] {
+        &self.maps
+    }
+
+    /// Accessor for the per-stream output configuration.
+    pub fn output_streams(&self) -> &[OutputStream] {
+        &self.output_streams
     }
 
     /// Accessor for the configured output path.
@@ -150,6 +416,21 @@ impl TranscodeBuilder {
         self.audio_codec.as_deref()
     }
 
+    /// Accessor for the typed video encoder options.
+    pub fn video_encoder_options_ref(&self) -> Option<&EncoderOptions> {
+        self.video_encoder_options.as_ref()
+    }
+
+    /// Accessor for the typed audio encoder options.
+    pub fn audio_encoder_options_ref(&self) -> Option<&EncoderOptions> {
+        self.audio_encoder_options.as_ref()
+    }
+
+    /// Accessor for the configured GOP and keyframe settings.
+    pub fn gop_settings(&self) -> &GopSettings {
+        &self.gop
+    }
+
     /// Accessor for the configured video bitrate.
     pub fn video_bitrate_value(&self) -> Option<u32> {
         self.video_bitrate
@@ -160,6 +441,16 @@ impl TranscodeBuilder {
         self.audio_bitrate
     }
 
+    /// Accessor for the configured video rate-control mode.
+    pub fn video_rate_control_value(&self) -> Option<RateControl> {
+        self.video_rate_control
+    }
+
+    /// Accessor for the configured audio rate-control mode.
+    pub fn audio_rate_control_value(&self) -> Option<RateControl> {
+        self.audio_rate_control
+    }
+
     /// Accessor for the configured frame rate.
     pub fn frame_rate_value(&self) -> Option<f64> {
         self.frame_rate
@@ -170,11 +461,48 @@ impl TranscodeBuilder {
         self.preset.as_deref()
     }
 
+    /// Accessor for the forced output container.
+    pub fn format_value(&self) -> Option<&str> {
+        self.format.as_deref()
+    }
+
     /// Returns whether overwriting outputs is enabled.
     pub fn overwrite_enabled(&self) -> bool {
         self.overwrite
     }
 
+    /// Returns whether two-pass encoding is enabled.
+    pub fn two_pass_enabled(&self) -> bool {
+        self.two_pass
+    }
+
+    /// Accessor for the configured target output size in bytes.
+    pub fn target_size_value(&self) -> Option<u64> {
+        self.target_size
+    }
+
+    /// Accessor for the maximum number of target-size encodes.
+    pub fn target_size_attempts_value(&self) -> u32 {
+        self.target_size_attempts
+            .unwrap_or(DEFAULT_TARGET_SIZE_ATTEMPTS)
+            .max(1)
+    }
+
+    /// Accessor for the configured timeout.
+    pub fn timeout_value(&self) -> Option<Duration> {
+        self.tools.timeout
+    }
+
+    /// Accessor for the configured clip range.
+    pub fn clip_value(&self) -> Option<(Time, Time)> {
+        self.clip
+    }
+
+    /// Accessor for the configured filter graph.
+    pub fn filter_graph_ref(&self) -> Option<&FilterGraph> {
+        self.filter_graph.as_ref()
+    }
+
     /// Accessor for the configured video filter chain.
     pub fn video_filters(&self) -> &[VideoFilter] {
         &self.video_filters
@@ -185,101 +513,979 @@ impl TranscodeBuilder {
         &self.audio_filters
     }
 
-    fn resolve_binaries(binaries: Option<FfmpegBinaryPaths>) -> Result<FfmpegBinaryPaths> {
-        if let Some(paths) = binaries {
-            return Ok(paths);
-        }
-        Ok(FfmpegLocator::system()?.binaries().clone())
-    }
-
     fn validate(self) -> Result<ValidatedTranscode> {
         let Self {
-            binaries,
-            input,
+            tools,
+            inputs,
+            has_primary: _,
+            maps,
             output,
+            output_streams,
             video_codec,
             audio_codec,
             video_bitrate,
             audio_bitrate,
+            video_rate_control,
+            audio_rate_control,
+            video_encoder_options,
+            audio_encoder_options,
+            gop,
             frame_rate,
             preset,
             video_filters,
             audio_filters,
+            filter_graph,
+            clip,
+            format,
             extra_args,
             overwrite,
+            two_pass,
+            target_size,
+            target_size_attempts,
         } = self;
 
-        let input = input.ok_or_else(|| Error::InvalidInput("input path is required".into()))?;
+        if inputs.is_empty() {
+            return Err(Error::InvalidInput("input path is required".into()));
+        }
         let output = output.ok_or_else(|| Error::InvalidInput("output path is required".into()))?;
+        check_maps(&maps, inputs.len())?;
+        let mut inputs = inputs;
+        if let Some((start, end)) = clip {
+            if end <= start {
+                return Err(Error::InvalidInput(format!(
+                    "clip end {end} is not after its start {start}"
+                )));
+            }
+            if [&video_codec, &audio_codec]
+                .iter()
+                .any(|codec| codec.as_deref() == Some("copy"))
+            {
+                return Err(Error::InvalidInput(
+                    "clip() trims through filters and cannot stream-copy; use cut::CutBuilder"
+                        .into(),
+                ));
+            }
+            let primary = &inputs[0];
+            if primary.seek_value().is_some() || primary.duration_value().is_some() {
+                return Err(Error::InvalidInput(
+                    "clip() conflicts with a seek or duration on the primary input".into(),
+                ));
+            }
+            inputs[0] = primary.clone().seek(start);
+        }
+        let filter_graph_args = match &filter_graph {
+            Some(graph) => {
+                check_filter_graph(
+                    graph,
+                    inputs.len(),
+                    [&video_codec, &audio_codec],
+                    !video_filters.is_empty() || !audio_filters.is_empty() || clip.is_some(),
+                )?;
+                graph.to_args()?
+            }
+            None => Vec::new(),
+        };
+        let video_codec = resolve_encoder("video", video_codec, video_encoder_options.as_ref())?;
+        let audio_codec = resolve_encoder("audio", audio_codec, audio_encoder_options.as_ref())?;
+        gop.validate(frame_rate)?;
+        if gop.interval.is_some()
+            && video_encoder_options
+                .as_ref()
+                .is_some_and(EncoderOptions::has_keyint)
+        {
+            return Err(Error::InvalidInput(
+                "the GOP and the encoder options both set a keyframe interval; keep one".into(),
+            ));
+        }
+        // x264/x265 keep only the last `-x264-params`/`-x265-params`, so the
+        // rate-control and GOP entries join the typed options' parameters.
+        let rate_params = match (video_codec.as_deref(), video_rate_control) {
+            (Some(encoder), Some(rate)) if video_bitrate.is_none() => rate.encoder_params(encoder),
+            _ => Vec::new(),
+        };
+        let video_encoder_options = match (video_codec.as_deref(), video_encoder_options) {
+            (Some("libx265"), options) if !gop.is_empty() || !rate_params.is_empty() => {
+                let mut options = match options {
+                    Some(EncoderOptions::X265(options)) => options,
+                    _ => X265Options::new(),
+                };
+                options = gop.apply_x265(options, frame_rate);
+                for (key, value) in &rate_params {
+                    options = options.param(key, value);
+                }
+                let options = EncoderOptions::X265(options);
+                options.validate()?;
+                Some(options)
+            }
+            (Some("libx264" | "libx264rgb"), options) if !rate_params.is_empty() => {
+                let mut options = match options {
+                    Some(EncoderOptions::X264(options)) => options,
+                    _ => X264Options::new(),
+                };
+                for (key, value) in &rate_params {
+                    options = options.param(key, value);
+                }
+                let options = EncoderOptions::X264(options);
+                options.validate()?;
+                Some(options)
+            }
+            (_, options) => options,
+        };
+        let video_gop_args = gop.to_args(video_codec.as_deref(), frame_rate);
+        let audio_encoder_args = audio_encoder_options
+            .map(|options| options.to_args("a"))
+            .unwrap_or_default();
+        let video_rate_args = render_rate_control(
+            "video",
+            video_codec.as_deref(),
+            video_bitrate,
+            video_rate_control,
+        )?;
+        let audio_rate_args = render_rate_control(
+            "audio",
+            audio_codec.as_deref(),
+            audio_bitrate,
+            audio_rate_control,
+        )?;
+        let size_target = match target_size {
+            Some(_) if video_bitrate.is_some() || video_rate_control.is_some() => {
+                return Err(Error::InvalidInput(
+                    "target_size() picks the video bitrate; remove video_bitrate() and video_rate_control()"
+                        .into(),
+                ))
+            }
+            Some(bytes) => Some(SizeTarget {
+                bytes,
+                attempts: target_size_attempts
+                    .unwrap_or(DEFAULT_TARGET_SIZE_ATTEMPTS)
+                    .max(1),
+                attempt: 0,
+                audio_kbps: audio_bitrate.or(match audio_rate_control {
+                    Some(RateControl::Abr(kbps) | RateControl::Cbr { kbps, .. }) => Some(kbps),
+                    _ => None,
+                }),
+            }),
+            None => None,
+        };
+        let two_pass = two_pass || size_target.is_some();
+        if two_pass && matches!(video_codec.as_deref(), None | Some("copy")) {
+            return Err(Error::InvalidInput(
+                "two-pass encoding requires a video codec other than copy".into(),
+            ));
+        }
 
+        let (binaries, runner) = tools.resolve()?;
         Ok(ValidatedTranscode {
-            binaries: Self::resolve_binaries(binaries)?,
-            input,
+            binaries,
+            runner,
+            inputs,
+            maps,
             output,
+            output_streams,
             video_codec,
             audio_codec,
             video_bitrate,
             audio_bitrate,
+            video_rate_args,
+            audio_rate_args,
+            video_encoder_options,
+            video_gop_args,
+            audio_encoder_args,
             frame_rate,
             preset,
             video_filters,
             audio_filters,
+            filter_graph_args,
+            clip_length: clip.map(|(start, end)| end.as_duration() - start.as_duration()),
+            format,
             extra_args,
             overwrite,
+            two_pass,
+            size_target,
+            timeout: tools.timeout,
+            log: tools.log,
         })
     }
 
+    /// Check the job against what the installed ffmpeg supports.
+    ///
+    /// Infers the output container from [`format`](Self::format) or the output
+    /// extension, then reports codec/container mismatches, missing encoders,
+    /// muxers and filters, and stream-copy conflicts. Input and output paths
+    /// must be set; everything else is returned as a [`ValidationReport`].
+    pub fn validate_with(&self, caps: &FfmpegCapabilities) -> Result<ValidationReport> {
+        if self.inputs.is_empty() {
+            return Err(Error::InvalidInput("input path is required".into()));
+        }
+        check_maps(&self.maps, self.inputs.len())?;
+        let output = self
+            .output
+            .as_deref()
+            .ok_or_else(|| Error::InvalidInput("output path is required".into()))?;
+
+        let mut report = ValidationReport::default();
+        let container = match self.format.as_deref() {
+            Some(format) => Some(format),
+            None => infer_container(output),
+        };
+        match container {
+            Some(container) if !caps.supports_muxer(container) => report.push(
+                Severity::Error,
+                IssueKind::MissingMuxer,
+                format!("ffmpeg has no `{container}` muxer"),
+            ),
+            Some(_) => {}
+            None => report.push(
+                Severity::Warning,
+                IssueKind::UnknownContainer,
+                format!(
+                    "cannot infer the container of {}; set one with format()",
+                    output.display()
+                ),
+            ),
+        }
+
+        let video_chain = join_filters(&self.video_filters);
+        let audio_chain = join_filters(&self.audio_filters);
+        let mut streams = vec![
+            (
+                "video",
+                "the video stream".to_string(),
+                self.video_codec.as_deref().or(self
+                    .video_encoder_options
+                    .as_ref()
+                    .map(EncoderOptions::encoder)),
+            ),
+            (
+                "audio",
+                "the audio stream".to_string(),
+                self.audio_codec.as_deref().or(self
+                    .audio_encoder_options
+                    .as_ref()
+                    .map(EncoderOptions::encoder)),
+            ),
+        ];
+        for stream in &self.output_streams {
+            let kind = match stream.kind() {
+                StreamType::Video => "video",
+                StreamType::Audio => "audio",
+                StreamType::Subtitle => "subtitle",
+                StreamType::Data => "data",
+            };
+            let target = format!("output stream {}", stream.specifier());
+            streams.push((kind, target, stream.codec_ref()));
+        }
+        for (kind, target, codec) in streams {
+            let chain = match kind {
+                "video" => video_chain.as_str(),
+                "audio" => audio_chain.as_str(),
+                _ => "",
+            };
+            match codec {
+                Some("copy") if !chain.is_empty() => report.push(
+                    Severity::Error,
+                    IssueKind::CopyWithFilters,
+                    format!("{kind} filters cannot be applied while copying {target}"),
+                ),
+                Some("copy") | None => {}
+                Some(encoder) => {
+                    if !caps.supports_encoder(encoder) {
+                        report.push(
+                            Severity::Error,
+                            IssueKind::MissingEncoder,
+                            format!("encoder `{encoder}` is not available in this ffmpeg build"),
+                        );
+                    }
+                    if let Some(container) = container {
+                        check_codec_container(&mut report, caps, container, encoder);
+                    }
+                }
+            }
+        }
+        check_filter_chain(&mut report, caps, &video_chain);
+        check_filter_chain(&mut report, caps, &audio_chain);
+        if let Some(Ok(graph)) = self
+            .filter_graph
+            .as_ref()
+            .map(FilterGraph::to_filter_string)
+        {
+            check_filter_chain(&mut report, caps, &graph);
+        }
+
+        if self.video_codec.as_deref() == Some("copy") {
+            let ignored = [
+                ("bitrate", self.video_bitrate.is_some()),
+                ("rate control", self.video_rate_control.is_some()),
+                ("frame rate", self.frame_rate.is_some()),
+                ("preset", self.preset.is_some()),
+                ("GOP setting", !self.gop.is_empty()),
+            ];
+            for (option, _) in ignored.iter().filter(|(_, set)| *set) {
+                report.push(
+                    Severity::Warning,
+                    IssueKind::IgnoredWithCopy,
+                    format!("video {option} has no effect while copying the video stream"),
+                );
+            }
+        }
+        if self.audio_codec.as_deref() == Some("copy") {
+            let ignored = [
+                ("bitrate", self.audio_bitrate.is_some()),
+                ("rate control", self.audio_rate_control.is_some()),
+            ];
+            for (option, _) in ignored.iter().filter(|(_, set)| *set) {
+                report.push(
+                    Severity::Warning,
+                    IssueKind::IgnoredWithCopy,
+                    format!("audio {option} has no effect while copying the audio stream"),
+                );
+            }
+        }
+        Ok(report)
+    }
+
+    /// Validate the configuration and return the ffmpeg command without running it.
+    ///
+    /// Useful for logging or dry runs via [`FfmpegCommand::to_shell_string`].
+    /// Two-pass jobs have one command per pass; use
+    /// [`build_commands`](Self::build_commands) for those.
+    pub fn build_command(self) -> Result<FfmpegCommand> {
+        let validated = self.validate()?;
+        if validated.two_pass {
+            return Err(Error::InvalidInput(
+                "two-pass jobs run two commands; use build_commands()".into(),
+            ));
+        }
+        Ok(validated.into_command())
+    }
+
+    /// Validate the configuration and return the command of every pass.
+    ///
+    /// Two-pass commands use the passlog prefix `ffmpeg2pass` in the working
+    /// directory, as ffmpeg does by default; [`run`](Self::run) uses a
+    /// temporary directory instead.
+    /// Target-size jobs probe the input to pick the bitrate of their first attempt.
+    pub fn build_commands(self) -> Result<Vec<FfmpegCommand>> {
+        let mut validated = self.validate()?;
+        if validated.size_target.is_some() {
+            let probed = validated.probe_input();
+            validated.plan_size(probed.as_ref())?;
+        }
+        Ok(validated.commands(Path::new(PASSLOG_PREFIX)))
+    }
+
     /// Execute ffmpeg with the configured arguments.
     pub fn run(self) -> Result<()> {
+        let mut validated = self.validate()?;
+        if validated.size_target.is_some() {
+            let probed = validated.probe_input();
+            validated.plan_size(probed.as_ref())?;
+        }
+        loop {
+            let passes = validated.passes(None)?;
+            for (pass, cmd) in passes.iter() {
+                cmd.run().map_err(|err| passes.tag(pass, err))?;
+            }
+            if !validated.retry_for_size()? {
+                return validated.verify_clip();
+            }
+        }
+    }
+
+    /// Execute ffmpeg, reporting progress through `callback`.
+    ///
+    /// The input is probed first so reports carry percent and ETA; if probing
+    /// fails the job still runs without those estimates. Each
+    /// [`target_size`](Self::target_size) attempt reports from 0% again.
+    pub fn run_with_progress<F>(self, mut callback: F) -> Result<()>
+    where
+        F: FnMut(&Progress),
+    {
+        let mut validated = self.validate()?;
+        let probed = validated.probe_input();
+        let duration = validated.duration(probed.as_ref());
+        validated.plan_size(probed.as_ref())?;
+        loop {
+            let passes = validated.passes(duration)?;
+            for (pass, cmd) in passes.iter() {
+                cmd.run_with_progress(|progress| {
+                    callback(&progress.clone().for_pass(pass, passes.len(), duration))
+                })
+                .map_err(|err| passes.tag(pass, err))?;
+            }
+            if !validated.retry_for_size()? {
+                return validated.verify_clip();
+            }
+        }
+    }
+
+    /// Execute ffmpeg asynchronously (requires `tokio`).
+    ///
+    /// Dropping the future kills the ffmpeg process.
+    #[cfg(feature = "tokio")]
+    pub async fn run_async(self) -> Result<()> {
+        let mut validated = self.validate()?;
+        if validated.size_target.is_some() {
+            let probed = validated.probe_input_async().await;
+            validated.plan_size(probed.as_ref())?;
+        }
+        loop {
+            let passes = validated.passes(None)?;
+            for (pass, cmd) in passes.iter() {
+                cmd.run_async().await.map_err(|err| passes.tag(pass, err))?;
+            }
+            if !validated.retry_for_size()? {
+                return validated.verify_clip_async().await;
+            }
+        }
+    }
+
+    /// Async variant of [`run_with_progress`](Self::run_with_progress) (requires `tokio`).
+    ///
+    /// Dropping the future kills the ffmpeg process.
+    #[cfg(feature = "tokio")]
+    pub async fn run_with_progress_async<F>(self, mut callback: F) -> Result<()>
+    where
+        F: FnMut(&Progress) + Send,
+    {
+        let mut validated = self.validate()?;
+        let probed = validated.probe_input_async().await;
+        let duration = validated.duration(probed.as_ref());
+        validated.plan_size(probed.as_ref())?;
+        loop {
+            let passes = validated.passes(duration)?;
+            for (pass, cmd) in passes.iter() {
+                cmd.run_with_progress_async(|progress| {
+                    callback(&progress.clone().for_pass(pass, passes.len(), duration))
+                })
+                .await
+                .map_err(|err| passes.tag(pass, err))?;
+            }
+            if !validated.retry_for_size()? {
+                return validated.verify_clip_async().await;
+            }
+        }
+    }
+
+    /// Spawn ffmpeg and return a stream of progress reports (requires `tokio`).
+    ///
+    /// Not available for two-pass jobs; use
+    /// [`run_with_progress_async`](Self::run_with_progress_async) instead.
+    #[cfg(feature = "tokio")]
+    pub async fn progress_stream(self) -> Result<ProgressStream> {
         let validated = self.validate()?;
-        validated.run()
+        if validated.two_pass {
+            return Err(Error::Unsupported(
+                "progress streams cover a single command; two-pass jobs need run_with_progress_async()"
+                    .into(),
+            ));
+        }
+        let probed = validated.probe_input_async().await;
+        let duration = validated.duration(probed.as_ref());
+        let mut cmd = validated.into_command();
+        if let Some(duration) = duration {
+            cmd.duration_hint(duration);
+        }
+        cmd.progress_stream()
+    }
+}
+
+fn check_maps(maps: &[StreamMap], inputs: usize) -> Result<()> {
+    match maps.iter().find(|map| map.input_index() >= inputs) {
+        Some(map) => Err(Error::InvalidInput(format!(
+            "stream map `{map}` refers to input {} but only {inputs} input(s) are configured",
+            map.input_index()
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Check that a filter graph fits the rest of the job.
+fn check_filter_graph(
+    graph: &FilterGraph,
+    inputs: usize,
+    [video_codec, audio_codec]: [&Option<String>; 2],
+    has_chains: bool,
+) -> Result<()> {
+    if has_chains {
+        return Err(Error::InvalidInput(
+            "filter_graph() cannot be combined with -vf/-af filters or clip(); add them as graph nodes"
+                .into(),
+        ));
+    }
+    if let Some(index) = graph.max_input_index().filter(|index| *index >= inputs) {
+        return Err(Error::InvalidInput(format!(
+            "filter graph refers to input {index} but only {inputs} input(s) are configured"
+        )));
+    }
+    for (kind, codec) in [
+        (StreamType::Video, video_codec),
+        (StreamType::Audio, audio_codec),
+    ] {
+        if codec.as_deref() == Some("copy")
+            && graph.outputs_ref().iter().any(|pad| *pad.kind() == kind)
+        {
+            return Err(Error::InvalidInput(
+                "filtered streams cannot be stream-copied; pick an encoder".into(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Validate typed encoder options and derive the codec from them when unset.
+fn resolve_encoder(
+    kind: &str,
+    codec: Option<String>,
+    options: Option<&EncoderOptions>,
+) -> Result<Option<String>> {
+    let Some(options) = options else {
+        return Ok(codec);
+    };
+    options.validate()?;
+    match codec {
+        Some(codec) if codec != options.encoder() => Err(Error::InvalidInput(format!(
+            "{} options do not apply to {kind} codec {codec}",
+            options.encoder()
+        ))),
+        _ => Ok(Some(options.encoder().to_string())),
+    }
+}
+
+/// Render a rate-control mode for the stream's encoder.
+///
+/// Copied streams render nothing; [`TranscodeBuilder::validate_with`] warns about them.
+fn render_rate_control(
+    kind: &str,
+    codec: Option<&str>,
+    bitrate: Option<u32>,
+    rate: Option<RateControl>,
+) -> Result<Vec<OsString>> {
+    let Some(rate) = rate else {
+        return Ok(Vec::new());
+    };
+    if bitrate.is_some() {
+        return Err(Error::InvalidInput(format!(
+            "{kind} bitrate and {kind} rate control are mutually exclusive"
+        )));
+    }
+    match codec {
+        Some("copy") => Ok(Vec::new()),
+        // Encoder parameters are merged into the encoder options instead.
+        Some(encoder) => rate.flag_args(encoder, &kind[..1]),
+        None => Err(Error::InvalidInput(format!(
+            "{kind} rate control requires an explicit {kind} codec"
+        ))),
+    }
+}
+
+fn check_clip_length(expected: Duration, actual: Option<Duration>) -> Result<()> {
+    let actual = actual
+        .ok_or_else(|| Error::Parse("the clipped output does not report a duration".into()))?;
+    let off = if actual > expected {
+        actual - expected
+    } else {
+        expected - actual
+    };
+    if off > CLIP_TOLERANCE {
+        return Err(Error::DurationMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+fn join_filters<T: ToString>(filters: &[T]) -> String {
+    filters
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// How far the probed length of a clipped output may be from the requested one.
+const CLIP_TOLERANCE: Duration = Duration::from_millis(100);
+
+/// Upper bound for the duration probe that precedes progress-reporting runs.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Encodes attempted by target-size jobs unless configured otherwise.
+const DEFAULT_TARGET_SIZE_ATTEMPTS: u32 = 3;
+
+/// Audio bitrate assumed by target-size jobs when none is configured.
+const DEFAULT_AUDIO_KBPS: u32 = 128;
+
+/// Share of a target size reserved for container overhead.
+const CONTAINER_OVERHEAD: f64 = 0.02;
+
+/// Lowest video bitrate a target-size job will encode at.
+const MIN_VIDEO_KBPS: f64 = 16.0;
+
+/// Extra reduction applied on top of the measured overshoot when retrying.
+const RETRY_MARGIN: f64 = 0.95;
+
+/// File name prefix ffmpeg uses for two-pass statistics.
+const PASSLOG_PREFIX: &str = "ffmpeg2pass";
+
+/// Escape the separators of an `-x265-params` value (e.g. a Windows drive colon).
+fn escape_x265_param(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, ':' | '\\' | '=') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Where pass 1 writes its (discarded) output.
+const NULL_SINK: &str = if cfg!(windows) { "NUL" } else { "/dev/null" };
+
+/// The commands of a job, one per pass.
+struct Passes {
+    commands: Vec<FfmpegCommand>,
+    // Keeps the passlog directory alive until every pass has run.
+    _passlog: Option<TempDir>,
+}
+
+impl Passes {
+    fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Commands with their 1-based pass number.
+    fn iter(&self) -> impl Iterator<Item = (usize, &FfmpegCommand)> {
+        self.commands
+            .iter()
+            .enumerate()
+            .map(|(i, cmd)| (i + 1, cmd))
+    }
+
+    /// Attach the pass number to errors of multi-pass jobs.
+    fn tag(&self, pass: usize, error: Error) -> Error {
+        if self.len() > 1 {
+            Error::PassFailed {
+                pass,
+                error: Box::new(error),
+            }
+        } else {
+            error
+        }
     }
 }
 
 struct ValidatedTranscode {
     binaries: FfmpegBinaryPaths,
-    input: PathBuf,
+    runner: Arc<dyn CommandRunner>,
+    inputs: Vec<Input>,
+    maps: Vec<StreamMap>,
     output: PathBuf,
+    output_streams: Vec<OutputStream>,
     video_codec: Option<String>,
     audio_codec: Option<String>,
     video_bitrate: Option<u32>,
     audio_bitrate: Option<u32>,
+    video_rate_args: Vec<OsString>,
+    audio_rate_args: Vec<OsString>,
+    video_encoder_options: Option<EncoderOptions>,
+    video_gop_args: Vec<OsString>,
+    audio_encoder_args: Vec<OsString>,
     frame_rate: Option<f64>,
     preset: Option<String>,
     video_filters: Vec<VideoFilter>,
     audio_filters: Vec<AudioFilter>,
+    filter_graph_args: Vec<OsString>,
+    clip_length: Option<Duration>,
+    format: Option<String>,
     extra_args: Vec<OsString>,
     overwrite: bool,
+    two_pass: bool,
+    size_target: Option<SizeTarget>,
+    timeout: Option<Duration>,
+    log: LogCapture,
+}
+
+/// Bitrate search state of a [`TranscodeBuilder::target_size`] job.
+struct SizeTarget {
+    bytes: u64,
+    attempts: u32,
+    attempt: u32,
+    /// Audio bitrate set on the builder, if any.
+    audio_kbps: Option<u32>,
 }
 
 impl ValidatedTranscode {
-    fn run(self) -> Result<()> {
+    /// Expected output duration: the clip length, the primary input's `-t`, or its probed length.
+    fn known_duration(&self) -> Option<Duration> {
+        self.clip_length
+            .or_else(|| self.inputs[0].duration_value().map(Time::as_duration))
+    }
+
+    /// Probe a clipped output and compare its length with the requested one.
+    fn verify_clip(&self) -> Result<()> {
+        let Some(expected) = self.clip_length else {
+            return Ok(());
+        };
+        let probed = probe_with_runner(&self.binaries, &self.runner, &self.output, self.timeout)?;
+        check_clip_length(expected, probed.duration())
+    }
+
+    #[cfg(feature = "tokio")]
+    async fn verify_clip_async(&self) -> Result<()> {
+        let Some(expected) = self.clip_length else {
+            return Ok(());
+        };
+        let probed =
+            probe_with_runner_async(&self.binaries, &self.runner, &self.output, self.timeout)
+                .await?;
+        check_clip_length(expected, probed.duration())
+    }
+
+    /// Probe the primary input, unless its duration is known and no size target needs its audio.
+    fn probe_input(&self) -> Option<ProbeResult> {
+        if self.known_duration().is_some() && self.size_target.is_none() {
+            return None;
+        }
+        probe_with_runner(
+            &self.binaries,
+            &self.runner,
+            self.inputs[0].path(),
+            Some(self.timeout.unwrap_or(PROBE_TIMEOUT)),
+        )
+        .ok()
+    }
+
+    #[cfg(feature = "tokio")]
+    async fn probe_input_async(&self) -> Option<ProbeResult> {
+        if self.known_duration().is_some() && self.size_target.is_none() {
+            return None;
+        }
+        probe_with_runner_async(
+            &self.binaries,
+            &self.runner,
+            self.inputs[0].path(),
+            Some(self.timeout.unwrap_or(PROBE_TIMEOUT)),
+        )
+        .await
+        .ok()
+    }
+
+    /// Expected output duration, falling back to the probed input length.
+    fn duration(&self, probed: Option<&ProbeResult>) -> Option<Duration> {
+        self.known_duration()
+            .or_else(|| probed.and_then(ProbeResult::duration))
+    }
+
+    /// Audio bitrate a size target reserves next to the video.
+    ///
+    /// Nothing for outputs without audio, the probed bitrate for copied audio,
+    /// and the configured bitrate (128 kbps by default) for encoded audio.
+    fn reserved_audio_kbps(&self, configured: Option<u32>, probed: Option<&ProbeResult>) -> u32 {
+        let probed_audio = probed.map(ProbeResult::first_audio);
+        let single_source = self.inputs.len() == 1 && self.filter_graph_args.is_empty();
+        if self.extra_args.iter().any(|arg| arg == "-an")
+            || (single_source && matches!(probed_audio, Some(None)))
+        {
+            return 0;
+        }
+        if self.audio_codec.as_deref() == Some("copy") {
+            if let Some(bits) = probed_audio.flatten().and_then(|audio| audio.bit_rate) {
+                return u32::try_from((bits + 999) / 1000).unwrap_or(u32::MAX);
+            }
+        }
+        configured.unwrap_or(DEFAULT_AUDIO_KBPS)
+    }
+
+    fn into_command(self) -> FfmpegCommand {
+        self.command(None)
+    }
+
+    /// Commands for every pass, with passlog files at `passlog`.
+    fn commands(&self, passlog: &Path) -> Vec<FfmpegCommand> {
+        if self.two_pass {
+            vec![
+                self.command(Some((1, passlog))),
+                self.command(Some((2, passlog))),
+            ]
+        } else {
+            vec![self.command(None)]
+        }
+    }
+
+    /// Pick the first video bitrate of a target-size job from the input duration.
+    fn plan_size(&mut self, probed: Option<&ProbeResult>) -> Result<()> {
+        let Some(target) = &self.size_target else {
+            return Ok(());
+        };
+        let seconds = self
+            .duration(probed)
+            .map(|duration| duration.as_secs_f64())
+            .filter(|seconds| *seconds > 0.0)
+            .ok_or_else(|| {
+                Error::InvalidInput(
+                    "target size needs the input duration, but probing the input failed".into(),
+                )
+            })?;
+        let total_kbps = target.bytes as f64 * 8.0 / 1000.0 * (1.0 - CONTAINER_OVERHEAD) / seconds;
+        let audio_kbps = self.reserved_audio_kbps(target.audio_kbps, probed);
+        let video_kbps = total_kbps - f64::from(audio_kbps);
+        if video_kbps < MIN_VIDEO_KBPS {
+            return Err(Error::InvalidInput(format!(
+                "a {} byte target leaves {video_kbps:.0} kbps for {seconds:.1}s of video",
+                target.bytes
+            )));
+        }
+        self.video_bitrate = Some(video_kbps as u32);
+        Ok(())
+    }
+
+    /// Compare the output with the size target after an attempt.
+    ///
+    /// Returns `true` when another attempt should run at the reduced bitrate.
+    fn retry_for_size(&mut self) -> Result<bool> {
+        let Some(target) = &mut self.size_target else {
+            return Ok(false);
+        };
+        target.attempt += 1;
+        let actual = fs::metadata(&self.output)?.len();
+        if actual <= target.bytes {
+            return Ok(false);
+        }
+        let current = f64::from(self.video_bitrate.unwrap_or_default());
+        let reduced = current * target.bytes as f64 / actual as f64 * RETRY_MARGIN;
+        if target.attempt >= target.attempts || reduced < MIN_VIDEO_KBPS {
+            return Err(Error::TargetSizeExceeded {
+                target: target.bytes,
+                actual,
+                attempts: target.attempt,
+            });
+        }
+        self.video_bitrate = Some(reduced as u32);
+        // The previous attempt's output is ours to replace.
+        self.overwrite = true;
+        Ok(true)
+    }
+
+    /// Commands to run, with the passlog directory of two-pass jobs.
+    fn passes(&self, duration: Option<Duration>) -> Result<Passes> {
+        let passlog = if self.two_pass {
+            Some(TempDir::new("ffmpeg-light-2pass")?)
+        } else {
+            None
+        };
+        let prefix = passlog
+            .as_ref()
+            .map(|dir| dir.path().join(PASSLOG_PREFIX))
+            .unwrap_or_default();
+        let mut commands = self.commands(&prefix);
+        if let Some(duration) = duration {
+            for cmd in &mut commands {
+                cmd.duration_hint(duration);
+            }
+        }
+        Ok(Passes {
+            commands,
+            _passlog: passlog,
+        })
+    }
+
+    fn is_x265(&self) -> bool {
+        self.video_codec.as_deref() == Some("libx265")
+    }
+
+    /// Typed video encoder flags; libx265 ignores `-pass`, so its passes go in `-x265-params`.
+    fn video_encoder_args(&self, pass: Option<(usize, &Path)>) -> Vec<OsString> {
+        match (pass.filter(|_| self.is_x265()), &self.video_encoder_options) {
+            (Some((pass, passlog)), options) => {
+                let options = match options {
+                    Some(EncoderOptions::X265(options)) => options.clone(),
+                    _ => X265Options::new(),
+                };
+                let stats = format!("{}-0.log", passlog.display());
+                EncoderOptions::X265(
+                    options
+                        .param("pass", pass.to_string())
+                        .param("stats", escape_x265_param(&stats)),
+                )
+                .to_args("v")
+            }
+            (None, options) => options
+                .as_ref()
+                .map(|options| options.to_args("v"))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Render the command of a single-pass job, or of `pass` of a two-pass job.
+    ///
+    /// Pass 1 only analyses video: audio is disabled and the output discarded.
+    fn command(&self, pass: Option<(usize, &Path)>) -> FfmpegCommand {
+        let analysis = matches!(pass, Some((1, _)));
         let mut cmd = FfmpegCommand::new(self.binaries.ffmpeg());
-        cmd.arg(if self.overwrite { "-y" } else { "-n" });
-        cmd.arg("-i").arg(&self.input);
+        cmd.runner(Arc::clone(&self.runner));
+        if let Some(timeout) = self.timeout {
+            cmd.timeout(timeout);
+        }
+        cmd.log_capture(match pass {
+            Some((pass, _)) => self.log.for_pass(pass),
+            None => self.log.clone(),
+        });
+        // `-n` would refuse to write to the null sink, which always exists.
+        cmd.arg(if self.overwrite || analysis {
+            "-y"
+        } else {
+            "-n"
+        });
+        for input in &self.inputs {
+            cmd.args(&input.to_args());
+        }
+        cmd.args(&self.filter_graph_args);
+        for map in &self.maps {
+            cmd.arg("-map").arg(map.to_string());
+        }
 
-        if let Some(codec) = self.video_codec {
+        if let Some(codec) = &self.video_codec {
             cmd.arg("-c:v").arg(codec);
         }
-        if let Some(codec) = self.audio_codec {
+        if let Some(codec) = self.audio_codec.as_ref().filter(|_| !analysis) {
             cmd.arg("-c:a").arg(codec);
         }
         if let Some(kbps) = self.video_bitrate {
             cmd.arg("-b:v").arg(format!("{kbps}k"));
         }
-        if let Some(kbps) = self.audio_bitrate {
+        if let Some(kbps) = self.audio_bitrate.filter(|_| !analysis) {
             cmd.arg("-b:a").arg(format!("{kbps}k"));
         }
+        cmd.args(&self.video_rate_args);
+        if !analysis {
+            cmd.args(&self.audio_rate_args);
+        }
         if let Some(fps) = self.frame_rate {
             cmd.arg("-r").arg(format!("{fps}"));
         }
-        if let Some(preset) = self.preset {
+        if let Some(preset) = &self.preset {
             cmd.arg("-preset").arg(preset);
         }
+        cmd.args(&self.video_encoder_args(pass));
+        cmd.args(&self.video_gop_args);
+        if !analysis {
+            cmd.args(&self.audio_encoder_args);
+        }
+        for stream in &self.output_streams {
+            if analysis && *stream.kind() == StreamType::Audio {
+                continue;
+            }
+            cmd.args(&stream.to_args());
+        }
 
         // Build video filter chain
         let mut vf_strings: Vec<String> = Vec::new();
-        for filter in self.video_filters {
+        let mut af_strings: Vec<String> = Vec::new();
+        // Plain seconds: the colons of a timestamp would split the filter options.
+        if let Some(length) = self.clip_length.map(|length| length.as_secs_f64()) {
+            vf_strings.push(format!("trim=duration={length},setpts=PTS-STARTPTS"));
+            af_strings.push(format!("atrim=duration={length},asetpts=PTS-STARTPTS"));
+        }
+        for filter in &self.video_filters {
             vf_strings.push(filter.to_filter_string());
         }
         if !vf_strings.is_empty() {
@@ -287,20 +1493,33 @@ impl ValidatedTranscode {
         }
 
         // Build audio filter chain
-        let mut af_strings: Vec<String> = Vec::new();
-        for filter in self.audio_filters {
+        for filter in &self.audio_filters {
             af_strings.push(filter.to_filter_string());
         }
-        if !af_strings.is_empty() {
+        if !af_strings.is_empty() && !analysis {
             cmd.arg("-af").arg(af_strings.join(","));
         }
 
-        for arg in self.extra_args {
+        for arg in &self.extra_args {
             cmd.arg(arg);
         }
+        // Streams that bypass the filters (e.g. subtitles) stop at the same point.
+        if let Some(length) = self.clip_length.map(Time::from) {
+            cmd.arg("-t").arg(length.to_ffmpeg_timestamp());
+        }
 
+        if let Some((pass, passlog)) = pass.filter(|_| !self.is_x265()) {
+            cmd.arg("-pass").arg(pass.to_string());
+            cmd.arg("-passlogfile").arg(passlog);
+        }
+        if analysis {
+            cmd.arg("-an").arg("-f").arg("null").arg(NULL_SINK);
+            return cmd;
+        }
+        if let Some(format) = &self.format {
+            cmd.arg("-f").arg(format);
+        }
         cmd.arg(&self.output);
-        cmd.run()
+        cmd
     }
 }
-
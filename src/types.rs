@@ -96,11 +96,11 @@ impl CodecType {
         match name.to_lowercase().as_str() {
             "h264" | "libx264" => CodecType::H264,
             "hevc" | "h265" | "libx265" => CodecType::Hevc,
-            "vp9" => CodecType::Vp9,
-            "av1" => CodecType::Av1,
-            "aac" => CodecType::Aac,
-            "mp3" => CodecType::Mp3,
-            "opus" => CodecType::Opus,
+            "vp9" | "libvpx-vp9" => CodecType::Vp9,
+            "av1" | "libaom-av1" | "libsvtav1" => CodecType::Av1,
+            "aac" | "libfdk_aac" => CodecType::Aac,
+            "mp3" | "libmp3lame" => CodecType::Mp3,
+            "opus" | "libopus" => CodecType::Opus,
             "pcm_s16le" => CodecType::PcmS16Le,
             "copy" => CodecType::Copy,
             other => CodecType::Other(other.to_string()),
@@ -197,6 +197,16 @@ pub struct VideoStreamInfo {
     pub bit_rate: Option<u64>,
     /// Average frame rate (frames per second).
     pub frame_rate: Option<f64>,
+    /// Pixel format (e.g. "yuv420p").
+    pub pix_fmt: Option<String>,
+    /// Codec profile as named by ffprobe (e.g. "High").
+    pub profile: Option<String>,
+    /// Codec level as reported by ffprobe (e.g. 40 for H.264 level 4.0).
+    pub level: Option<i32>,
+    /// Sample aspect ratio (e.g. "1:1").
+    pub sample_aspect_ratio: Option<String>,
+    /// Stream time base (e.g. "1/15360").
+    pub time_base: Option<String>,
 }
 
 /// Audio stream metadata.
@@ -210,6 +220,10 @@ pub struct AudioStreamInfo {
     pub sample_rate: Option<u32>,
     /// Bit rate in bits/sec.
     pub bit_rate: Option<u64>,
+    /// Channel layout (e.g. "stereo").
+    pub channel_layout: Option<String>,
+    /// Sample format (e.g. "fltp").
+    pub sample_fmt: Option<String>,
 }
 
 /// Subtitle stream metadata.
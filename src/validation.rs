@@ -0,0 +1,307 @@
+//! Pre-flight checks run against [`FfmpegCapabilities`] before spawning ffmpeg.
+//!
+//! Catching a missing encoder or an impossible codec/container pairing up
+//! front gives a readable error instead of ffmpeg bailing out after the input
+//! has been opened.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::capabilities::{FfmpegCapabilities, FfmpegVersion};
+use crate::error::{Error, Result};
+
+/// How serious a [`ValidationIssue`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// ffmpeg may still succeed, but probably not as intended.
+    Warning,
+    /// ffmpeg is expected to fail.
+    Error,
+}
+
+/// Category of a [`ValidationIssue`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IssueKind {
+    /// The requested encoder is not compiled into ffmpeg.
+    MissingEncoder,
+    /// The output container has no muxer in this ffmpeg build.
+    MissingMuxer,
+    /// A filter in one of the chains is not available.
+    MissingFilter,
+    /// The codec cannot be stored in the output container.
+    CodecContainerMismatch,
+    /// Stream copy was combined with filters, which require re-encoding.
+    CopyWithFilters,
+    /// An encoding option has no effect because the stream is copied.
+    IgnoredWithCopy,
+    /// The output container could not be inferred from the path.
+    UnknownContainer,
+}
+
+/// A single problem found during validation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// Whether the issue is fatal.
+    pub severity: Severity,
+    /// What kind of problem was found.
+    pub kind: IssueKind,
+    /// Human readable description.
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{label}: {}", self.message)
+    }
+}
+
+/// Outcome of a pre-flight validation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// All issues in the order they were found.
+    pub fn issues(&self) -> &[ValidationIssue] {
+        &self.issues
+    }
+
+    /// Issues that are expected to make ffmpeg fail.
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.with_severity(Severity::Error)
+    }
+
+    /// Issues that are suspicious but not fatal.
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.with_severity(Severity::Warning)
+    }
+
+    /// True when no errors were found (warnings are allowed).
+    pub fn is_ok(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    /// Whether an issue of the given kind was reported.
+    pub fn has(&self, kind: IssueKind) -> bool {
+        self.issues.iter().any(|issue| issue.kind == kind)
+    }
+
+    /// Turn errors into [`Error::InvalidInput`], keeping the report for its warnings.
+    pub fn into_result(self) -> Result<Self> {
+        if self.is_ok() {
+            return Ok(self);
+        }
+        let messages: Vec<&str> = self.errors().map(|issue| issue.message.as_str()).collect();
+        Err(Error::InvalidInput(messages.join("; ")))
+    }
+
+    pub(crate) fn push(&mut self, severity: Severity, kind: IssueKind, message: String) {
+        self.issues.push(ValidationIssue {
+            severity,
+            kind,
+            message,
+        });
+    }
+
+    fn with_severity(&self, severity: Severity) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues
+            .iter()
+            .filter(move |issue| issue.severity == severity)
+    }
+}
+
+/// Muxer name ffmpeg would pick for `path`, based on its extension.
+pub fn infer_container(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let muxer = match extension.as_str() {
+        "mp4" | "m4v" => "mp4",
+        "m4a" => "ipod",
+        "mov" => "mov",
+        "mkv" | "mka" => "matroska",
+        "webm" => "webm",
+        "avi" => "avi",
+        "flv" => "flv",
+        "ts" | "m2ts" => "mpegts",
+        "ogg" | "oga" | "ogv" => "ogg",
+        "opus" => "opus",
+        "mp3" => "mp3",
+        "wav" => "wav",
+        "flac" => "flac",
+        "aac" => "adts",
+        "gif" => "gif",
+        "m3u8" => "hls",
+        "mpd" => "dash",
+        "png" | "jpg" | "jpeg" | "bmp" | "webp" => "image2",
+        _ => return None,
+    };
+    Some(muxer)
+}
+
+/// Opus in MP4 stopped requiring `-strict experimental` in ffmpeg 4.3.
+const OPUS_IN_MP4_SINCE: FfmpegVersion = FfmpegVersion::new(4, 3, 0);
+
+/// Codec family an encoder (or codec) name produces, e.g. `libx264` -> `h264`.
+pub(crate) fn codec_family(encoder: &str) -> &str {
+    let name = encoder.strip_prefix("lib").unwrap_or(encoder);
+    match name {
+        "x264" | "openh264" => "h264",
+        "x265" | "h265" => "hevc",
+        "vpx" => "vp8",
+        "vpx-vp9" => "vp9",
+        "aom-av1" | "svtav1" | "rav1e" | "dav1d" => "av1",
+        "fdk_aac" => "aac",
+        "mp3lame" | "shine" => "mp3",
+        "vorbis" => "vorbis",
+        "opus" => "opus",
+        _ if name.starts_with("pcm_") => "pcm",
+        // Hardware encoders: h264_nvenc, hevc_vaapi, av1_qsv, ...
+        _ => name.split_once('_').map_or(name, |(family, _)| family),
+    }
+}
+
+/// Check whether `container` can hold `codec`, reporting mismatches.
+pub(crate) fn check_codec_container(
+    report: &mut ValidationReport,
+    caps: &FfmpegCapabilities,
+    container: &str,
+    codec: &str,
+) {
+    let family = codec_family(codec);
+    let mismatch = |reason: &str| format!("{codec} ({family}) {reason} {container} output");
+    match (container, family) {
+        ("webm", "vp8" | "vp9" | "av1" | "vorbis" | "opus") => {}
+        ("webm", "h264" | "hevc" | "aac" | "mp3" | "pcm" | "flac") => report.push(
+            Severity::Error,
+            IssueKind::CodecContainerMismatch,
+            mismatch("cannot be stored in"),
+        ),
+        ("mp4" | "ipod", "pcm" | "vorbis") => report.push(
+            Severity::Error,
+            IssueKind::CodecContainerMismatch,
+            mismatch("cannot be stored in"),
+        ),
+        ("mp4" | "ipod", "opus") => match caps.version() {
+            Some(version) if version >= OPUS_IN_MP4_SINCE => {}
+            Some(version) => report.push(
+                Severity::Error,
+                IssueKind::CodecContainerMismatch,
+                mismatch(&format!(
+                    "requires ffmpeg {OPUS_IN_MP4_SINCE} or newer (found {version}) for"
+                )),
+            ),
+            None => report.push(
+                Severity::Warning,
+                IssueKind::CodecContainerMismatch,
+                mismatch("needs ffmpeg 4.3 or newer for"),
+            ),
+        },
+        _ => {}
+    }
+}
+
+/// Report filters from a comma-separated chain that ffmpeg does not provide.
+pub(crate) fn check_filter_chain(
+    report: &mut ValidationReport,
+    caps: &FfmpegCapabilities,
+    chain: &str,
+) {
+    for name in filter_names(chain) {
+        if !caps.supports_filter(name) {
+            report.push(
+                Severity::Error,
+                IssueKind::MissingFilter,
+                format!("filter `{name}` is not available in this ffmpeg build"),
+            );
+        }
+    }
+}
+
+/// Filter names used in a chain such as `[0:v]scale=640:-1,fps=30[out]`.
+///
+/// Splits on `,` and `;` the way ffmpeg's graph parser does: quoted (`'...'`)
+/// and backslash-escaped characters belong to the filter arguments.
+fn filter_names(chain: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, c) in chain.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '\'' => quoted = !quoted,
+            ',' | ';' if !quoted => {
+                names.extend(filter_name(&chain[start..index]));
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    names.extend(filter_name(&chain[start..]));
+    names
+}
+
+/// Name of one filter, without `[label]` pads, `@instance` suffix or arguments.
+fn filter_name(segment: &str) -> Option<&str> {
+    let mut segment = segment.trim();
+    while let Some(rest) = segment.strip_prefix('[') {
+        segment = rest
+            .split_once(']')
+            .map_or("", |(_, after)| after)
+            .trim_start();
+    }
+    let end = segment
+        .find(|c: char| matches!(c, '=' | '[' | '@') || c.is_whitespace())
+        .unwrap_or(segment.len());
+    let name = &segment[..end];
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_filter_names_from_chains() {
+        let names = filter_names("[0:v]scale=640:-1,fps=30[v];[1:a] loudnorm=I=-16 ,hflip");
+        assert_eq!(names, ["scale", "fps", "loudnorm", "hflip"]);
+    }
+
+    #[test]
+    fn skips_quoted_and_escaped_separators() {
+        assert_eq!(
+            filter_names("drawtext=text='a,b; c':x=10,drawbox=t=fill"),
+            ["drawtext", "drawbox"]
+        );
+        assert_eq!(
+            filter_names(r"drawtext=text=a\,b\;c,hflip"),
+            ["drawtext", "hflip"]
+        );
+        assert_eq!(
+            filter_names("[0:v][1:v] overlay@logo=W-w:0 [out];[out]split=2[a][b]"),
+            ["overlay", "split"]
+        );
+    }
+
+    #[test]
+    fn maps_encoders_to_codec_families() {
+        assert_eq!(codec_family("libx264"), "h264");
+        assert_eq!(codec_family("h264_nvenc"), "h264");
+        assert_eq!(codec_family("libvpx-vp9"), "vp9");
+        assert_eq!(codec_family("pcm_s16le"), "pcm");
+        assert_eq!(codec_family("libfdk_aac"), "aac");
+        assert_eq!(codec_family("aac"), "aac");
+    }
+
+    #[test]
+    fn infers_container_from_extension() {
+        assert_eq!(infer_container(Path::new("out.MP4")), Some("mp4"));
+        assert_eq!(infer_container(Path::new("out.mkv")), Some("matroska"));
+        assert_eq!(infer_container(Path::new("out")), None);
+    }
+}
@@ -0,0 +1,13 @@
+//! Helpers shared by the integration tests.
+
+use std::sync::Arc;
+
+use ffmpeg_light::command::FfmpegBinaryPaths;
+use ffmpeg_light::config::FfmpegLocator;
+use ffmpeg_light::testing::MockRunner;
+
+/// Locator whose commands go through `mock` instead of spawning ffmpeg.
+pub fn mock_locator(mock: &MockRunner) -> FfmpegLocator {
+    FfmpegLocator::from_paths(FfmpegBinaryPaths::with_paths("ffmpeg", "ffprobe"))
+        .with_runner(Arc::new(mock.clone()))
+}
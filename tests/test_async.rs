@@ -0,0 +1,139 @@
+//! Async API tests. Drives the tokio variants through the mock runner.
+
+#![cfg(feature = "tokio")]
+
+mod common;
+
+use std::future::Future;
+use std::time::Duration;
+
+use common::mock_locator;
+use ffmpeg_light::probe::ProbeBuilder;
+use ffmpeg_light::runner::StdoutMode;
+use ffmpeg_light::testing::{MockResponse, MockRunner};
+use ffmpeg_light::thumbnail::{self, ThumbnailOptions};
+use ffmpeg_light::types::Time;
+use ffmpeg_light::{Error, TranscodeBuilder};
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn test_transcode_run_async_records_invocation() {
+    let mock = MockRunner::new();
+    block_on(
+        TranscodeBuilder::new()
+            .with_locator(&mock_locator(&mock))
+            .input("input.mov")
+            .output("output.mp4")
+            .audio_codec("aac")
+            .run_async(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        mock.last_invocation().unwrap().args_lossy(),
+        [
+            "-loglevel",
+            "level+info",
+            "-y",
+            "-i",
+            "input.mov",
+            "-c:a",
+            "aac",
+            "output.mp4"
+        ]
+    );
+    assert_eq!(mock.last_invocation().unwrap().stdout, StdoutMode::Inherit);
+}
+
+#[test]
+fn test_transcode_run_async_maps_failures() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::failure(1, "Unknown encoder 'libx265'"));
+    let err = block_on(
+        TranscodeBuilder::new()
+            .with_locator(&mock_locator(&mock))
+            .input("input.mov")
+            .output("output.mp4")
+            .run_async(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::ProcessingError { .. }));
+}
+
+#[test]
+fn test_run_with_progress_async_reports_percent() {
+    let mock = MockRunner::new();
+    mock.push_response(
+        MockResponse::success().stdout(r#"{"format": {"duration": "8.0"}, "streams": []}"#),
+    );
+    mock.push_response(
+        MockResponse::success().stdout("out_time_us=2000000\nprogress=continue\nprogress=end\n"),
+    );
+
+    let mut percents = Vec::new();
+    block_on(
+        TranscodeBuilder::new()
+            .with_locator(&mock_locator(&mock))
+            .input("input.mov")
+            .output("output.mp4")
+            .run_with_progress_async(|progress| percents.push(progress.percent)),
+    )
+    .unwrap();
+
+    assert_eq!(percents, [Some(25.0), Some(100.0)]);
+}
+
+#[test]
+fn test_thumbnail_generate_async_uses_locator_runner() {
+    let mock = MockRunner::new();
+    let options = ThumbnailOptions::new(Time::from_seconds(1));
+    block_on(thumbnail::generate_with_locator_async(
+        &mock_locator(&mock),
+        "input.mp4",
+        "thumb.png",
+        &options,
+    ))
+    .unwrap();
+
+    let args = mock.last_invocation().unwrap().args_lossy();
+    assert_eq!(args[2..5], ["-y", "-ss", "00:00:01.000"]);
+}
+
+#[test]
+fn test_async_timeout_response() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::timeout());
+    let err = block_on(thumbnail::generate_with_locator_async(
+        &mock_locator(&mock),
+        "input.mp4",
+        "thumb.png",
+        &ThumbnailOptions::new(Time::zero()),
+    ))
+    .unwrap_err();
+    assert!(matches!(err, Error::TimeoutError(_)));
+}
+
+#[test]
+fn test_probe_builder_run_async_times_out() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::timeout());
+    let err = block_on(
+        ProbeBuilder::new("input.mp4")
+            .with_locator(&mock_locator(&mock))
+            .timeout(Duration::from_secs(1))
+            .run_async(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::TimeoutError(_)));
+    assert_eq!(
+        mock.last_invocation().unwrap().timeout,
+        Some(Duration::from_secs(1))
+    );
+}
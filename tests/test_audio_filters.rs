@@ -76,7 +76,7 @@ fn test_audio_filter_display() {
 
 #[test]
 fn test_multiple_audio_filters_compatibility() {
-    let filters = vec![
+    let filters = [
         AudioFilter::Normalization {
             target_level: -23.0,
         },
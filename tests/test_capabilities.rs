@@ -0,0 +1,213 @@
+//! Capability discovery tests. Feeds canned ffmpeg listings through the mock runner.
+
+mod common;
+
+use common::mock_locator;
+use ffmpeg_light::capabilities::FfmpegVersion;
+use ffmpeg_light::output::OutputStream;
+use ffmpeg_light::testing::{MockResponse, MockRunner};
+use ffmpeg_light::validation::{IssueKind, Severity};
+use ffmpeg_light::{CodecType, TranscodeBuilder, VideoFilter};
+
+const VERSION: &str = "ffmpeg version 6.1.1 Copyright (c) 2000-2023 the FFmpeg developers\n\
+configuration: --enable-gpl --enable-libx264 --enable-libopus\n";
+const ENCODERS: &str = "Encoders:\n V..... = Video\n ------\n \
+V....D libx264              libx264 H.264 (codec h264)\n \
+A....D aac                  AAC (Advanced Audio Coding)\n \
+A....D libopus              libopus Opus (codec opus)\n";
+const DECODERS: &str = "Decoders:\n ------\n VFS..D h264                 H.264\n";
+const MUXERS: &str =
+    "Muxers:\n --\n  E mp4             MP4 (MPEG-4 Part 14)\n  E webm            WebM\n";
+const DEMUXERS: &str = "Demuxers:\n --\n D  mov,mp4,m4a,3gp,3g2,mj2 QuickTime / MOV\n";
+const FILTERS: &str = "Filters:\n  T.. = Timeline support\n \
+TSC scale             V->V       Scale the input video size.\n \
+... loudnorm          A->A       EBU R128 loudness normalization\n";
+const PIX_FMTS: &str =
+    "Pixel formats:\nFLAGS NAME NB_COMPONENTS BITS_PER_PIXEL BIT_DEPTHS\n-----\n\
+IO... yuv420p                3             12      8-8-8\n";
+const PROTOCOLS: &str = "Supported file protocols:\nInput:\n  file\n  https\nOutput:\n  file\n";
+
+fn mock_with_listings() -> MockRunner {
+    let mock = MockRunner::new();
+    for listing in [
+        VERSION, ENCODERS, DECODERS, MUXERS, DEMUXERS, FILTERS, PIX_FMTS, PROTOCOLS,
+    ] {
+        mock.push_response(MockResponse::success().stdout(listing));
+    }
+    mock
+}
+
+#[test]
+fn test_capabilities_are_parsed() {
+    let mock = mock_with_listings();
+    let locator = mock_locator(&mock);
+    let caps = locator.capabilities().unwrap();
+
+    assert_eq!(caps.version(), Some(FfmpegVersion::new(6, 1, 1)));
+    assert!(caps.has_configure_flag("--enable-libx264"));
+    assert!(caps.supports_encoder("libx264"));
+    assert!(!caps.supports_encoder("libx265"));
+    assert!(caps.supports_codec(&CodecType::H264));
+    assert!(!caps.supports_codec(&CodecType::Hevc));
+    assert!(caps.supports_codec(&CodecType::Copy));
+    assert!(caps.supports_decoder("h264"));
+    assert!(caps.supports_muxer("webm"));
+    assert!(caps.supports_demuxer("mp4"));
+    assert!(caps.supports_filter("loudnorm"));
+    assert!(caps.supports_pix_fmt("yuv420p"));
+    assert!(caps.supports_input_protocol("https"));
+    assert!(!caps.supports_output_protocol("https"));
+}
+
+#[test]
+fn test_capabilities_are_cached_across_clones() {
+    let mock = mock_with_listings();
+    let locator = mock_locator(&mock);
+    locator.capabilities().unwrap();
+    locator.clone().capabilities().unwrap();
+
+    let flags: Vec<String> = mock
+        .invocations()
+        .iter()
+        .map(|invocation| invocation.args_lossy()[3].clone())
+        .collect();
+    assert_eq!(
+        flags,
+        [
+            "-version",
+            "-encoders",
+            "-decoders",
+            "-muxers",
+            "-demuxers",
+            "-filters",
+            "-pix_fmts",
+            "-protocols"
+        ]
+    );
+}
+
+#[test]
+fn test_failed_detection_is_not_cached() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::failure(1, "boom"));
+    let locator = mock_locator(&mock);
+    assert!(locator.capabilities().is_err());
+    assert!(locator.capabilities().is_ok());
+}
+
+fn detected() -> ffmpeg_light::FfmpegCapabilities {
+    mock_locator(&mock_with_listings())
+        .capabilities()
+        .unwrap()
+        .clone()
+}
+
+#[test]
+fn test_validate_with_accepts_supported_job() {
+    let report = TranscodeBuilder::new()
+        .input("input.mov")
+        .output("output.mp4")
+        .video_codec("libx264")
+        .audio_codec("aac")
+        .size(1280, 720)
+        .validate_with(&detected())
+        .unwrap();
+    assert!(report.issues().is_empty(), "{:?}", report.issues());
+}
+
+#[test]
+fn test_validate_with_flags_container_and_encoder_problems() {
+    let caps = detected();
+    let report = TranscodeBuilder::new()
+        .input("input.mov")
+        .output("output.webm")
+        .video_codec("libx264")
+        .audio_codec("libfdk_aac")
+        .validate_with(&caps)
+        .unwrap();
+    assert!(!report.is_ok());
+    assert!(report.has(IssueKind::CodecContainerMismatch));
+    assert!(report.has(IssueKind::MissingEncoder));
+    assert!(report
+        .errors()
+        .any(|issue| issue.message.contains("libfdk_aac")));
+
+    let pcm = TranscodeBuilder::new()
+        .input("input.mov")
+        .output("output.mp4")
+        .audio_codec("pcm_s16le")
+        .validate_with(&caps)
+        .unwrap();
+    assert!(pcm.has(IssueKind::CodecContainerMismatch));
+
+    let opus = TranscodeBuilder::new()
+        .input("input.mov")
+        .output("output.mp4")
+        .audio_codec("libopus")
+        .validate_with(&caps)
+        .unwrap();
+    assert!(opus.is_ok(), "opus in mp4 is fine on ffmpeg 6.1");
+}
+
+#[test]
+fn test_validate_with_flags_filters_and_copy_conflicts() {
+    let report = TranscodeBuilder::new()
+        .input("input.mov")
+        .output("output.mkv")
+        .video_codec("copy")
+        .video_bitrate(2000)
+        .add_video_filter(VideoFilter::Deinterlace)
+        .validate_with(&detected())
+        .unwrap();
+    assert!(report.has(IssueKind::CopyWithFilters));
+    assert!(report.has(IssueKind::MissingFilter));
+    let warning = report.warnings().next().unwrap();
+    assert_eq!(warning.kind, IssueKind::IgnoredWithCopy);
+    assert_eq!(warning.severity, Severity::Warning);
+    assert!(report.into_result().is_err());
+}
+
+#[test]
+fn test_validate_with_explicit_format() {
+    let caps = detected();
+    let report = TranscodeBuilder::new()
+        .input("input.mov")
+        .output("pipe:1")
+        .format("mpegts")
+        .validate_with(&caps)
+        .unwrap();
+    assert!(report.has(IssueKind::MissingMuxer));
+
+    let report = TranscodeBuilder::new()
+        .input("input.mov")
+        .output("output.bin")
+        .validate_with(&caps)
+        .unwrap();
+    assert!(report.is_ok());
+    assert!(report.has(IssueKind::UnknownContainer));
+}
+
+#[test]
+fn test_validate_with_checks_per_stream_codecs() {
+    let report = TranscodeBuilder::new()
+        .input("input.mov")
+        .output("output.webm")
+        .video_codec("libvpx-vp9")
+        .output_stream(OutputStream::audio(0).codec("libopus"))
+        .output_stream(OutputStream::audio(1).codec("ac3"))
+        .add_audio_filter(ffmpeg_light::AudioFilter::Custom("loudnorm".into()))
+        .output_stream(OutputStream::audio(2).codec("copy"))
+        .validate_with(&detected())
+        .unwrap();
+
+    let messages: Vec<_> = report
+        .errors()
+        .map(|issue| issue.message.as_str())
+        .collect();
+    assert!(messages.iter().any(|m| m.contains("`libvpx-vp9`")));
+    assert!(messages.iter().any(|m| m.contains("`ac3`")));
+    assert!(messages
+        .iter()
+        .any(|m| m.contains("while copying output stream a:2")));
+    assert!(!messages.iter().any(|m| m.contains("libopus")));
+}
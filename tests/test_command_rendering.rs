@@ -0,0 +1,608 @@
+//! Command rendering tests. Validates dry runs and shell quoting of generated commands.
+
+use std::time::Duration;
+
+use ffmpeg_light::command::FfmpegBinaryPaths;
+use ffmpeg_light::dash::DashBuilder;
+use ffmpeg_light::encoder::{
+    H264Profile, OpusApplication, OpusOptions, Tune, X264Options, X265Options,
+};
+use ffmpeg_light::hls::{HlsBuilder, HlsSegmentType};
+use ffmpeg_light::input::{Input, StreamMap};
+use ffmpeg_light::keyframe::ForceKeyframes;
+use ffmpeg_light::output::{Disposition, OutputStream};
+use ffmpeg_light::rendition::Rendition;
+use ffmpeg_light::runner::StdoutMode;
+use ffmpeg_light::thumbnail::{self, ThumbnailOptions};
+use ffmpeg_light::types::Time;
+use ffmpeg_light::{
+    probe, Error, FilterGraph, GraphFilter, RateControl, StreamType, TranscodeBuilder, VideoFilter,
+};
+
+fn binaries() -> FfmpegBinaryPaths {
+    FfmpegBinaryPaths::with_paths("/usr/bin/ffmpeg", "/usr/bin/ffprobe")
+}
+
+#[test]
+fn test_transcode_build_command_shell_string() {
+    let cmd = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("My Movies/input.mov")
+        .output("out.mp4")
+        .video_codec("libx264")
+        .add_video_filter(VideoFilter::Scale {
+            width: 1280,
+            height: 720,
+        })
+        .add_video_filter(VideoFilter::Custom("drawtext=text='Hello'".into()))
+        .build_command()
+        .unwrap();
+
+    assert_eq!(
+        cmd.to_shell_string(),
+        "/usr/bin/ffmpeg -y -i 'My Movies/input.mov' -c:v libx264 \
+         -vf 'scale=1280:720,drawtext=text='\\''Hello'\\''' out.mp4"
+    );
+}
+
+#[test]
+fn test_transcode_explicit_format_precedes_output() {
+    let cmd = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("in.mov")
+        .output("pipe:1")
+        .format("mpegts")
+        .build_command()
+        .unwrap();
+    assert_eq!(
+        cmd.to_shell_string(),
+        "/usr/bin/ffmpeg -y -i in.mov -f mpegts pipe:1"
+    );
+}
+
+#[test]
+fn test_transcode_build_command_requires_input() {
+    let result = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .output("out.mp4")
+        .build_command();
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_thumbnail_dry_run() {
+    let options = ThumbnailOptions::new(Time::from_seconds(90)).size(320, 180);
+    let line = thumbnail::dry_run_with_binaries(&binaries(), "in.mp4", "thumbs/frame", &options);
+    assert_eq!(
+        line,
+        "/usr/bin/ffmpeg -y -ss 00:01:30.000 -i in.mp4 -vframes 1 -vf scale=320:180 \
+         -f image2 thumbs/frame.png"
+    );
+    assert!(!std::path::Path::new("thumbs").exists());
+}
+
+#[test]
+fn test_probe_dry_run() {
+    let line = probe::dry_run_with_binaries(&binaries(), "clip (1).mkv");
+    assert_eq!(
+        line,
+        "/usr/bin/ffprobe -v level+error -print_format json -show_format -show_streams 'clip (1).mkv'"
+    );
+}
+
+#[test]
+fn test_transcode_muxes_external_audio_with_maps() {
+    let cmd = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("video.mp4")
+        .add_input(Input::new("commentary.m4a").offset(Time::from_seconds(2)))
+        .map(StreamMap::of_type(0, StreamType::Video))
+        .map(StreamMap::of_type(1, StreamType::Audio).nth(1))
+        .map(StreamMap::language(0, "eng").optional())
+        .map(StreamMap::of_type(0, StreamType::Subtitle).exclude())
+        .video_codec("copy")
+        .output("out.mkv")
+        .build_command()
+        .unwrap();
+
+    assert_eq!(
+        cmd.to_shell_string(),
+        "/usr/bin/ffmpeg -y -i video.mp4 -itsoffset 00:00:02.000 -i commentary.m4a \
+         -map 0:v -map 1:a:1 -map '0:m:language:eng?' -map -0:s -c:v copy out.mkv"
+    );
+}
+
+#[test]
+fn test_transcode_input_replaces_primary_only() {
+    let builder = TranscodeBuilder::new()
+        .input("first.mp4")
+        .add_input("extra.wav")
+        .input("second.mp4");
+    let paths: Vec<_> = builder.inputs().iter().map(Input::path).collect();
+    assert_eq!(
+        paths,
+        [
+            std::path::Path::new("second.mp4"),
+            std::path::Path::new("extra.wav")
+        ]
+    );
+}
+
+#[test]
+fn test_transcode_primary_input_precedes_added_inputs() {
+    let cmd = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .add_input("music.wav")
+        .input("video.mp4")
+        .output("out.mp4")
+        .build_command()
+        .unwrap();
+    assert_eq!(
+        cmd.to_shell_string(),
+        "/usr/bin/ffmpeg -y -i video.mp4 -i music.wav out.mp4"
+    );
+}
+
+#[test]
+fn test_transcode_rejects_map_to_missing_input() {
+    let err = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("video.mp4")
+        .map(StreamMap::input(1))
+        .output("out.mp4")
+        .build_command()
+        .unwrap_err();
+    assert!(err.to_string().contains("refers to input 1"));
+}
+
+#[test]
+fn test_transcode_per_stream_audio_configuration() {
+    let cmd = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .map(StreamMap::of_type(0, StreamType::Video))
+        .map(StreamMap::of_type(0, StreamType::Audio).nth(0))
+        .map(StreamMap::of_type(0, StreamType::Audio).nth(0))
+        .video_codec("copy")
+        .output_stream(
+            OutputStream::audio(0)
+                .codec("aac")
+                .bitrate(192)
+                .channels(2)
+                .language("eng")
+                .disposition(Disposition::Default),
+        )
+        .output_stream(
+            OutputStream::audio(1)
+                .codec("ac3")
+                .bitrate(448)
+                .channel_layout("5.1")
+                .sample_rate(48000)
+                .title("Surround")
+                .clear_disposition(),
+        )
+        .output("deliverable.mp4")
+        .build_command()
+        .unwrap();
+
+    assert_eq!(
+        cmd.to_shell_string(),
+        "/usr/bin/ffmpeg -y -i master.mov -map 0:v -map 0:a:0 -map 0:a:0 -c:v copy \
+         -c:a:0 aac -b:a:0 192k -ac:a:0 2 -disposition:a:0 default -metadata:s:a:0 language=eng \
+         -c:a:1 ac3 -b:a:1 448k -ch_layout:a:1 5.1 -ar:a:1 48000 -disposition:a:1 0 \
+         -metadata:s:a:1 title=Surround deliverable.mp4"
+    );
+}
+
+#[test]
+fn test_transcode_renders_rate_control_for_encoder() {
+    let cmd = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .video_codec("libvpx-vp9")
+        .audio_codec("libopus")
+        .video_rate_control(RateControl::CappedCrf {
+            crf: 31.0,
+            max_kbps: 3000,
+            buffer_kbps: 6000,
+        })
+        .audio_rate_control(RateControl::Abr(128))
+        .output("stream.webm")
+        .build_command()
+        .unwrap();
+
+    assert_eq!(
+        cmd.to_shell_string(),
+        "/usr/bin/ffmpeg -y -i master.mov -c:v libvpx-vp9 -c:a libopus \
+         -crf:v 31 -b:v 3000k -maxrate:v 3000k -bufsize:v 6000k -b:a 128k -vbr:a constrained stream.webm"
+    );
+}
+
+#[test]
+fn test_transcode_rate_control_conflicts() {
+    let err = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .video_codec("libx264")
+        .video_bitrate(2500)
+        .video_rate_control(RateControl::Crf(20.0))
+        .output("out.mp4")
+        .build_command()
+        .unwrap_err();
+    assert!(err.to_string().contains("mutually exclusive"));
+
+    let err = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .audio_rate_control(RateControl::Vbr(2.0))
+        .output("out.mp3")
+        .build_command()
+        .unwrap_err();
+    assert!(err.to_string().contains("requires an explicit audio codec"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_two_pass_build_commands() {
+    let build = || {
+        TranscodeBuilder::new()
+            .with_binaries(&binaries())
+            .input("master.mov")
+            .video_codec("libx264")
+            .audio_codec("aac")
+            .video_bitrate(2500)
+            .audio_bitrate(128)
+            .two_pass(true)
+            .output("out.mp4")
+    };
+    let commands = build().build_commands().unwrap();
+    let rendered: Vec<_> = commands.iter().map(|cmd| cmd.to_shell_string()).collect();
+    assert_eq!(
+        rendered,
+        [
+            "/usr/bin/ffmpeg -y -i master.mov -c:v libx264 -b:v 2500k \
+             -pass 1 -passlogfile ffmpeg2pass -an -f null /dev/null",
+            "/usr/bin/ffmpeg -y -i master.mov -c:v libx264 -c:a aac -b:v 2500k -b:a 128k \
+             -pass 2 -passlogfile ffmpeg2pass out.mp4",
+        ]
+    );
+    assert!(build().build_command().is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_x265_two_pass_uses_encoder_params() {
+    let commands = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .encoder_options(X265Options::new().refs(3))
+        .video_bitrate(2500)
+        .two_pass(true)
+        .output("out.mp4")
+        .build_commands()
+        .unwrap();
+    let rendered: Vec<_> = commands.iter().map(|cmd| cmd.to_shell_string()).collect();
+    assert_eq!(
+        rendered,
+        [
+            "/usr/bin/ffmpeg -y -i master.mov -c:v libx265 -b:v 2500k \
+             -x265-params:v ref=3:pass=1:stats=ffmpeg2pass-0.log -an -f null /dev/null",
+            "/usr/bin/ffmpeg -y -i master.mov -c:v libx265 -b:v 2500k \
+             -x265-params:v ref=3:pass=2:stats=ffmpeg2pass-0.log out.mp4",
+        ]
+    );
+}
+
+#[test]
+fn test_transcode_renders_typed_encoder_options() {
+    let cmd = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .encoder_options(
+            X264Options::new()
+                .profile(H264Profile::High)
+                .level("4.1")
+                .tune(Tune::Film)
+                .keyint(48)
+                .min_keyint(48)
+                .scenecut(0)
+                .param("nal-hrd", "vbr"),
+        )
+        .encoder_options(
+            OpusOptions::new()
+                .application(OpusApplication::Voip)
+                .frame_duration(20.0),
+        )
+        .output("out.mkv")
+        .build_command()
+        .unwrap();
+
+    assert_eq!(
+        cmd.to_shell_string(),
+        "/usr/bin/ffmpeg -y -i master.mov -c:v libx264 -c:a libopus -profile:v high -level:v 4.1 \
+         -tune:v film -g:v 48 -keyint_min:v 48 -sc_threshold:v 0 -x264-params:v nal-hrd=vbr \
+         -application:a voip -frame_duration:a 20 out.mkv"
+    );
+}
+
+#[test]
+fn test_transcode_merges_cbr_into_encoder_params() {
+    let cbr = RateControl::Cbr {
+        kbps: 3000,
+        buffer_kbps: 3000,
+    };
+    let cmd = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .encoder_options(X264Options::new().refs(4).param("aud", "1"))
+        .video_rate_control(cbr)
+        .output("out.ts")
+        .build_command()
+        .unwrap();
+    assert_eq!(
+        cmd.to_shell_string(),
+        "/usr/bin/ffmpeg -y -i master.mov -c:v libx264 -b:v 3000k -minrate:v 3000k \
+         -maxrate:v 3000k -bufsize:v 3000k -refs:v 4 -x264-params:v aud=1:nal-hrd=cbr out.ts"
+    );
+
+    let cmd = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .video_codec("libx265")
+        .video_rate_control(cbr)
+        .gop_frames(48)
+        .output("out.ts")
+        .build_command()
+        .unwrap();
+    let shell = cmd.to_shell_string();
+    assert_eq!(shell.matches("-x265-params").count(), 1);
+    assert!(shell.contains("-x265-params:v keyint=48:min-keyint=48:hrd=1:strict-cbr=1 out.ts"));
+}
+
+#[test]
+fn test_transcode_rejects_mismatched_encoder_options() {
+    let err = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .video_codec("libx265")
+        .encoder_options(X264Options::new().refs(4))
+        .output("out.mp4")
+        .build_command()
+        .unwrap_err();
+    assert!(err.to_string().contains("libx264 options do not apply"));
+
+    let err = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .encoder_options(X264Options::new().refs(20))
+        .output("out.mp4")
+        .build_command()
+        .unwrap_err();
+    assert!(err.to_string().contains("refs must be between 1 and 16"));
+}
+
+#[test]
+fn test_transcode_renders_gop_settings() {
+    let cmd = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .video_codec("libx264")
+        .frame_rate(25.0)
+        .gop_seconds(2.0)
+        .closed_gop(true)
+        .scenecut(false)
+        .force_key_frames(ForceKeyframes::At(vec![
+            Time::from_seconds(10),
+            Time::from_seconds(20),
+        ]))
+        .output("out.mp4")
+        .build_command()
+        .unwrap();
+    assert_eq!(
+        cmd.to_shell_string(),
+        "/usr/bin/ffmpeg -y -i master.mov -c:v libx264 -r 25 -g:v 50 -keyint_min:v 50 \
+         -sc_threshold:v 0 -flags:v +cgop -force_key_frames:v 00:00:10.000,00:00:20.000 out.mp4"
+    );
+
+    let cmd = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .video_codec("libx265")
+        .gop_frames(48)
+        .closed_gop(true)
+        .scenecut(false)
+        .output("out.mp4")
+        .build_command()
+        .unwrap();
+    assert_eq!(
+        cmd.to_shell_string(),
+        "/usr/bin/ffmpeg -y -i master.mov -c:v libx265 \
+         -x265-params:v keyint=48:min-keyint=48:scenecut=0:open-gop=0 out.mp4"
+    );
+}
+
+#[test]
+fn test_transcode_rejects_gop_and_encoder_keyint() {
+    let err = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .encoder_options(X265Options::new().keyint(96))
+        .gop_frames(48)
+        .output("out.mp4")
+        .build_command()
+        .unwrap_err();
+    assert!(err.to_string().contains("both set a keyframe interval"));
+
+    let err = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .encoder_options(X264Options::new().min_keyint(24))
+        .gop_seconds(2.0)
+        .output("out.mp4")
+        .build_command()
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidInput(_)));
+}
+
+#[test]
+fn test_transcode_clip_trims_audio_and_video() {
+    let cmd = TranscodeBuilder::new()
+        .with_binaries(&binaries())
+        .input("in.mov")
+        .output("clip.mp4")
+        .video_codec("libx264")
+        .size(1280, 720)
+        .clip(Time::from_seconds(90), Time::from_seconds_f64(102.5))
+        .build_command()
+        .unwrap();
+    assert_eq!(
+        cmd.to_shell_string(),
+        "/usr/bin/ffmpeg -y -ss 00:01:30.000 -i in.mov -c:v libx264 \
+         -vf trim=duration=12.5,setpts=PTS-STARTPTS,scale=1280:720 \
+         -af atrim=duration=12.5,asetpts=PTS-STARTPTS -t 00:00:12.500 clip.mp4"
+    );
+
+    let clip = |builder: TranscodeBuilder| {
+        builder
+            .with_binaries(&binaries())
+            .output("clip.mp4")
+            .clip(Time::from_seconds(10), Time::from_seconds(20))
+            .build_command()
+    };
+    assert!(clip(TranscodeBuilder::new().input("in.mov").video_codec("copy")).is_err());
+    assert!(clip(
+        TranscodeBuilder::new().add_input(Input::new("in.mov").seek(Time::from_seconds(5)))
+    )
+    .is_err());
+}
+
+#[test]
+fn test_transcode_filter_graph_overlays_second_input() {
+    let mut graph = FilterGraph::new();
+    let logo = graph.input(1, StreamType::Video);
+    let small = graph
+        .apply(
+            VideoFilter::Scale {
+                width: 160,
+                height: 90,
+            },
+            &[&logo],
+        )
+        .unwrap();
+    let video = graph
+        .apply(
+            GraphFilter::Overlay {
+                x: "W-w-16".into(),
+                y: "16".into(),
+            },
+            &[&graph.input(0, StreamType::Video), &small],
+        )
+        .unwrap();
+    graph.map(&video);
+    let build = |graph: FilterGraph| {
+        TranscodeBuilder::new()
+            .with_binaries(&binaries())
+            .input("talk.mp4")
+            .add_input("logo.png")
+            .output("branded.mp4")
+            .video_codec("libx264")
+            .audio_codec("copy")
+            .filter_graph(graph)
+            .map(StreamMap::of_type(0, StreamType::Audio))
+            .build_command()
+    };
+    assert_eq!(
+        build(graph.clone()).unwrap().to_shell_string(),
+        "/usr/bin/ffmpeg -y -i talk.mp4 -i logo.png \
+         -filter_complex '[1:v]scale=160:90[v0];[0:v][v0]overlay=W-w-16:16[v1]' \
+         -map '[v1]' -map 0:a -c:v libx264 -c:a copy branded.mp4"
+    );
+
+    // The logo pad is never consumed, and input 2 does not exist.
+    let mut dangling = graph.clone();
+    dangling.apply(VideoFilter::Deinterlace, &[&logo]).unwrap();
+    assert!(build(dangling).is_err());
+    let mut missing = graph;
+    let extra = missing
+        .apply(
+            VideoFilter::Deinterlace,
+            &[&missing.input(2, StreamType::Video)],
+        )
+        .unwrap();
+    missing.map(&extra);
+    assert!(build(missing).is_err());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_hls_single_pass_with_var_stream_map() {
+    let cmd = HlsBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .rendition(Rendition::new(1280, 720, 3000).max_bitrate(3300))
+        .rendition(Rendition::new(640, 360, 800).audio_bitrate(64))
+        .segment_type(HlsSegmentType::Fmp4)
+        .segment_duration(Duration::from_secs(4))
+        .output_dir("hls")
+        .build_command()
+        .unwrap();
+
+    assert_eq!(
+        cmd.to_shell_string(),
+        "/usr/bin/ffmpeg -y -i master.mov \
+         -map 0:v:0 -c:v:0 libx264 -b:v:0 3000k -maxrate:v:0 3300k -bufsize:v:0 6600k \
+         -filter:v:0 scale=1280:720 -map 0:a:0 -c:a:0 aac -b:a:0 128k \
+         -map 0:v:0 -c:v:1 libx264 -b:v:1 800k -filter:v:1 scale=640:360 \
+         -map 0:a:0 -c:a:1 aac -b:a:1 64k \
+         -force_key_frames:v 'expr:gte(t,n_forced*4)' -f hls -hls_time 4 -hls_playlist_type vod \
+         -hls_segment_type fmp4 -hls_fmp4_init_filename init.mp4 \
+         -hls_segment_filename hls/%v/segment_%05d.m4s -master_pl_name master.m3u8 \
+         -var_stream_map 'v:0,a:0,name:720p v:1,a:1,name:360p' hls/%v/index.m3u8"
+    );
+}
+
+#[test]
+fn test_dash_shares_audio_between_renditions() {
+    let cmd = DashBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .rendition(Rendition::new(1280, 720, 3000))
+        .rendition(Rendition::new(640, 360, 800))
+        .output("dash/manifest.mpd")
+        .build_command()
+        .unwrap();
+
+    assert_eq!(
+        cmd.to_shell_string(),
+        "/usr/bin/ffmpeg -y -i master.mov \
+         -map 0:v:0 -c:v:0 libx264 -b:v:0 3000k -filter:v:0 scale=1280:720 \
+         -map 0:v:0 -c:v:1 libx264 -b:v:1 800k -filter:v:1 scale=640:360 \
+         -map 0:a:0 -c:a:0 aac -b:a:0 128k -force_key_frames:v 'expr:gte(t,n_forced*4)' \
+         -f dash -seg_duration 4 -use_template 1 -use_timeline 1 \
+         -init_seg_name 'init-$RepresentationID$.$ext$' \
+         -media_seg_name 'chunk-$RepresentationID$-$Number%05d$.$ext$' \
+         -adaptation_sets 'id=0,streams=v id=1,streams=a' dash/manifest.mpd"
+    );
+
+    let args: Vec<String> = DashBuilder::new()
+        .with_binaries(&binaries())
+        .input("master.mov")
+        .rendition(Rendition::new(640, 360, 800).no_audio())
+        .single_file(true)
+        .output("single.mpd")
+        .build_command()
+        .unwrap()
+        .invocation(StdoutMode::Inherit)
+        .args_lossy();
+    assert!(args.ends_with(&[
+        "-use_template".into(),
+        "0".into(),
+        "-use_timeline".into(),
+        "1".into(),
+        "-single_file".into(),
+        "1".into(),
+        "-adaptation_sets".into(),
+        "id=0,streams=v".into(),
+        "single.mpd".into(),
+    ]));
+}
@@ -0,0 +1,79 @@
+//! Cuts against a real ffmpeg; run with `cargo test -- --ignored`.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use ffmpeg_light::command::{FfmpegBinaryPaths, FfmpegCommand};
+use ffmpeg_light::cut::{CutBuilder, CutMode};
+use ffmpeg_light::probe;
+use ffmpeg_light::types::Time;
+
+/// Ten seconds of H.264/AAC with a keyframe every two seconds.
+fn write_source(binaries: &FfmpegBinaryPaths, path: &Path) {
+    let mut cmd = FfmpegCommand::new(binaries.ffmpeg());
+    cmd.args(&[
+        "-y",
+        "-f",
+        "lavfi",
+        "-i",
+        "testsrc2=duration=10:size=320x240:rate=25",
+        "-f",
+        "lavfi",
+        "-i",
+        "sine=duration=10",
+        "-c:v",
+        "libx264",
+        "-g",
+        "50",
+        "-pix_fmt",
+        "yuv420p",
+        "-c:a",
+        "aac",
+    ]);
+    cmd.arg(path);
+    cmd.run().unwrap();
+}
+
+#[test]
+#[ignore = "needs ffmpeg and ffprobe with libx264 on PATH"]
+fn test_smart_cut_output_decodes_cleanly() {
+    let binaries = FfmpegBinaryPaths::auto().unwrap();
+    let dir = std::env::temp_dir().join(format!("ffmpeg-light-cut-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("source.mp4");
+    write_source(&binaries, &source);
+
+    for output in ["clip.mp4", "clip.mkv"] {
+        let output = dir.join(output);
+        let report = CutBuilder::new()
+            .with_binaries(&binaries)
+            .input(&source)
+            .output(&output)
+            .start(Time::from_seconds_f64(2.5))
+            .end(Time::from_seconds_f64(6.5))
+            .mode(CutMode::Smart)
+            .run()
+            .unwrap();
+        let reencoded: Vec<_> = report.segments.iter().map(|s| s.reencoded).collect();
+        assert_eq!(reencoded, [true, false, true]);
+
+        let decode = Command::new(binaries.ffmpeg())
+            .args(["-v", "error", "-xerror", "-i"])
+            .arg(&output)
+            .args(["-f", "null", "-"])
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&decode.stderr);
+        assert!(decode.status.success(), "{}", stderr);
+        assert!(stderr.trim().is_empty(), "decode errors: {}", stderr);
+
+        let duration = probe::probe(&output)
+            .unwrap()
+            .duration()
+            .unwrap()
+            .as_secs_f64();
+        assert!((duration - 4.0).abs() < 0.2, "duration {duration}");
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,6 +1,8 @@
 //! Error handling tests. Validates granular error types and recovery suggestions.
 
-use ffmpeg_light::{Error, Result};
+#![allow(clippy::unnecessary_literal_unwrap)]
+
+use ffmpeg_light::{Error, FailureKind, Result};
 
 #[test]
 fn test_ffmpeg_not_found_error_has_suggestion() {
@@ -91,3 +93,157 @@ fn test_result_type_err() {
     let err = result.unwrap_err();
     assert!(err.to_string().contains("test error"));
 }
+
+fn ffmpeg_failure(stderr: &str) -> Error {
+    Error::ProcessingError {
+        binary: "ffmpeg".to_string(),
+        exit_code: Some(1),
+        message: stderr.to_string(),
+    }
+}
+
+#[test]
+fn test_classifies_missing_input() {
+    let err = ffmpeg_failure("[error] missing.mp4: No such file or directory\n");
+    assert_eq!(
+        err.failure_kind(),
+        Some(FailureKind::InputNotFound {
+            path: Some("missing.mp4".to_string())
+        })
+    );
+    assert!(err.suggestion().unwrap().contains("missing.mp4"));
+    assert!(!err.is_retryable());
+    // Logs without `[level]` tags, e.g. from older runners, still classify.
+    assert_eq!(
+        ffmpeg_failure("missing.mp4: No such file or directory\n").failure_kind(),
+        err.failure_kind()
+    );
+
+    let modern = ffmpeg_failure(
+        "[in#0 @ 0x55d0] [error] Error opening input: No such file or directory\n\
+         [fatal] Error opening input file clips/a.mov.\n\
+         [fatal] Error opening input files: No such file or directory\n",
+    );
+    assert_eq!(
+        modern.failure_kind(),
+        Some(FailureKind::InputNotFound {
+            path: Some("clips/a.mov".to_string())
+        })
+    );
+
+    // A missing output directory or segment file is not a missing input.
+    let output = ffmpeg_failure(
+        "[out#0/mp4 @ 0x55d0] [error] Error opening output out/x.mp4: No such file or directory\n\
+         [fatal] Error opening output files: No such file or directory\n",
+    );
+    assert_eq!(output.failure_kind(), Some(FailureKind::Other));
+    // Older builds name only the path; the command's inputs tell them apart.
+    let bare = ffmpeg_failure("[error] out/x.mp4: No such file or directory\n");
+    assert_eq!(
+        bare.failure_kind_with_inputs(&["missing.mp4"]),
+        Some(FailureKind::Other)
+    );
+    assert_eq!(
+        bare.failure_kind_with_inputs(&["out/x.mp4"]),
+        Some(FailureKind::InputNotFound {
+            path: Some("out/x.mp4".to_string())
+        })
+    );
+}
+
+#[test]
+fn test_classifies_encoder_and_container_failures() {
+    let err = ffmpeg_failure("[fatal] Unknown encoder 'libx265'\n");
+    assert_eq!(
+        err.failure_kind(),
+        Some(FailureKind::UnknownEncoder {
+            name: Some("libx265".to_string())
+        })
+    );
+    assert!(err.suggestion().unwrap().contains("installed"));
+
+    let err = ffmpeg_failure(
+        "[mp4 @ 0x1] [error] Could not find tag for codec pcm_s16le in stream #1, codec not currently supported in container\n",
+    );
+    assert_eq!(
+        err.failure_kind(),
+        Some(FailureKind::UnsupportedCodecForContainer {
+            codec: Some("pcm_s16le".to_string())
+        })
+    );
+}
+
+#[test]
+fn test_classifies_filter_graph_errors() {
+    let err = ffmpeg_failure(
+        "[AVFilterGraph @ 0x1] [error] No such filter: 'sclae'\n[fatal] Error parsing filterchain\n",
+    );
+    assert_eq!(
+        err.failure_kind(),
+        Some(FailureKind::FilterGraph {
+            filter: Some("sclae".to_string())
+        })
+    );
+    assert!(err.suggestion().unwrap().contains("sclae"));
+
+    let err = ffmpeg_failure(
+        "[Parsed_scale_0 @ 0x1] [error] Invalid size 'big'\n[error] Error reinitializing filters!\n",
+    );
+    assert_eq!(
+        err.failure_kind(),
+        Some(FailureKind::FilterGraph {
+            filter: Some("scale".to_string())
+        })
+    );
+}
+
+#[test]
+fn test_classifies_io_conditions() {
+    let cases = [
+        ("[error] out.mp4: Permission denied\n", false),
+        (
+            "[error] av_interleaved_write_frame(): No space left on device\n",
+            false,
+        ),
+        ("[fatal] File 'out.mp4' already exists. Exiting.\n", false),
+        (
+            "[error] input.mkv: Invalid data found when processing input\n",
+            false,
+        ),
+        (
+            "[tcp @ 0x1] [error] Connection to tcp://cdn:443 failed: Connection refused\n",
+            true,
+        ),
+    ];
+    let kinds: Vec<_> = cases
+        .iter()
+        .map(|(stderr, retryable)| {
+            let err = ffmpeg_failure(stderr);
+            assert_eq!(err.is_retryable(), *retryable, "{stderr}");
+            err.failure_kind().unwrap()
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            FailureKind::PermissionDenied {
+                path: Some("out.mp4".to_string())
+            },
+            FailureKind::DiskFull,
+            FailureKind::OutputExists,
+            FailureKind::InvalidData,
+            FailureKind::Network,
+        ]
+    );
+}
+
+#[test]
+fn test_unclassified_failures_and_other_variants() {
+    assert_eq!(
+        ffmpeg_failure("something odd happened").failure_kind(),
+        Some(FailureKind::Other)
+    );
+    assert_eq!(Error::InvalidInput("x".into()).failure_kind(), None);
+    assert!(Error::TimeoutError("slow".into()).is_retryable());
+    assert!(!Error::InvalidInput("x".into()).is_retryable());
+}
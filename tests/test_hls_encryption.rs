@@ -0,0 +1,254 @@
+//! HLS encryption tests. A fake runner plays the part of ffmpeg's `hls` muxer,
+//! reading the key info file the way `-hls_flags periodic_rekey` does.
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::process::ExitStatusExt;
+use std::path::PathBuf;
+use std::process::{ExitStatus, Output};
+use std::sync::Arc;
+
+use ffmpeg_light::command::FfmpegBinaryPaths;
+use ffmpeg_light::hls::{HlsBuilder, HlsEncryption};
+use ffmpeg_light::rendition::Rendition;
+use ffmpeg_light::runner::{CommandRunner, Invocation};
+use ffmpeg_light::{Error, Result};
+
+/// Writes five segments and a playlist shaped like ffmpeg's, reporting the
+/// `out_time` values listed for each segment after writing it.
+#[derive(Debug)]
+struct FakeHlsMuxer {
+    reports: Vec<Vec<u64>>,
+}
+
+impl FakeHlsMuxer {
+    /// One report halfway through every 6-second segment.
+    fn steady() -> Self {
+        Self {
+            reports: (0..5).map(|segment| vec![segment * 6 + 3]).collect(),
+        }
+    }
+}
+
+impl CommandRunner for FakeHlsMuxer {
+    fn run(&self, invocation: &Invocation, on_line: &mut dyn FnMut(&str)) -> Result<Output> {
+        let args = invocation.args_lossy();
+        let value = |flag: &str| {
+            let at = args.iter().position(|arg| arg == flag)?;
+            args.get(at + 1).cloned()
+        };
+        let key_info = PathBuf::from(value("-hls_key_info_file").expect("key info file"));
+        let rekey = value("-hls_flags").as_deref() == Some("periodic_rekey");
+
+        let mut playlist = String::from(
+            "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:7\n\
+             #EXT-X-MEDIA-SEQUENCE:0\n#EXT-X-PLAYLIST-TYPE:VOD\n",
+        );
+        let mut current = String::new();
+        for segment in 0..5u64 {
+            if segment == 0 || rekey {
+                let info = fs::read_to_string(&key_info)?;
+                let lines: Vec<&str> = info.lines().collect();
+                // ffmpeg loads the key from the path on the second line.
+                assert_eq!(fs::read(lines[1])?.len(), 16);
+                assert_eq!(lines[2].len(), 32);
+                let tag = format!(
+                    "#EXT-X-KEY:METHOD=AES-128,URI=\"{}\",IV=0x{}",
+                    lines[0], lines[2]
+                );
+                if tag != current {
+                    playlist.push_str(&tag);
+                    playlist.push('\n');
+                    current = tag;
+                }
+            }
+            playlist.push_str(&format!("#EXTINF:6.006000,\nsegment_{segment:05}.ts\n"));
+            for seconds in &self.reports[segment as usize] {
+                on_line(&format!("out_time_us={}", seconds * 1_000_000));
+                on_line("progress=continue");
+            }
+        }
+        playlist.push_str("#EXT-X-ENDLIST\n");
+        let output = args.last().unwrap().replace("%v", "720p");
+        fs::write(output, playlist)?;
+        Ok(Output {
+            status: ExitStatus::from_raw(0),
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        })
+    }
+}
+
+fn output_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ffmpeg-light-{name}-{}", std::process::id()))
+}
+
+fn builder(dir: &PathBuf) -> HlsBuilder {
+    builder_with(dir, FakeHlsMuxer::steady())
+}
+
+fn builder_with(dir: &PathBuf, muxer: FakeHlsMuxer) -> HlsBuilder {
+    HlsBuilder::new()
+        .with_binaries(&FfmpegBinaryPaths::with_paths("ffmpeg", "ffprobe"))
+        .with_runner(Arc::new(muxer))
+        .input("input.mov")
+        .rendition(Rendition::new(1280, 720, 3000))
+        .output_dir(dir)
+}
+
+#[test]
+fn test_rotated_keys_appear_in_playlist() {
+    let dir = output_dir("hls-rotate");
+    let manifest = builder(&dir)
+        .encryption(HlsEncryption::aes128("https://keys.example.com/").rotate_every(2))
+        .run()
+        .unwrap();
+
+    let firsts: Vec<_> = manifest.keys.iter().map(|key| key.first_segment).collect();
+    assert_eq!(firsts, [0, 2, 4]);
+    let playlist = fs::read_to_string(&manifest.variants[0].playlist).unwrap();
+    let tags: Vec<_> = playlist
+        .lines()
+        .filter(|line| line.starts_with("#EXT-X-KEY"))
+        .collect();
+    let expected: Vec<_> = manifest
+        .keys
+        .iter()
+        .map(|key| {
+            format!(
+                "#EXT-X-KEY:METHOD=AES-128,URI=\"https://keys.example.com/key-{}.key\",IV={}",
+                key.first_segment / 2,
+                key.iv_hex()
+            )
+        })
+        .collect();
+    assert_eq!(tags, expected);
+    // Each tag sits right before the first segment it covers.
+    let lines: Vec<_> = playlist.lines().collect();
+    for (key, tag) in manifest.keys.iter().zip(&tags) {
+        let at = lines.iter().position(|line| line == tag).unwrap();
+        assert_eq!(
+            lines[at + 2],
+            format!("segment_{:05}.ts", key.first_segment)
+        );
+    }
+    for key in &manifest.keys {
+        assert_eq!(fs::read(&key.path).unwrap(), key.key);
+        assert_eq!(key.path.parent(), Some(dir.as_path()));
+    }
+    assert_ne!(manifest.keys[0].key, manifest.keys[1].key);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_keys_follow_playlist_with_bursty_progress() {
+    let dir = output_dir("hls-bursty");
+    // Nothing until a burst after segment 1, then a last report during segment 3.
+    let muxer = FakeHlsMuxer {
+        reports: vec![vec![], vec![1, 4, 8, 11], vec![], vec![21], vec![]],
+    };
+    let manifest = builder_with(&dir, muxer)
+        .encryption(HlsEncryption::aes128("keys/").rotate_every(2))
+        .run()
+        .unwrap();
+
+    let used: Vec<_> = manifest
+        .keys
+        .iter()
+        .map(|key| (key.uri.as_str(), key.first_segment))
+        .collect();
+    assert_eq!(
+        used,
+        [
+            ("keys/key-0.key", 0),
+            ("keys/key-1.key", 2),
+            ("keys/key-2.key", 4)
+        ]
+    );
+    // The last key was staged from the final report and must survive.
+    let playlist = fs::read_to_string(&manifest.variants[0].playlist).unwrap();
+    for key in &manifest.keys {
+        assert!(playlist.contains(&format!("URI=\"{}\"", key.uri)));
+        assert_eq!(fs::read(&key.path).unwrap(), key.key);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_skipped_keys_are_deleted() {
+    let dir = output_dir("hls-skipped");
+    // One burst stages key-1 and then key-2 before ffmpeg reads key-1, and the
+    // final report stages key-3 after the last segment.
+    let muxer = FakeHlsMuxer {
+        reports: vec![vec![3, 9, 15, 21], vec![], vec![], vec![], vec![30]],
+    };
+    let manifest = builder_with(&dir, muxer)
+        .encryption(HlsEncryption::aes128("keys/").rotate_every(2))
+        .run()
+        .unwrap();
+
+    let used: Vec<_> = manifest
+        .keys
+        .iter()
+        .map(|key| (key.uri.as_str(), key.first_segment))
+        .collect();
+    assert_eq!(used, [("keys/key-0.key", 0), ("keys/key-2.key", 1)]);
+    assert!(!dir.join("key-1.key").exists());
+    assert!(!dir.join("key-3.key").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_single_key_without_rotation() {
+    let dir = output_dir("hls-single-key");
+    let key_dir = dir.join("keys");
+    let manifest = builder(&dir)
+        .encryption(HlsEncryption::aes128("../keys/").key_dir(&key_dir))
+        .run()
+        .unwrap();
+
+    assert_eq!(manifest.keys.len(), 1);
+    assert_eq!(manifest.keys[0].path, key_dir.join("key-0.key"));
+    let playlist = fs::read_to_string(&manifest.variants[0].playlist).unwrap();
+    assert_eq!(playlist.matches("#EXT-X-KEY").count(), 1);
+    assert!(playlist.contains("URI=\"../keys/key-0.key\""));
+    let key = &manifest.keys[0];
+    assert_eq!(
+        fs::read_to_string(key_dir.join("hls.keyinfo")).unwrap(),
+        format!(
+            "../keys/key-0.key\n{}\n{}\n",
+            key.path.display(),
+            &key.iv_hex()[2..]
+        )
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_encryption_settings_are_validated() {
+    let dir = output_dir("hls-invalid");
+    let err = builder(&dir)
+        .encryption(HlsEncryption::aes128("keys/").rotate_every(0))
+        .build_command()
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidInput(_)));
+
+    let key_info = dir.join("keys/hls.keyinfo");
+    let cmd = builder(&dir)
+        .encryption(HlsEncryption::aes128("keys/").key_dir(dir.join("keys")))
+        .build_command()
+        .unwrap();
+    assert!(cmd.to_shell_string().contains(&format!(
+        "-hls_key_info_file {} -hls_segment_filename",
+        key_info.display()
+    )));
+    let cmd = builder(&dir)
+        .encryption(HlsEncryption::aes128("keys/").rotate_every(3))
+        .build_command()
+        .unwrap();
+    assert!(cmd.to_shell_string().contains(&format!(
+        "-hls_key_info_file {} -hls_flags periodic_rekey",
+        dir.join("hls.keyinfo").display()
+    )));
+    assert!(!key_info.exists());
+}
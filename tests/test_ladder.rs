@@ -0,0 +1,128 @@
+//! Bitrate ladder tests. Derives renditions from hand-built probe results.
+
+use ffmpeg_light::command::FfmpegBinaryPaths;
+use ffmpeg_light::ladder::{self, LadderPolicy, LadderRung};
+use ffmpeg_light::{
+    CodecType, FormatInfo, ProbeResult, StreamInfo, TranscodeBuilder, VideoStreamInfo,
+};
+
+fn source(width: u32, height: u32, fps: f64, bit_rate: Option<u64>) -> ProbeResult {
+    ProbeResult::new(
+        FormatInfo::new(None, None, None, None, None),
+        vec![StreamInfo::Video(VideoStreamInfo {
+            codec: CodecType::H264,
+            width: Some(width),
+            height: Some(height),
+            bit_rate,
+            frame_rate: Some(fps),
+            pix_fmt: None,
+            profile: None,
+            level: None,
+            sample_aspect_ratio: None,
+            time_base: None,
+        })],
+    )
+}
+
+fn summary(source: &ProbeResult, policy: &LadderPolicy) -> Vec<(String, u32, u32, u32)> {
+    ladder::generate(source, policy)
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r.name_ref().to_string(),
+                r.width(),
+                r.height(),
+                r.video_bitrate_value(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_apple_ladder_stops_at_source_size() {
+    let renditions =
+        ladder::generate(&source(1280, 720, 60.0, None), &LadderPolicy::AppleHls).unwrap();
+    let sizes: Vec<_> = renditions
+        .iter()
+        .map(|r| (r.name_ref(), r.width(), r.height()))
+        .collect();
+    assert_eq!(
+        sizes,
+        [
+            ("234p", 416, 234),
+            ("360p", 640, 360),
+            ("432p-730k", 768, 432),
+            ("432p-1100k", 768, 432),
+            ("540p", 960, 540),
+            ("720p-3000k", 1280, 720),
+            ("720p-4500k", 1280, 720),
+        ]
+    );
+    // Low rungs are limited to 30 fps; higher ones keep the source rate.
+    assert_eq!(renditions[0].frame_rate_value(), Some(30.0));
+    assert_eq!(renditions[4].frame_rate_value(), None);
+    assert_eq!(renditions[5].max_bitrate_value(), Some(3300));
+}
+
+#[test]
+fn test_ladder_keeps_aspect_ratio_with_even_dimensions() {
+    let policy = LadderPolicy::Custom(vec![LadderRung::new(480, 1200), LadderRung::new(240, 400)]);
+    // 2.39:1 scope and a 9:16 portrait source.
+    assert_eq!(
+        summary(&source(1920, 804, 24.0, None), &policy),
+        [
+            ("240p".to_string(), 574, 240, 400),
+            ("480p".to_string(), 1146, 480, 1200)
+        ]
+    );
+    assert_eq!(
+        summary(&source(1080, 1920, 30.0, None), &policy),
+        [
+            ("426p".to_string(), 240, 426, 400),
+            ("854p".to_string(), 480, 854, 1200)
+        ]
+    );
+}
+
+#[test]
+fn test_ladder_caps_bitrate_and_never_upscales() {
+    let policy = LadderPolicy::Custom(vec![
+        LadderRung::new(720, 3000),
+        LadderRung::new(1080, 6000),
+    ]);
+    // A 2 Mbps 720p source: the 1080p rung is dropped and 720p capped.
+    assert_eq!(
+        summary(&source(1280, 720, 25.0, Some(2_000_000)), &policy),
+        [("720p".to_string(), 1280, 720, 2000)]
+    );
+    // Smaller than every rung: one rendition at the source size.
+    assert_eq!(
+        summary(&source(321, 241, 25.0, None), &policy),
+        [("240p".to_string(), 320, 240, 3000)]
+    );
+    assert!(ladder::generate(
+        &ProbeResult::new(FormatInfo::new(None, None, None, None, None), Vec::new()),
+        &policy
+    )
+    .is_err());
+}
+
+#[test]
+fn test_rendition_configures_transcode_builder() {
+    let rendition = ladder::generate(&source(1920, 1080, 30.0, None), &LadderPolicy::AppleHls)
+        .unwrap()
+        .remove(1);
+    let cmd = TranscodeBuilder::new()
+        .with_binaries(&FfmpegBinaryPaths::with_paths("ffmpeg", "ffprobe"))
+        .input("master.mov")
+        .output("360p.mp4")
+        .rendition(&rendition)
+        .build_command()
+        .unwrap();
+    assert_eq!(
+        cmd.to_shell_string(),
+        "ffmpeg -y -i master.mov -c:v libx264 -c:a aac -b:v 365k -b:a 128k \
+         -vf scale=640:360 -maxrate:v 401k -bufsize:v 802k 360p.mp4"
+    );
+}
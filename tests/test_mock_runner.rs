@@ -0,0 +1,833 @@
+//! Mock runner tests. Exercises the high-level APIs without spawning ffmpeg.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::mock_locator;
+use ffmpeg_light::command::FfmpegCommand;
+use ffmpeg_light::concat::{ConcatBuilder, ConcatMethod, Transition};
+use ffmpeg_light::cut::{CutBuilder, CutMode};
+use ffmpeg_light::dash::DashBuilder;
+use ffmpeg_light::hls::HlsBuilder;
+use ffmpeg_light::logging::LogCapture;
+use ffmpeg_light::rendition::Rendition;
+use ffmpeg_light::runner::StdoutMode;
+use ffmpeg_light::testing::{MockResponse, MockRunner};
+use ffmpeg_light::thumbnail::{self, ThumbnailOptions};
+use ffmpeg_light::types::Time;
+use ffmpeg_light::{probe, Error, FailureKind, StreamType, TranscodeBuilder};
+
+const PROBE_JSON: &str = r#"{
+    "format": {"format_name": "mov,mp4,m4a,3gp,3g2,mj2", "duration": "10.000000"},
+    "streams": [
+        {"codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080, "avg_frame_rate": "30/1"},
+        {"codec_type": "audio", "codec_name": "aac", "channels": 2, "sample_rate": "48000"}
+    ]
+}"#;
+
+#[test]
+fn test_transcode_records_invocation() {
+    let mock = MockRunner::new();
+    TranscodeBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mov")
+        .output("output.mp4")
+        .video_codec("libx264")
+        .run()
+        .unwrap();
+
+    let invocation = mock.last_invocation().unwrap();
+    assert_eq!(invocation.binary.to_str(), Some("ffmpeg"));
+    assert_eq!(
+        invocation.args_lossy(),
+        vec![
+            "-loglevel",
+            "level+info",
+            "-y",
+            "-i",
+            "input.mov",
+            "-c:v",
+            "libx264",
+            "output.mp4"
+        ]
+    );
+}
+
+#[test]
+fn test_transcode_failure_maps_to_processing_error() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::failure(
+        1,
+        "input.mov: No such file or directory",
+    ));
+    let err = TranscodeBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mov")
+        .output("output.mp4")
+        .run()
+        .unwrap_err();
+
+    assert_eq!(
+        err.failure_kind(),
+        Some(FailureKind::InputNotFound {
+            path: Some("input.mov".to_string())
+        })
+    );
+    match err {
+        Error::ProcessingError {
+            exit_code, message, ..
+        } => {
+            assert_eq!(exit_code, Some(1));
+            assert!(message.contains("No such file"));
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
+
+#[test]
+fn test_probe_parses_canned_json() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(PROBE_JSON));
+    let result = probe::probe_with_locator(&mock_locator(&mock), "input.mp4").unwrap();
+
+    assert_eq!(result.duration().map(|d| d.as_secs()), Some(10));
+    assert_eq!(result.first_video().and_then(|v| v.width), Some(1920));
+    assert_eq!(mock.last_invocation().unwrap().stdout, StdoutMode::Capture);
+}
+
+#[test]
+fn test_thumbnail_uses_locator_runner() {
+    let mock = MockRunner::new();
+    let options = ThumbnailOptions::new(Time::from_seconds(3)).size(320, 180);
+    thumbnail::generate_with_locator(&mock_locator(&mock), "input.mp4", "thumb", &options).unwrap();
+
+    let args = mock.last_invocation().unwrap().args_lossy();
+    assert!(args.contains(&"scale=320:180".to_string()));
+    assert_eq!(args.last().map(String::as_str), Some("thumb.png"));
+}
+
+#[test]
+fn test_progress_lines_are_delivered_from_mock() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(PROBE_JSON));
+    mock.push_response(
+        MockResponse::success().stdout("out_time_us=5000000\nspeed=1x\nprogress=continue\n"),
+    );
+
+    let mut reports = Vec::new();
+    TranscodeBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mp4")
+        .output("output.mp4")
+        .run_with_progress(|progress| reports.push(progress.clone()))
+        .unwrap();
+
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].percent, Some(50.0));
+    let invocations = mock.invocations();
+    assert_eq!(invocations[0].binary.to_str(), Some("ffprobe"));
+    assert_eq!(invocations[1].args_lossy()[..2], ["-progress", "pipe:1"]);
+}
+
+#[test]
+fn test_mock_timeout_response() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::timeout());
+    let err = probe::probe_with_locator(&mock_locator(&mock), "input.mp4").unwrap_err();
+    assert!(matches!(err, Error::TimeoutError(_)));
+}
+
+#[test]
+fn test_probe_builder_passes_timeout() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(PROBE_JSON));
+    let result = probe::ProbeBuilder::new("input.mp4")
+        .with_locator(&mock_locator(&mock))
+        .timeout(Duration::from_secs(5))
+        .run()
+        .unwrap();
+    assert_eq!(result.duration(), Some(Duration::from_secs(10)));
+    let invocation = mock.last_invocation().unwrap();
+    assert_eq!(invocation.binary.to_str(), Some("ffprobe"));
+    assert_eq!(invocation.timeout, Some(Duration::from_secs(5)));
+}
+
+#[test]
+fn test_spawn_rejects_custom_runner() {
+    let mock = MockRunner::new();
+    let mut cmd = FfmpegCommand::new("ffmpeg");
+    cmd.runner(Arc::new(mock.clone())).arg("-version");
+    assert!(matches!(cmd.spawn(), Err(Error::Unsupported(_))));
+    assert!(mock.invocations().is_empty());
+}
+
+const FAILING_LOG: &str = "ffmpeg version 6.1.1\n\
+    [info] Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'input.mov':\n\
+    [warning] deprecated pixel format used\n\
+    [error] Unknown encoder 'libx265'\n";
+
+#[test]
+fn test_memory_log_capture_is_attached_to_errors() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::failure(1, FAILING_LOG));
+    let err = TranscodeBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mov")
+        .output("output.mp4")
+        .video_codec("libx265")
+        .log_capture(LogCapture::memory(2))
+        .run()
+        .unwrap_err();
+
+    assert_eq!(
+        err.log().unwrap(),
+        [
+            "[warning] deprecated pixel format used",
+            "[error] Unknown encoder 'libx265'",
+        ]
+    );
+    assert!(matches!(err.without_log(), Error::ProcessingError { .. }));
+    assert_eq!(
+        err.failure_kind(),
+        Some(FailureKind::UnknownEncoder {
+            name: Some("libx265".to_string())
+        })
+    );
+    assert!(err.to_string().contains("ffmpeg failed"));
+}
+
+#[test]
+fn test_file_log_capture_keeps_complete_log() {
+    let path = std::env::temp_dir().join(format!("ffmpeg-light-log-{}.txt", std::process::id()));
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stderr(FAILING_LOG));
+    TranscodeBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mov")
+        .output("output.mp4")
+        .log_capture(LogCapture::file(&path))
+        .run()
+        .unwrap();
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(log, FAILING_LOG);
+}
+
+#[test]
+fn test_two_pass_runs_both_passes_with_shared_passlog() {
+    let mock = MockRunner::new();
+    let mut reports = Vec::new();
+    TranscodeBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mov")
+        .output("output.mp4")
+        .video_codec("libx264")
+        .audio_codec("aac")
+        .video_bitrate(2500)
+        .two_pass(true)
+        .run_with_progress(|progress| reports.push(progress.clone()))
+        .unwrap();
+
+    let invocations = mock.invocations();
+    // ffprobe for the duration, then both passes.
+    assert_eq!(invocations.len(), 3);
+    let pass1 = invocations[1].args_lossy();
+    let pass2 = invocations[2].args_lossy();
+    let passlog = |args: &[String]| {
+        let at = args.iter().position(|arg| arg == "-passlogfile").unwrap();
+        args[at + 1].clone()
+    };
+    assert_eq!(passlog(&pass1), passlog(&pass2));
+    assert!(!std::path::Path::new(&passlog(&pass1))
+        .parent()
+        .unwrap()
+        .exists());
+    assert_eq!(
+        pass1[pass1.len() - 4..pass1.len() - 1],
+        ["-an", "-f", "null"]
+    );
+    assert!(!pass1.contains(&"aac".to_string()));
+    assert!(pass2.contains(&"aac".to_string()));
+    assert_eq!(pass2.last().map(String::as_str), Some("output.mp4"));
+}
+
+#[test]
+fn test_two_pass_file_log_capture_keeps_both_passes() {
+    let dir = std::env::temp_dir().join(format!("ffmpeg-light-passlogs-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stderr("analysing\n"))
+        .push_response(MockResponse::success().stderr("encoding\n"));
+    TranscodeBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mov")
+        .output("output.mp4")
+        .video_codec("libx264")
+        .video_bitrate(2500)
+        .two_pass(true)
+        .log_capture(LogCapture::file(dir.join("job.log")))
+        .run()
+        .unwrap();
+
+    let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+    let logs = (read("job-pass1.log"), read("job-pass2.log"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(logs, ("analysing\n".to_string(), "encoding\n".to_string()));
+}
+
+#[test]
+fn test_two_pass_failure_reports_pass() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success());
+    mock.push_response(MockResponse::failure(1, "No space left on device"));
+    let err = TranscodeBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mov")
+        .output("output.mp4")
+        .video_codec("libx264")
+        .video_bitrate(2500)
+        .two_pass(true)
+        .run()
+        .unwrap_err();
+
+    assert_eq!(err.pass(), Some(2));
+    assert!(err.to_string().starts_with("pass 2 failed"));
+    assert_eq!(err.failure_kind(), Some(FailureKind::DiskFull));
+    assert!(!err.is_retryable());
+}
+
+fn sized_output(name: &str, bytes: usize) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("ffmpeg-light-{name}-{}.mp4", std::process::id()));
+    std::fs::write(&path, vec![0u8; bytes]).unwrap();
+    path
+}
+
+fn video_bitrates(mock: &MockRunner) -> Vec<String> {
+    mock.invocations()
+        .iter()
+        .map(|invocation| invocation.args_lossy())
+        .filter_map(|args| {
+            let at = args.iter().position(|arg| arg == "-b:v")?;
+            Some(args[at + 1].clone())
+        })
+        .collect()
+}
+
+#[test]
+fn test_target_size_derives_bitrate_from_duration() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(PROBE_JSON));
+    let output = sized_output("target-ok", 900_000);
+    TranscodeBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mov")
+        .output(&output)
+        .video_codec("libx264")
+        .audio_codec("aac")
+        .target_size(1_000_000)
+        .run()
+        .unwrap();
+    std::fs::remove_file(&output).unwrap();
+
+    // 8000 kbit minus 2% overhead over 10s, minus 128 kbps of audio.
+    assert_eq!(video_bitrates(&mock), ["656k", "656k"]);
+    assert!(mock.invocations()[1]
+        .args_lossy()
+        .contains(&"-pass".to_string()));
+}
+
+#[test]
+fn test_target_size_reserves_copied_audio_bitrate() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(
+        r#"{
+            "format": {"duration": "10.000000"},
+            "streams": [
+                {"codec_type": "video", "codec_name": "h264"},
+                {"codec_type": "audio", "codec_name": "aac", "bit_rate": "320000"}
+            ]
+        }"#,
+    ));
+    let output = sized_output("target-copy", 900_000);
+    TranscodeBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mov")
+        .output(&output)
+        .video_codec("libx264")
+        .audio_codec("copy")
+        .target_size(1_000_000)
+        .run()
+        .unwrap();
+    std::fs::remove_file(&output).unwrap();
+
+    assert_eq!(video_bitrates(&mock), ["464k", "464k"]);
+}
+
+#[test]
+fn test_target_size_reserves_nothing_without_audio() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(
+        r#"{
+            "format": {"duration": "10.000000"},
+            "streams": [{"codec_type": "video", "codec_name": "h264"}]
+        }"#,
+    ));
+    let silent = sized_output("target-silent", 900_000);
+    TranscodeBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mov")
+        .output(&silent)
+        .video_codec("libx264")
+        .target_size(1_000_000)
+        .run()
+        .unwrap();
+    std::fs::remove_file(&silent).unwrap();
+    mock.push_response(MockResponse::success().stdout(PROBE_JSON));
+    let dropped = sized_output("target-an", 900_000);
+    TranscodeBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mov")
+        .output(&dropped)
+        .video_codec("libx264")
+        .audio_bitrate(192)
+        .extra_arg("-an")
+        .target_size(1_000_000)
+        .run()
+        .unwrap();
+    std::fs::remove_file(&dropped).unwrap();
+
+    assert_eq!(video_bitrates(&mock), ["784k", "784k", "784k", "784k"]);
+}
+
+#[test]
+fn test_target_size_retries_with_lower_bitrate() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(PROBE_JSON));
+    let output = sized_output("target-over", 2_000_000);
+    let err = TranscodeBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mov")
+        .output(&output)
+        .video_codec("libx264")
+        .audio_bitrate(96)
+        .target_size(1_000_000)
+        .target_size_attempts(2)
+        .run()
+        .unwrap_err();
+    std::fs::remove_file(&output).unwrap();
+
+    assert_eq!(video_bitrates(&mock), ["688k", "688k", "326k", "326k"]);
+    match err {
+        Error::TargetSizeExceeded {
+            target,
+            actual,
+            attempts,
+        } => assert_eq!((target, actual, attempts), (1_000_000, 2_000_000, 2)),
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
+
+#[test]
+fn test_keyframe_timestamps_read_packet_flags() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(
+        r#"{"packets": [
+            {"pts_time": "-0.021333", "flags": "K__"},
+            {"pts_time": "1.000000", "flags": "___"},
+            {"pts_time": "4.000000", "flags": "K__"},
+            {"dts_time": "2.000000", "flags": "K__"},
+            {"pts_time": "N/A", "flags": "K__"}
+        ]}"#,
+    ));
+    let keyframes =
+        probe::keyframe_timestamps_with_locator(&mock_locator(&mock), "input.mp4").unwrap();
+
+    assert_eq!(
+        keyframes,
+        [
+            Duration::ZERO,
+            Duration::from_secs(2),
+            Duration::from_secs(4)
+        ]
+    );
+    let args = mock.last_invocation().unwrap().args;
+    assert!(args.windows(2).any(|pair| pair[0] == "-show_entries"
+        && pair[1] == "packet=pts_time,dts_time,duration_time,flags"));
+    assert!(!args.iter().any(|arg| arg == "-skip_frame"));
+}
+
+#[test]
+fn test_hls_run_creates_variant_dirs_and_describes_output() {
+    let mock = MockRunner::new();
+    let dir = std::env::temp_dir().join(format!("ffmpeg-light-hls-{}", std::process::id()));
+    let manifest = HlsBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mov")
+        .rendition(Rendition::new(1280, 720, 3000))
+        .rendition(Rendition::new(640, 360, 800).name("mobile"))
+        .output_dir(&dir)
+        .run()
+        .unwrap();
+
+    assert_eq!(manifest.master_playlist, dir.join("master.m3u8"));
+    assert_eq!(manifest.variants[1].playlist, dir.join("mobile/index.m3u8"));
+    assert_eq!(manifest.variants[1].rendition.bandwidth(), 928_000);
+    assert_eq!(manifest.variants[1].init_segment, None);
+    assert!(dir.join("720p").is_dir() && dir.join("mobile").is_dir());
+    let args = mock.last_invocation().unwrap().args_lossy();
+    assert!(args.contains(&"v:0,a:0,name:720p v:1,a:1,name:mobile".to_string()));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+const DASH_MPD: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" profiles="urn:mpeg:dash:profile:isoff-live:2011" type="static" mediaPresentationDuration="PT10.0S" maxSegmentDuration="PT4.0S" minBufferTime="PT8.0S">
+	<ProgramInformation>
+	</ProgramInformation>
+	<Period id="0" start="PT0.0S">
+		<AdaptationSet id="0" contentType="video" startWithSAP="1" segmentAlignment="true" frameRate="25/1" maxWidth="1280" maxHeight="720">
+			<Representation id="0" mimeType="video/mp4" codecs="avc1.64001f" bandwidth="3000000" width="1280" height="720" sar="1:1">
+				<SegmentTemplate timescale="12800" initialization="init-$RepresentationID$.m4s" media="chunk-$RepresentationID$-$Number%05d$.m4s" startNumber="1">
+					<SegmentTimeline>
+						<S t="0" d="51200" r="1" />
+						<S d="25600" />
+					</SegmentTimeline>
+				</SegmentTemplate>
+			</Representation>
+			<Representation id="1" mimeType="video/mp4" codecs="avc1.64001e" bandwidth="800000" width="640" height="360" sar="1:1">
+				<SegmentTemplate timescale="12800" initialization="init-$RepresentationID$.m4s" media="chunk-$RepresentationID$-$Number%05d$.m4s" startNumber="1">
+					<SegmentTimeline>
+						<S t="0" d="51200" r="1" />
+						<S d="25600" />
+					</SegmentTimeline>
+				</SegmentTemplate>
+			</Representation>
+		</AdaptationSet>
+		<AdaptationSet id="1" contentType="audio" startWithSAP="1" segmentAlignment="true">
+			<Representation id="2" mimeType="audio/mp4" codecs="mp4a.40.2" bandwidth="128000" audioSamplingRate="48000">
+				<AudioChannelConfiguration schemeIdUri="urn:mpeg:dash:23003:3:audio_channel_configuration:2011" value="2" />
+				<SegmentTemplate timescale="48000" initialization="init-$RepresentationID$.m4s" media="chunk-$RepresentationID$-$Number%05d$.m4s" startNumber="1">
+					<SegmentTimeline>
+						<S t="0" d="192000" r="1" />
+						<S d="96000" />
+					</SegmentTimeline>
+				</SegmentTemplate>
+			</Representation>
+		</AdaptationSet>
+	</Period>
+</MPD>
+"#;
+
+#[test]
+fn test_dash_run_reads_back_generated_mpd() {
+    let mock = MockRunner::new();
+    let dir = std::env::temp_dir().join(format!("ffmpeg-light-dash-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mpd = dir.join("manifest.mpd");
+    // The mock does not run ffmpeg, so put its MPD in place beforehand.
+    std::fs::write(&mpd, DASH_MPD).unwrap();
+    let manifest = DashBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mov")
+        .rendition(Rendition::new(1280, 720, 3000))
+        .rendition(Rendition::new(640, 360, 800))
+        .output(&mpd)
+        .run()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(manifest.duration, Some(Duration::from_secs(10)));
+    assert_eq!(manifest.adaptation_sets.len(), 2);
+    let video: Vec<_> = manifest
+        .adaptation_sets_of(StreamType::Video)
+        .flat_map(|set| &set.representations)
+        .collect();
+    assert_eq!(video.len(), 2);
+    assert_eq!((video[1].width, video[1].height), (Some(640), Some(360)));
+    assert_eq!(video[1].frame_rate, Some(25.0));
+    assert_eq!(video[1].segments, Some(3));
+    assert_eq!(
+        video[1].media.as_deref(),
+        Some("chunk-$RepresentationID$-$Number%05d$.m4s")
+    );
+    let audio = manifest.representations().last().unwrap();
+    assert_eq!(
+        (audio.id.as_str(), audio.bandwidth, audio.sample_rate),
+        ("2", 128_000, Some(48_000))
+    );
+}
+
+const PACKETS_JSON: &str = r#"{
+    "packets": [
+        {"pts_time": "0.000000", "dts_time": "-0.066667", "duration_time": "0.033333", "flags": "K__"},
+        {"pts_time": "2.000000", "dts_time": "1.933333", "duration_time": "0.033333", "flags": "K__"},
+        {"pts_time": "2.066667", "dts_time": "1.966667", "duration_time": "0.033333", "flags": "___"},
+        {"pts_time": "4.000000", "dts_time": "3.933333", "duration_time": "0.033333", "flags": "K__"},
+        {"pts_time": "6.000000", "dts_time": "5.933333", "duration_time": "0.033333", "flags": "K__"},
+        {"pts_time": "N/A", "dts_time": "N/A", "duration_time": "0.033333", "flags": "_D_"}
+    ],
+    "format": {"duration": "8.000000"}
+}"#;
+
+#[test]
+fn test_cut_copies_from_keyframe_before_start() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(PACKETS_JSON));
+    let report = CutBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mp4")
+        .output("clip.mp4")
+        .start(Time::from_seconds_f64(2.5))
+        .end(Time::from_seconds(5))
+        .run()
+        .unwrap();
+
+    assert_eq!(report.start, Time::from_seconds(2));
+    assert_eq!(report.end, Time::from_seconds(6));
+    assert!(report.is_lossless());
+    let invocations = mock.invocations();
+    assert_eq!(invocations.len(), 2);
+    assert!(invocations[0]
+        .args_lossy()
+        .contains(&"packet=pts_time,dts_time,duration_time,flags".to_string()));
+    assert_eq!(
+        invocations[1].to_shell_string(),
+        "ffmpeg -loglevel level+info -y -ss 2.000000 -i input.mp4 -t 4.000000 -c copy \
+         -avoid_negative_ts make_zero clip.mp4"
+    );
+}
+
+/// MPEG-TS packets whose timestamps start at 1.4s.
+const OFFSET_PACKETS_JSON: &str = r#"{
+    "packets": [
+        {"pts_time": "1.400000", "duration_time": "0.040000", "flags": "K__"},
+        {"pts_time": "3.400000", "duration_time": "0.040000", "flags": "K__"},
+        {"pts_time": "3.440000", "duration_time": "0.040000", "flags": "___"},
+        {"pts_time": "5.400000", "duration_time": "0.040000", "flags": "K__"},
+        {"pts_time": "7.400000", "duration_time": "0.040000", "flags": "K__"},
+        {"pts_time": "9.360000", "duration_time": "0.040000", "flags": "___"}
+    ],
+    "format": {"start_time": "1.400000", "duration": "8.000000"}
+}"#;
+
+#[test]
+fn test_cut_counts_keyframes_from_the_stream_start() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(OFFSET_PACKETS_JSON))
+        .push_response(MockResponse::success())
+        .push_response(MockResponse::success().stdout(OFFSET_PACKETS_JSON));
+    let cut = |start: Time, end: Option<Time>| {
+        let mut builder = CutBuilder::new()
+            .with_locator(&mock_locator(&mock))
+            .input("input.ts")
+            .output("clip.ts")
+            .start(start);
+        if let Some(end) = end {
+            builder = builder.end(end);
+        }
+        builder.run().unwrap()
+    };
+
+    let report = cut(Time::from_seconds_f64(2.5), Some(Time::from_seconds(5)));
+    assert_eq!(report.start, Time::from_seconds(2));
+    assert_eq!(report.end, Time::from_seconds(6));
+    assert_eq!(
+        mock.last_invocation().unwrap().to_shell_string(),
+        "ffmpeg -loglevel level+info -y -ss 2.000000 -i input.ts -t 4.000000 -c copy \
+         -avoid_negative_ts make_zero clip.ts"
+    );
+
+    let report = cut(Time::from_seconds(7), None);
+    assert_eq!(report.start, Time::from_seconds(6));
+    assert_eq!(report.end, Time::from_seconds(8));
+}
+
+const SMART_CUT_PROBE_JSON: &str = r#"{
+    "format": {"format_name": "mov,mp4,m4a,3gp,3g2,mj2", "duration": "8.000000"},
+    "streams": [
+        {"codec_type": "video", "codec_name": "h264", "profile": "High", "level": 40,
+         "pix_fmt": "yuv420p", "sample_aspect_ratio": "1:1", "time_base": "1/15360"},
+        {"codec_type": "audio", "codec_name": "aac", "channels": 2, "sample_rate": "48000"}
+    ]
+}"#;
+
+#[test]
+fn test_smart_cut_reencodes_boundaries_and_concatenates() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(PACKETS_JSON))
+        .push_response(MockResponse::success().stdout(SMART_CUT_PROBE_JSON));
+    let report = CutBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.mp4")
+        .output("clip.mp4")
+        .start(Time::from_seconds_f64(2.5))
+        .end(Time::from_seconds(7))
+        .mode(CutMode::Smart)
+        .run()
+        .unwrap();
+
+    assert_eq!(
+        (report.start, report.end),
+        (Time::from_seconds_f64(2.5), Time::from_seconds(7))
+    );
+    let reencoded: Vec<_> = report.segments.iter().map(|s| s.reencoded).collect();
+    assert_eq!(reencoded, [true, false, true]);
+
+    let invocations = mock.invocations();
+    assert_eq!(invocations.len(), 6);
+    let head = &invocations[2].args_lossy()[2..];
+    assert_eq!(
+        &head[..8],
+        [
+            "-y",
+            "-ss",
+            "2.500000",
+            "-i",
+            "input.mp4",
+            "-t",
+            "1.500000",
+            "-c:v"
+        ]
+    );
+    assert_eq!(
+        &head[8..],
+        [
+            "libx264",
+            "-profile:v",
+            "high",
+            "-level:v",
+            "4.0",
+            "-pix_fmt:v",
+            "yuv420p",
+            "-vf",
+            "setsar=1/1",
+            "-c:a",
+            "aac",
+            "-ar",
+            "48000",
+            "-ac",
+            "2",
+            head.last().unwrap().as_str()
+        ]
+    );
+    assert!(head.last().unwrap().ends_with("part-0.ts"));
+    let copied = invocations[3].args_lossy();
+    assert!(copied.contains(&"copy".to_string()));
+    assert!(copied.last().unwrap().ends_with("part-1.ts"));
+    let concat = invocations[5].args_lossy();
+    assert_eq!(&concat[2..7], ["-y", "-f", "concat", "-safe", "0"]);
+    assert_eq!(
+        &concat[concat.len() - 5..],
+        ["-c", "copy", "-tag:v", "avc3", "clip.mp4"]
+    );
+}
+
+#[test]
+fn test_smart_cut_rejects_unmatched_codecs() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(PACKETS_JSON))
+        .push_response(MockResponse::success().stdout(
+            r#"{"streams": [{"codec_type": "video", "codec_name": "vp9", "profile": "Profile 0"}]}"#,
+        ));
+    let err = CutBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .input("input.webm")
+        .output("clip.webm")
+        .start(Time::from_seconds_f64(2.5))
+        .end(Time::from_seconds(7))
+        .mode(CutMode::Smart)
+        .run()
+        .unwrap_err();
+
+    assert!(matches!(err, Error::Unsupported(_)));
+    assert_eq!(mock.invocations().len(), 2);
+}
+
+#[test]
+fn test_clip_probes_output_duration() {
+    let clip_probe = |duration: &str| {
+        MockResponse::success().stdout(format!(
+            r#"{{"format": {{"duration": "{duration}"}}, "streams": []}}"#
+        ))
+    };
+    let clip = |mock: &MockRunner| {
+        TranscodeBuilder::new()
+            .with_locator(&mock_locator(mock))
+            .input("input.mov")
+            .output("clip.mp4")
+            .clip(Time::from_seconds(30), Time::from_seconds(40))
+            .run()
+    };
+
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success())
+        .push_response(clip_probe("10.023000"));
+    clip(&mock).unwrap();
+    let invocations = mock.invocations();
+    assert_eq!(invocations.len(), 2);
+    assert_eq!(invocations[1].args_lossy().last().unwrap(), "clip.mp4");
+
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success())
+        .push_response(clip_probe("12.000000"));
+    let err = clip(&mock).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::DurationMismatch { expected, actual }
+            if expected == Duration::from_secs(10) && actual == Duration::from_secs(12)
+    ));
+}
+
+#[test]
+fn test_concat_copies_identical_inputs_and_filters_others() {
+    let concat = |mock: &MockRunner| {
+        ConcatBuilder::new()
+            .with_locator(&mock_locator(mock))
+            .inputs(["intro.mp4", "main.mp4"])
+            .output("joined.mp4")
+            .run()
+            .unwrap()
+    };
+
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(PROBE_JSON))
+        .push_response(MockResponse::success().stdout(PROBE_JSON));
+    assert_eq!(concat(&mock), ConcatMethod::Demuxer);
+    let args = &mock.last_invocation().unwrap().args_lossy()[2..];
+    assert_eq!(&args[..6], ["-y", "-f", "concat", "-safe", "0", "-i"]);
+    assert!(args[6].ends_with("inputs.txt"));
+    assert_eq!(&args[7..], ["-c", "copy", "joined.mp4"]);
+
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(PROBE_JSON))
+        .push_response(MockResponse::success().stdout(PROBE_JSON.replace("1920", "1280")));
+    assert_eq!(concat(&mock), ConcatMethod::Filter);
+    let args = &mock.last_invocation().unwrap().args_lossy()[2..];
+    assert_eq!(&args[..5], ["-y", "-i", "intro.mp4", "-i", "main.mp4"]);
+    assert!(args[6].starts_with("[0:v:0]scale=1920:1080:force_original_aspect_ratio=decrease"));
+    assert!(args[6].ends_with("[v0][a0][v1][a1]concat=n=2:v=1:a=1[v][a]"));
+    assert_eq!(&args[7..], ["-map", "[v]", "-map", "[a]", "joined.mp4"]);
+}
+
+#[test]
+fn test_concat_transition_blends_identical_inputs() {
+    let mock = MockRunner::new();
+    mock.push_response(MockResponse::success().stdout(PROBE_JSON))
+        .push_response(MockResponse::success().stdout(PROBE_JSON));
+    let method = ConcatBuilder::new()
+        .with_locator(&mock_locator(&mock))
+        .inputs(["a.mp4", "b.mp4"])
+        .output("reel.mp4")
+        .transition(Transition::CircleOpen, Duration::from_secs(1))
+        .run()
+        .unwrap();
+
+    assert_eq!(method, ConcatMethod::Filter);
+    let graph = &mock.last_invocation().unwrap().args_lossy()[8];
+    assert!(graph.ends_with(
+        "[v0][v1]xfade=transition=circleopen:duration=1:offset=9[v];\
+         [a0][a1]acrossfade=d=1[a]"
+    ));
+}